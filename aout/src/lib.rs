@@ -1,6 +1,6 @@
 use std::io::{Read, Write};
 
-use common::misc::{IsEven, ReadU16, ToU16P, WriteU16};
+use common::misc::{IsEven, ToU16P, WriteU16};
 
 pub struct Symbol {}
 
@@ -29,54 +29,73 @@ impl Aout {
         }
     }
 
+    // Panics if the file is malformed; see try_read_from().
     pub fn read_from(reader: &mut impl Read) -> Aout {
-        let magic = reader.read_u16();
-        assert_eq!(magic, Self::MAGIC);
+        Self::try_read_from(reader).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_read_from(reader: &mut impl Read) -> Result<Aout, String> {
+        let mut read_u16 = || -> Result<u16, String> {
+            let mut buf = [0u8; 2];
+            reader
+                .read_exact(&mut buf)
+                .map_err(|e| format!("reading header: {e}"))?;
+            Ok(u16::from_le_bytes(buf))
+        };
+
+        let magic = read_u16()?;
+        if magic != Self::MAGIC {
+            return Err(format!("bad magic number {magic:06o}"));
+        }
 
         // All bytes
-        let text_size = reader.read_u16();
+        let text_size = read_u16()?;
 
-        let data_size = reader.read_u16();
-        assert_eq!(data_size, 0);
-        assert!(data_size.is_even());
+        let data_size = read_u16()?;
+        if data_size != 0 {
+            return Err("data sections aren't supported".into());
+        }
 
-        let bss_size = reader.read_u16();
-        assert_eq!(bss_size, 0);
-        assert!(bss_size.is_even());
+        let bss_size = read_u16()?;
+        if bss_size != 0 {
+            return Err("bss sections aren't supported".into());
+        }
 
-        let symbol_table_size = reader.read_u16();
-        assert_eq!(symbol_table_size, 0);
+        let symbol_table_size = read_u16()?;
+        if symbol_table_size != 0 {
+            return Err("symbol tables aren't supported".into());
+        }
 
-        let entry_point = reader.read_u16();
-        assert!(entry_point < text_size);
-        assert!(entry_point.is_even());
+        let entry_point = read_u16()?;
+        if entry_point >= text_size || !entry_point.is_even() {
+            return Err(format!("bad entry point {entry_point:06o}"));
+        }
 
         // Unused
-        let _ = reader.read_u16();
+        let _ = read_u16()?;
 
-        let _relocation_suppressed = reader.read_u16();
+        let _relocation_suppressed = read_u16()?;
 
         let mut text = vec![0u8; text_size as usize];
-        reader.read_exact(&mut text).unwrap();
-
-        let mut data = vec![0u8; data_size as usize];
-        reader.read_exact(&mut data).unwrap();
-
-        let mut bss = vec![0u8; bss_size as usize];
-        reader.read_exact(&mut bss).unwrap();
+        reader
+            .read_exact(&mut text)
+            .map_err(|e| format!("reading text: {e}"))?;
 
         // Make sure we read the whole file.
         let mut buf = [0u8; 1];
-        let res = reader.read_exact(&mut buf);
-        assert_eq!(res.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+        match reader.read_exact(&mut buf) {
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => (),
+            Err(e) => return Err(e.to_string()),
+            Ok(()) => return Err("trailing data after text".into()),
+        }
 
-        Aout {
+        Ok(Aout {
             text,
-            data,
-            bss,
+            data: vec![],
+            bss: vec![],
             entry_point,
             symbol_table: vec![],
-        }
+        })
     }
 
     pub fn write_to(&self, writer: &mut impl Write) {
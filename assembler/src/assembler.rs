@@ -246,6 +246,21 @@ impl Assembler {
                             ins.data = Expr::Atom(Atom::Val(val.val));
                         }
                     }
+                    Ins::Sob(ins) => self.eval_target(&mut ins.target),
+                    Ins::Mark(ins) => {
+                        if let Ok(val) = self.eval_expr(&ins.count) {
+                            assert_eq!(val.val & !MarkIns::COUNT_MASK, 0);
+                            ins.count = Expr::Atom(Atom::Val(val.val));
+                        }
+                    }
+                    Ins::FpSingleOperand(ins) => self.eval_operand(&mut ins.operand),
+                    Ins::Fp(ins) => self.eval_operand(&mut ins.operand),
+                    Ins::Spl(ins) => {
+                        if let Ok(val) = self.eval_expr(&ins.prio) {
+                            assert_eq!(val.val & !SplIns::PRIO_MASK, 0);
+                            ins.prio = Expr::Atom(Atom::Val(val.val));
+                        }
+                    }
                     _ => (),
                 }
             }
@@ -387,8 +402,9 @@ pub fn assemble_raw(prog: &str) -> Program {
 #[cfg(test)]
 mod tests {
     use super::assemble_raw;
+    use common::asm::Ins;
 
-    fn to_u16_vec(arr: &Vec<u8>) -> Vec<u16> {
+    fn to_u16_vec(arr: &[u8]) -> Vec<u16> {
         assert_eq!(arr.len() % 2, 0);
         let mut out = Vec::new();
         for chunk in arr.chunks(2) {
@@ -608,7 +624,7 @@ mod tests {
         let prog = r#"
             .byte 400
         "#;
-        assemble_raw(prog).text;
+        assemble_raw(prog);
     }
 
     #[test]
@@ -617,7 +633,7 @@ mod tests {
         let prog = r#"
             .word 200000
         "#;
-        assemble_raw(prog).text;
+        assemble_raw(prog);
     }
 
     #[test]
@@ -998,4 +1014,60 @@ mod tests {
         let bin = to_u16_vec(&assemble_raw(asm).text);
         assert_eq!(bin, expected);
     }
+
+    #[test]
+    fn sob() {
+        let prog = r#"
+            loop:
+                inc r0
+                sob r1, loop"#;
+        let bin = to_u16_vec(&assemble_raw(prog).text);
+        assert_eq!(bin[1], 0o077102);
+        assert_eq!(Ins::decode(&bin[1..]).unwrap().to_string(), "sob\t\tr1, . + 0o177776");
+    }
+
+    #[test]
+    fn mark_spl() {
+        let bin = to_u16_vec(&assemble_raw("mark 3\nspl 7").text);
+        assert_eq!(bin, [0o006403, 0o000237]);
+    }
+
+    #[test]
+    fn combined_cc() {
+        let bin = to_u16_vec(&assemble_raw("clc!clv\nscc\nccc\nsen!sez").text);
+        assert_eq!(bin, [0o000243, 0o000277, 0o000257, 0o000274]);
+        assert_eq!(Ins::decode(&bin[..1]).unwrap().to_string(), "clc!clv");
+        assert_eq!(Ins::decode(&bin[1..2]).unwrap().to_string(), "scc");
+    }
+
+    #[test]
+    fn fp() {
+        let prog = r#"
+            setd
+            addf ac4, ac1
+            ldd (r0)+, ac2
+            std ac3, -(sp)
+            stcfi ac0, r1
+            .flt2 1.5
+            .flt4 -0.375"#;
+        let bin = to_u16_vec(&assemble_raw(prog).text);
+        assert_eq!(
+            bin,
+            [
+                0o170011, 0o172104, 0o172620, 0o174346, 0o175401, 0o040300, 0, 0o137700, 0, 0,
+                0
+            ]
+        );
+        let dis = |i: usize| Ins::decode(&bin[i..]).unwrap().to_string();
+        assert_eq!(dis(1), "addf\t\tac4, ac1");
+        assert_eq!(dis(3), "stf\t\tac3, -(sp)");
+        assert_eq!(dis(4), "stcfi\t\tac0, r1");
+    }
+
+    #[test]
+    fn fis() {
+        let bin = to_u16_vec(&assemble_raw("fadd r1\nfsub r2\nfmul sp\nfdiv r0").text);
+        assert_eq!(bin, [0o075001, 0o075012, 0o075026, 0o075030]);
+        assert_eq!(Ins::decode(&bin[2..]).unwrap().to_string(), "fmul\t\tsp");
+    }
 }
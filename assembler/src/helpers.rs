@@ -1,9 +1,36 @@
-pub fn parse_int(s: &str, base: u32) -> u16 {
+use common::asm::{Atom, Expr};
+use common::float::{Float, Precision, to_words};
+use lalrpop_util::ParseError;
+
+use crate::grammar::ConditionParser;
+use crate::ir::Condition;
+
+pub fn parse_int<L, T>(s: &str, base: u32) -> Result<u16, ParseError<L, T, &'static str>> {
     let neg = s.starts_with('-');
     let offset = neg as usize;
-    let mut val = u16::from_str_radix(&s[offset..], base).unwrap();
+    let mut val = u16::from_str_radix(&s[offset..], base).map_err(|_| ParseError::User {
+        error: "number out of range",
+    })?;
     if neg {
         val = (!val).wrapping_add(1);
     }
-    val
+    Ok(val)
+}
+
+// A condition for a debugger, as in "r0 = @#1000 & 200".
+pub fn parse_condition(s: &str) -> Result<Condition, String> {
+    ConditionParser::new().parse(s).map_err(|e| e.to_string())
+}
+
+// Words of each float, as for .flt2 and .flt4.
+pub fn float_words(vals: &[f64], prec: Precision) -> Vec<Expr> {
+    let mut words = Vec::new();
+    for val in vals {
+        let float =
+            Float::from_f64(*val, prec).unwrap_or_else(|| panic!("Float {val} out of range"));
+        for word in to_words(float.pack(), prec) {
+            words.push(Expr::Atom(Atom::Val(word)));
+        }
+    }
+    words
 }
@@ -16,6 +16,25 @@ pub enum Cmd {
     LocDef(Expr),
 }
 
+// An operand in a debugger's condition.
+#[derive(Debug)]
+pub enum CondAtom {
+    Atom(Atom),
+    Reg(Reg),
+    Mem(Atom), // The word at the address.
+}
+
+// Operands joined by operators, evaluated left to right, with the first
+// added to 0.
+pub type CondExpr = Vec<(Op, CondAtom)>;
+
+// Holds if lhs equals rhs, or without rhs, if lhs is non-zero.
+#[derive(Debug)]
+pub struct Condition {
+    pub lhs: CondExpr,
+    pub rhs: Option<CondExpr>,
+}
+
 #[derive(Debug)]
 pub enum Label {
     Regular(String),
@@ -25,10 +44,7 @@ pub enum Label {
 
 impl Label {
     fn is_none(&self) -> bool {
-        match self {
-            Label::None => true,
-            _ => false,
-        }
+        matches!(self, Label::None)
     }
 }
 
@@ -6,7 +6,11 @@ pub mod ir;
 pub mod misc;
 mod tmp_f_tracker;
 
-lalrpop_mod!(grammar, "/grammar.rs");
+lalrpop_mod!(
+    #[allow(clippy::all)]
+    grammar,
+    "/grammar.rs"
+);
 
 pub use assembler::{assemble, assemble_raw, Program, SymbolType, SymbolValue};
 pub use misc::{Mode, Value};
@@ -43,7 +43,7 @@ impl AddrMode {
     pub const MASK: u16 = (1u16 << Self::NUM_BITS) - 1;
 }
 
-#[derive(Debug, Clone, IsVariant, Unwrap)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, IsVariant, Unwrap)]
 pub enum Op {
     Add,
     Sub,
@@ -259,6 +259,22 @@ impl Operand {
             Extra::Rel(expr) => expr.check_resolved(),
         }
     }
+
+    // FP11 floating point operands name accumulators, rather than registers,
+    // in mode 0.
+    pub fn fmt_fp(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            AddrMode::Gen => write!(f, "ac{}", self.reg.to_u16().unwrap()),
+            _ => fmt::Display::fmt(self, f),
+        }
+    }
+
+    pub fn fmt_fp_with_addr(&self, f: &mut fmt::Formatter, addr: u16) -> fmt::Result {
+        match self.mode {
+            AddrMode::Gen => self.fmt_fp(f),
+            _ => self.fmt_with_addr(f, addr),
+        }
+    }
 }
 
 impl fmt::Display for Operand {
@@ -752,6 +768,10 @@ pub enum SingleOperandOpcode {
     Asr,
     Asl,
 
+    Mfpi = 53, // Move from previous instruction space
+    Mtpi,      // Move to previous instruction space
+    Sxt,       // Sign extend
+
     ClrB = 552,
     ComB,
     IncB,
@@ -764,6 +784,11 @@ pub enum SingleOperandOpcode {
     RolB,
     AsrB,
     AslB,
+
+    Mtps = 564, // Move to PSW (byte)
+    Mfpd,       // Move from previous data space
+    Mtpd,       // Move to previous data space
+    Mfps,       // Move from PSW (byte)
 }
 
 impl fmt::Display for SingleOperandOpcode {
@@ -784,7 +809,8 @@ impl SingleOperandIns {
     }
 
     pub fn is_byte(&self) -> bool {
-        (self.op as u32) >= (SingleOperandOpcode::ClrB as u32)
+        let op = self.op as u32;
+        (SingleOperandOpcode::ClrB as u32..=SingleOperandOpcode::AslB as u32).contains(&op)
     }
 
     pub fn fmt_with_pc(&self, f: &mut fmt::Formatter, pc: u16) -> fmt::Result {
@@ -908,36 +934,35 @@ impl fmt::Display for EisIns {
 
 #[macro_export]
 macro_rules! cc_ins {
-    ($op:ident) => {
-        Ins::CC(CCIns { op: CCOpcode::$op })
+    ($op:ident, $flags:expr_2021) => {
+        Ins::CC(CCIns {
+            op: CCOpcode::$op,
+            flags: $flags,
+        })
     };
 }
+
+// The low four bits select the flags to clear or set, in the same bits as the
+// PSW. Clearing none is nop (0o240); setting none (0o260) is an alternate nop.
 #[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
 pub enum CCOpcode {
-    Nop = 0o240,
-    Clc = 0o241,
-    Clv = 0o242,
-    Clz = 0o244,
-    Cln = 0o250,
-    // 0o260 is an alternate nop
-    Sec = 0o261,
-    Sev = 0o262,
-    Sez = 0o264,
-    Sen = 0o270,
-}
-
-impl fmt::Display for CCOpcode {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", format!("{:?}", self).to_lowercase())
-    }
+    Clear = 0o12,
+    Set = 0o13,
 }
 
 #[derive(Debug, Clone)]
 pub struct CCIns {
     pub op: CCOpcode,
+    pub flags: u16,
 }
 
 impl CCIns {
+    pub const C: u16 = 0x1;
+    pub const V: u16 = 0x2;
+    pub const Z: u16 = 0x4;
+    pub const N: u16 = 0x8;
+    pub const FLAGS_MASK: u16 = 0xf;
+
     pub fn num_extra(&self) -> u16 {
         0
     }
@@ -947,12 +972,14 @@ impl CCIns {
     }
 
     pub fn emit(&self, out: &mut impl Write) {
-        out.write_u16(self.op.to_u16().unwrap());
+        assert_eq!(self.flags & !Self::FLAGS_MASK, 0);
+        out.write_u16((self.op.to_u16().unwrap() << Self::LOWER_BITS) | self.flags);
     }
 
     fn decode(input: &[u16]) -> Option<Ins> {
         let op = Self::decode_opcode(input[0])?;
-        Some(Ins::CC(Self { op }))
+        let flags = input[0] & Self::FLAGS_MASK;
+        Some(Ins::CC(Self { op, flags }))
     }
 
     pub fn check_resolved(&self) -> Result<(), ResolvedError> {
@@ -962,12 +989,39 @@ impl CCIns {
 }
 
 impl InstrVariant<CCOpcode> for CCIns {
-    const OPCODE_BITS: usize = 16;
+    const OPCODE_BITS: usize = 12;
 }
 
+// Combinations are written like clc!clv, as in MACRO-11.
 impl fmt::Display for CCIns {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.op)
+        let (prefix, all) = match self.op {
+            CCOpcode::Clear => ("cl", "ccc"),
+            CCOpcode::Set => ("se", "scc"),
+        };
+        match self.flags {
+            0 => write!(f, "nop"),
+            Self::FLAGS_MASK => write!(f, "{all}"),
+            flags => {
+                let names = [
+                    (Self::C, 'c'),
+                    (Self::V, 'v'),
+                    (Self::Z, 'z'),
+                    (Self::N, 'n'),
+                ];
+                let mut first = true;
+                for (bit, name) in names {
+                    if flags & bit != 0 {
+                        if !first {
+                            write!(f, "!")?;
+                        }
+                        write!(f, "{prefix}{name}")?;
+                        first = false;
+                    }
+                }
+                Ok(())
+            }
+        }
     }
 }
 
@@ -988,11 +1042,13 @@ pub enum MiscOpcode {
 
     // Actually part of traps, but 16 bits
     Rti = 2, // Return from interrupt
-    Iox,     // I/O executive routine, no defined mnemonic
+    Bpt,     // Breakpoint trap
     Iot,
 
     // Back to Misc proper
     Reset = 5,
+
+    Rtt = 6, // Return from interrupt, delaying any trace trap
 }
 
 impl fmt::Display for MiscOpcode {
@@ -1112,6 +1168,608 @@ impl fmt::Display for TrapIns {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+// Subtract one and branch (if not zero). The offset is 6 bits, and only backwards.
+
+#[macro_export]
+macro_rules! sob_ins {
+    ($reg:expr_2021, $tgt:expr_2021) => {
+        Ins::Sob(SobIns {
+            op: SobOpcode::Sob,
+            reg: $reg,
+            target: $tgt,
+        })
+    };
+}
+
+#[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum SobOpcode {
+    Sob = 0o77,
+}
+
+impl fmt::Display for SobOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SobIns {
+    pub op: SobOpcode,
+    pub reg: Reg,
+    pub target: Target, // Resolved to a (negative) branch offset, like BranchIns.
+}
+
+impl SobIns {
+    pub const OFFSET_NUM_BITS: usize = 6;
+    pub const OFFSET_MASK: u16 = (1u16 << Self::OFFSET_NUM_BITS) - 1;
+
+    pub fn num_extra(&self) -> u16 {
+        0
+    }
+
+    pub fn fmt_with_pc(&self, f: &mut fmt::Formatter, pc: u16) -> fmt::Result {
+        write!(f, "{}\t\t{}, ", self.op, self.reg)?;
+        self.target.fmt_with_pc(f, pc)
+    }
+
+    pub fn emit(&self, out: &mut impl Write) {
+        let offset = -(self.target.unwrap_offset() as i8 as i16);
+        assert!(
+            (0..=Self::OFFSET_MASK as i16).contains(&offset),
+            "SOB target out of range"
+        );
+        let bin = (self.op.to_u16().unwrap() << Self::LOWER_BITS)
+            | (self.reg.to_u16().unwrap() << Self::OFFSET_NUM_BITS)
+            | offset as u16;
+        out.write_u16(bin);
+    }
+
+    fn decode(input: &[u16]) -> Option<Ins> {
+        let op = Self::decode_opcode(input[0])?;
+        let reg = Reg::from_u16((input[0] >> Self::OFFSET_NUM_BITS) & Reg::MASK).unwrap();
+        let offset = -((input[0] & Self::OFFSET_MASK) as i16);
+        let target = Target::Offset(offset as i8 as u8);
+        Some(Ins::Sob(Self { op, reg, target }))
+    }
+
+    pub fn check_resolved(&self) -> Result<(), ResolvedError> {
+        self.target.check_resolved()
+    }
+}
+
+impl InstrVariant<SobOpcode> for SobIns {
+    const OPCODE_BITS: usize = 7;
+}
+
+impl fmt::Display for SobIns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\t\t{}, {}", self.op, self.reg, self.target)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// Mark the stack for a subroutine return, popping a count of words.
+
+#[macro_export]
+macro_rules! mark_ins {
+    ($count:expr_2021) => {
+        Ins::Mark(MarkIns {
+            op: MarkOpcode::Mark,
+            count: $count,
+        })
+    };
+}
+
+#[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum MarkOpcode {
+    Mark = 0o64,
+}
+
+impl fmt::Display for MarkOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MarkIns {
+    pub op: MarkOpcode,
+    pub count: Expr,
+}
+
+impl MarkIns {
+    pub const COUNT_MASK: u16 = (1u16 << Self::LOWER_BITS) - 1;
+
+    pub fn num_extra(&self) -> u16 {
+        0
+    }
+
+    pub fn fmt_with_pc(&self, f: &mut fmt::Formatter, _pc: u16) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+
+    pub fn emit(&self, out: &mut impl Write) {
+        let count = self.count.unwrap_val();
+        assert_eq!(count & !Self::COUNT_MASK, 0);
+        out.write_u16((self.op.to_u16().unwrap() << Self::LOWER_BITS) | count);
+    }
+
+    fn decode(input: &[u16]) -> Option<Ins> {
+        let op = Self::decode_opcode(input[0])?;
+        let count = input[0] & Self::COUNT_MASK;
+        Some(Ins::Mark(Self {
+            op,
+            count: Expr::Atom(Atom::Val(count)),
+        }))
+    }
+
+    pub fn check_resolved(&self) -> Result<(), ResolvedError> {
+        self.count.check_resolved()
+    }
+}
+
+impl InstrVariant<MarkOpcode> for MarkIns {
+    const OPCODE_BITS: usize = 10;
+}
+
+impl fmt::Display for MarkIns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\t\t{:#o}", self.op, self.count.unwrap_val())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// Set priority level.
+
+#[macro_export]
+macro_rules! spl_ins {
+    ($prio:expr_2021) => {
+        Ins::Spl(SplIns {
+            op: SplOpcode::Spl,
+            prio: $prio,
+        })
+    };
+}
+
+#[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum SplOpcode {
+    Spl = 0o23,
+}
+
+impl fmt::Display for SplOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SplIns {
+    pub op: SplOpcode,
+    pub prio: Expr,
+}
+
+impl SplIns {
+    pub const PRIO_MASK: u16 = (1u16 << Self::LOWER_BITS) - 1;
+
+    pub fn num_extra(&self) -> u16 {
+        0
+    }
+
+    pub fn fmt_with_pc(&self, f: &mut fmt::Formatter, _pc: u16) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+
+    pub fn emit(&self, out: &mut impl Write) {
+        let prio = self.prio.unwrap_val();
+        assert_eq!(prio & !Self::PRIO_MASK, 0);
+        out.write_u16((self.op.to_u16().unwrap() << Self::LOWER_BITS) | prio);
+    }
+
+    fn decode(input: &[u16]) -> Option<Ins> {
+        let op = Self::decode_opcode(input[0])?;
+        let prio = input[0] & Self::PRIO_MASK;
+        Some(Ins::Spl(Self {
+            op,
+            prio: Expr::Atom(Atom::Val(prio)),
+        }))
+    }
+
+    pub fn check_resolved(&self) -> Result<(), ResolvedError> {
+        self.prio.check_resolved()
+    }
+}
+
+impl InstrVariant<SplOpcode> for SplIns {
+    const OPCODE_BITS: usize = 13;
+}
+
+impl fmt::Display for SplIns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\t\t{:#o}", self.op, self.prio.unwrap_val())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// FP11 floating point instructions. In the floating point source or
+// destination (fsrc/fdst) of these, mode 0 names accumulators 0-5, rather than
+// general registers, and the AC field can only name accumulators 0-3. Whether
+// operations are single or double precision (e.g., ADDF vs ADDD), and whether
+// integers are a word or long, comes from the FPS register, not the opcode.
+
+#[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum Ac {
+    Ac0 = 0,
+    Ac1,
+    Ac2,
+    Ac3,
+    Ac4,
+    Ac5,
+}
+
+impl Ac {
+    pub const NUM_ACS: usize = 6;
+
+    // Bits of the AC field in FpIns.
+    pub const FIELD_BITS: usize = 2;
+    pub const FIELD_MASK: u16 = (1u16 << Self::FIELD_BITS) - 1;
+}
+
+impl fmt::Display for Ac {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[macro_export]
+macro_rules! fp_misc_ins {
+    ($op:ident) => {
+        Ins::FpMisc(FpMiscIns {
+            op: FpMiscOpcode::$op,
+        })
+    };
+}
+
+#[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum FpMiscOpcode {
+    Cfcc = 0o170000, // Copy floating condition codes
+    Setf,            // Single precision
+    Seti,            // Word integers
+    Setd = 0o170011, // Double precision
+    Setl,            // Long integers
+}
+
+impl fmt::Display for FpMiscOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FpMiscIns {
+    pub op: FpMiscOpcode,
+}
+
+impl FpMiscIns {
+    pub fn num_extra(&self) -> u16 {
+        0
+    }
+
+    pub fn fmt_with_pc(&self, f: &mut fmt::Formatter, _pc: u16) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+
+    pub fn emit(&self, out: &mut impl Write) {
+        out.write_u16(self.op.to_u16().unwrap());
+    }
+
+    fn decode(input: &[u16]) -> Option<Ins> {
+        let op = Self::decode_opcode(input[0])?;
+        Some(Ins::FpMisc(Self { op }))
+    }
+
+    pub fn check_resolved(&self) -> Result<(), ResolvedError> {
+        // No expr to check
+        Ok(())
+    }
+}
+
+impl InstrVariant<FpMiscOpcode> for FpMiscIns {
+    const OPCODE_BITS: usize = 16;
+}
+
+impl fmt::Display for FpMiscIns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.op)
+    }
+}
+
+#[macro_export]
+macro_rules! fp_single_operand_ins {
+    ($op:ident, $operand:expr_2021) => {
+        Ins::FpSingleOperand(FpSingleOperandIns {
+            op: FpSingleOperandOpcode::$op,
+            operand: $operand,
+        })
+    };
+}
+
+#[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum FpSingleOperandOpcode {
+    Ldfps = 0o1701, // Load FPS, src
+    Stfps,          // Store FPS, dst
+    Stst,           // Store FEC and FEA, dst
+
+    // fdst
+    Clrf,
+    Tstf,
+    Absf,
+    Negf,
+}
+
+impl fmt::Display for FpSingleOperandOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FpSingleOperandIns {
+    pub op: FpSingleOperandOpcode,
+    pub operand: Operand,
+}
+
+impl FpSingleOperandIns {
+    pub fn num_extra(&self) -> u16 {
+        self.operand.num_extra()
+    }
+
+    // Whether the operand is a float, rather than an integer.
+    pub fn is_float(&self) -> bool {
+        use FpSingleOperandOpcode::*;
+        matches!(self.op, Clrf | Tstf | Absf | Negf)
+    }
+
+    pub fn fmt_with_pc(&self, f: &mut fmt::Formatter, pc: u16) -> fmt::Result {
+        write!(f, "{}\t\t", self.op)?;
+        if self.is_float() {
+            self.operand.fmt_fp_with_addr(f, pc + 2)
+        } else {
+            self.operand.fmt_with_addr(f, pc + 2)
+        }
+    }
+
+    pub fn emit(&self, out: &mut impl Write) {
+        let bin = (self.op.to_u16().unwrap() << Self::LOWER_BITS) | self.operand.encode();
+        out.write_u16(bin);
+        if self.operand.has_extra() {
+            out.write_u16(self.operand.extra.unwrap_val());
+        }
+    }
+
+    fn decode(input: &[u16]) -> Option<Ins> {
+        let op = Self::decode_opcode(input[0])?;
+        let operand = Operand::decode(input[0], input, 1);
+        Some(Ins::FpSingleOperand(Self { op, operand }))
+    }
+
+    pub fn check_resolved(&self) -> Result<(), ResolvedError> {
+        self.operand.check_resolved()
+    }
+}
+
+impl InstrVariant<FpSingleOperandOpcode> for FpSingleOperandIns {
+    const OPCODE_BITS: usize = 10;
+}
+
+impl fmt::Display for FpSingleOperandIns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\t\t", self.op)?;
+        if self.is_float() {
+            self.operand.fmt_fp(f)
+        } else {
+            write!(f, "{}", self.operand)
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! fp_ins {
+    ($op:ident, $ac:expr_2021, $operand:expr_2021) => {
+        Ins::Fp(FpIns {
+            op: FpOpcode::$op,
+            ac: $ac,
+            operand: $operand,
+        })
+    };
+}
+
+// The top 8 bits; e.g., MULF is 171(AC)(FSRC).
+#[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum FpOpcode {
+    Mulf = 0o362, // fsrc, ac
+    Modf,         // fsrc, ac
+    Addf,         // fsrc, ac
+    Ldf,          // fsrc, ac
+    Subf,         // fsrc, ac
+    Cmpf,         // fsrc, ac
+    Stf,          // ac, fdst
+    Divf,         // fsrc, ac
+    Stexp,        // ac, dst
+    Stcfi,        // ac, dst. Also STCFL, STCDI and STCDL.
+    Stcfd,        // ac, fdst. Also STCDF.
+    Ldexp,        // src, ac
+    Ldcif,        // src, ac. Also LDCID, LDCLF and LDCLD.
+    Ldcdf,        // fsrc, ac. Also LDCFD.
+}
+
+impl fmt::Display for FpOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FpIns {
+    pub op: FpOpcode,
+    pub ac: Ac,
+    pub operand: Operand, // src or dst, depending on the op.
+}
+
+impl FpIns {
+    pub fn num_extra(&self) -> u16 {
+        self.operand.num_extra()
+    }
+
+    // Whether the AC is the source, and the operand the destination.
+    pub fn is_store(&self) -> bool {
+        use FpOpcode::*;
+        matches!(self.op, Stf | Stexp | Stcfi | Stcfd)
+    }
+
+    // Whether the operand is a float, rather than an integer.
+    pub fn is_float(&self) -> bool {
+        use FpOpcode::*;
+        !matches!(self.op, Stexp | Stcfi | Ldexp | Ldcif)
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter, addr: Option<u16>) -> fmt::Result {
+        match (self.is_float(), addr) {
+            (true, Some(addr)) => self.operand.fmt_fp_with_addr(f, addr),
+            (true, None) => self.operand.fmt_fp(f),
+            (false, Some(addr)) => self.operand.fmt_with_addr(f, addr),
+            (false, None) => write!(f, "{}", self.operand),
+        }
+    }
+
+    fn fmt_with_addr(&self, f: &mut fmt::Formatter, addr: Option<u16>) -> fmt::Result {
+        write!(f, "{}\t\t", self.op)?;
+        if self.is_store() {
+            write!(f, "{}, ", self.ac)?;
+            self.fmt_operand(f, addr)
+        } else {
+            self.fmt_operand(f, addr)?;
+            write!(f, ", {}", self.ac)
+        }
+    }
+
+    pub fn fmt_with_pc(&self, f: &mut fmt::Formatter, pc: u16) -> fmt::Result {
+        self.fmt_with_addr(f, Some(pc + 2))
+    }
+
+    pub fn emit(&self, out: &mut impl Write) {
+        let ac = self.ac.to_u16().unwrap();
+        assert_eq!(
+            ac & !Ac::FIELD_MASK,
+            0,
+            "Only ac0-ac3 can be in the AC field"
+        );
+        let bin = (self.op.to_u16().unwrap() << Self::LOWER_BITS)
+            | (ac << Operand::NUM_BITS)
+            | self.operand.encode();
+        out.write_u16(bin);
+        if self.operand.has_extra() {
+            out.write_u16(self.operand.extra.unwrap_val());
+        }
+    }
+
+    fn decode(input: &[u16]) -> Option<Ins> {
+        let op = Self::decode_opcode(input[0])?;
+        let ac = Ac::from_u16((input[0] >> Operand::NUM_BITS) & Ac::FIELD_MASK).unwrap();
+        let operand = Operand::decode(input[0], input, 1);
+        Some(Ins::Fp(Self { op, ac, operand }))
+    }
+
+    pub fn check_resolved(&self) -> Result<(), ResolvedError> {
+        self.operand.check_resolved()
+    }
+}
+
+impl InstrVariant<FpOpcode> for FpIns {
+    const OPCODE_BITS: usize = 8;
+}
+
+impl fmt::Display for FpIns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_with_addr(f, None)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+// KE11-F Floating Instruction Set instructions. The register points to a
+// stack of two word floats, in the FP11's single precision format.
+
+#[macro_export]
+macro_rules! fis_ins {
+    ($op:ident, $reg:expr_2021) => {
+        Ins::Fis(FisIns {
+            op: FisOpcode::$op,
+            reg: $reg,
+        })
+    };
+}
+
+#[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum FisOpcode {
+    Fadd = 0o7500,
+    Fsub,
+    Fmul,
+    Fdiv,
+}
+
+impl fmt::Display for FisOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FisIns {
+    pub op: FisOpcode,
+    pub reg: Reg,
+}
+
+impl FisIns {
+    pub fn num_extra(&self) -> u16 {
+        0
+    }
+
+    pub fn fmt_with_pc(&self, f: &mut fmt::Formatter, _pc: u16) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+
+    pub fn emit(&self, out: &mut impl Write) {
+        let bin = (self.op.to_u16().unwrap() << Self::LOWER_BITS) | self.reg.to_u16().unwrap();
+        out.write_u16(bin);
+    }
+
+    fn decode(input: &[u16]) -> Option<Ins> {
+        let op = Self::decode_opcode(input[0])?;
+        let reg = Reg::from_u16(input[0] & Reg::MASK).unwrap();
+        Some(Ins::Fis(Self { op, reg }))
+    }
+
+    pub fn check_resolved(&self) -> Result<(), ResolvedError> {
+        // No expr to check
+        Ok(())
+    }
+}
+
+impl InstrVariant<FisOpcode> for FisIns {
+    const OPCODE_BITS: usize = 13;
+}
+
+impl fmt::Display for FisIns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}\t\t{}", self.op, self.reg)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 #[derive(Debug, Clone)]
 pub enum Ins {
     DoubleOperand(DoubleOperandIns),
@@ -1124,6 +1782,13 @@ pub enum Ins {
     CC(CCIns),
     Misc(MiscIns),
     Trap(TrapIns),
+    Sob(SobIns),
+    Mark(MarkIns),
+    Spl(SplIns),
+    FpMisc(FpMiscIns),
+    FpSingleOperand(FpSingleOperandIns),
+    Fp(FpIns),
+    Fis(FisIns),
 }
 
 impl Ins {
@@ -1139,6 +1804,13 @@ impl Ins {
             Ins::CC(x) => x,
             Ins::Misc(x) => x,
             Ins::Trap(x) => x,
+            Ins::Sob(x) => x,
+            Ins::Mark(x) => x,
+            Ins::Spl(x) => x,
+            Ins::FpMisc(x) => x,
+            Ins::FpSingleOperand(x) => x,
+            Ins::Fp(x) => x,
+            Ins::Fis(x) => x,
         } {
             pub fn num_extra(&self) -> u16;
             pub fn fmt_with_pc(&self, f: &mut fmt::Formatter, pc: u16) -> fmt::Result;
@@ -1151,7 +1823,7 @@ impl Ins {
         WORD_SIZE + WORD_SIZE * self.num_extra()
     }
 
-    pub fn display_with_pc(&self, pc: u16) -> InsWithPc {
+    pub fn display_with_pc(&self, pc: u16) -> InsWithPc<'_> {
         InsWithPc(self, pc)
     }
 
@@ -1166,6 +1838,13 @@ impl Ins {
         CCIns::decode,
         MiscIns::decode,
         TrapIns::decode,
+        SobIns::decode,
+        MarkIns::decode,
+        SplIns::decode,
+        FpMiscIns::decode,
+        FpSingleOperandIns::decode,
+        FpIns::decode,
+        FisIns::decode,
     ];
 
     pub fn decode(input: &[u16]) -> Option<Ins> {
@@ -1193,6 +1872,13 @@ impl fmt::Display for Ins {
             Ins::CC(ins) => write!(f, "{ins}"),
             Ins::Misc(ins) => write!(f, "{ins}"),
             Ins::Trap(ins) => write!(f, "{ins}"),
+            Ins::Sob(ins) => write!(f, "{ins}"),
+            Ins::Mark(ins) => write!(f, "{ins}"),
+            Ins::Spl(ins) => write!(f, "{ins}"),
+            Ins::FpMisc(ins) => write!(f, "{ins}"),
+            Ins::FpSingleOperand(ins) => write!(f, "{ins}"),
+            Ins::Fp(ins) => write!(f, "{ins}"),
+            Ins::Fis(ins) => write!(f, "{ins}"),
         }
     }
 }
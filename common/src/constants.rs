@@ -8,3 +8,4 @@ pub const DATA_END: u16 = MMIO_START; // Exclusive
 pub const MMIO_START: u16 = 0o160000;
 pub const MEM_HIGH: u16 = 0o177777;
 pub const MEM_END: u32 = 0o1000000; // Exclusive, note type
+pub const MMIO_PHYS_START: u32 = 0o760000; // Physical address of the I/O page.
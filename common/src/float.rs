@@ -0,0 +1,163 @@
+// PDP-11 floating point format, as used by the FP11 and the assembler.
+//
+// A number is a sign bit, an 8 bit exponent in excess 128, and a fraction
+// that's normalized to 0.1xxx in binary, with the leading 1 not stored. Single
+// precision (F) is two words, with a 24 bit fraction; double precision (D)
+// is four words, with a 56 bit fraction. The first word holds the sign,
+// exponent, and most significant fraction bits, and is at the lowest address.
+//
+// An exponent of 0 is zero, whatever the fraction. If the sign is also set,
+// it's an "undefined variable", which the FP11 can trap on. There are no
+// infinities or NaNs.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    Single,
+    Double,
+}
+
+impl Precision {
+    pub fn words(self) -> u16 {
+        match self {
+            Precision::Single => 2,
+            Precision::Double => 4,
+        }
+    }
+
+    // Fraction bits, including the hidden bit.
+    pub fn frac_bits(self) -> u32 {
+        match self {
+            Precision::Single => 24,
+            Precision::Double => 56,
+        }
+    }
+}
+
+// An unpacked float. Raw values are held in a u64, with the first word in the
+// top bits; single precision values only use the top two words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Float {
+    pub neg: bool,
+    pub exp: i32,  // Biased; may be out of range before packing.
+    pub frac: u64, // 56 bits, including the hidden bit at FRAC_TOP.
+}
+
+impl Float {
+    pub const EXP_BIAS: i32 = 128;
+    pub const EXP_MAX: i32 = 0o377;
+
+    const FRAC_BITS: u32 = 56;
+    pub const FRAC_TOP: u64 = 1 << (Self::FRAC_BITS - 1);
+    const FRAC_MASK: u64 = Self::FRAC_TOP - 1; // Stored fraction bits.
+    const EXP_SHIFT: u32 = 55;
+    const SIGN_SHIFT: u32 = 63;
+
+    pub const ZERO: Float = Float {
+        neg: false,
+        exp: 0,
+        frac: 0,
+    };
+
+    pub fn is_zero(&self) -> bool {
+        self.exp == 0
+    }
+
+    pub fn unpack(raw: u64) -> Float {
+        let exp = ((raw >> Self::EXP_SHIFT) & Self::EXP_MAX as u64) as i32;
+        Float {
+            neg: (raw >> Self::SIGN_SHIFT) != 0,
+            exp,
+            frac: if exp == 0 {
+                0
+            } else {
+                Self::FRAC_TOP | (raw & Self::FRAC_MASK)
+            },
+        }
+    }
+
+    // The exponent is truncated to 8 bits, so out of range values wrap, as
+    // the FP11 stores them when overflow or underflow traps are enabled.
+    pub fn pack(&self) -> u64 {
+        ((self.neg as u64) << Self::SIGN_SHIFT)
+            | (((self.exp & Self::EXP_MAX) as u64) << Self::EXP_SHIFT)
+            | (self.frac & Self::FRAC_MASK)
+    }
+
+    pub fn in_range(&self) -> bool {
+        !self.overflowed() && !self.underflowed()
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.frac != 0 && self.exp > Self::EXP_MAX
+    }
+
+    pub fn underflowed(&self) -> bool {
+        self.frac != 0 && self.exp < 1
+    }
+
+    // Round mant * 2^scale to the given precision. Rounding adds half of the
+    // last place to the magnitude, unless truncating. The exponent isn't range
+    // checked, and a zero mant gives an exact zero.
+    pub fn round(neg: bool, mant: u128, scale: i32, prec: Precision, truncate: bool) -> Float {
+        if mant == 0 {
+            return Self::ZERO;
+        }
+
+        let bits = prec.frac_bits() as i32;
+        let top = (u128::BITS - 1 - mant.leading_zeros()) as i32;
+        let mut exp = top + scale + 1 + Self::EXP_BIAS;
+
+        let shift = top + 1 - bits;
+        let mut frac = if shift > 0 {
+            let mut mant = mant;
+            if !truncate {
+                mant += 1 << (shift - 1);
+            }
+            mant >> shift
+        } else {
+            mant << -shift
+        };
+        if frac >> bits != 0 {
+            frac >>= 1;
+            exp += 1;
+        }
+
+        Float {
+            neg,
+            exp,
+            frac: (frac as u64) << (Self::FRAC_BITS as i32 - bits),
+        }
+    }
+
+    // Only fails if the value is out of range.
+    pub fn from_f64(val: f64, prec: Precision) -> Option<Float> {
+        let bits = val.to_bits();
+        let neg = (bits >> 63) != 0;
+        let exp = ((bits >> 52) & 0x7ff) as i32;
+        let frac = bits & ((1 << 52) - 1);
+        if exp == 0x7ff {
+            return None;
+        }
+        let ret = if exp == 0 {
+            Self::round(neg, frac as u128, -1074, prec, false)
+        } else {
+            Self::round(neg, (frac | (1 << 52)) as u128, exp - 1075, prec, false)
+        };
+        ret.in_range().then_some(ret)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        if self.is_zero() {
+            return 0.0;
+        }
+        let mag = self.frac as f64 * 2f64.powi(self.exp - Self::EXP_BIAS - Self::FRAC_BITS as i32);
+        if self.neg { -mag } else { mag }
+    }
+}
+
+// Split a raw value into the words stored in memory, first word first.
+pub fn to_words(raw: u64, prec: Precision) -> Vec<u16> {
+    (0..prec.words())
+        .map(|i| (raw >> (48 - 16 * i)) as u16)
+        .collect()
+}
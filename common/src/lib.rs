@@ -1,5 +1,4 @@
-#![feature(pointer_is_aligned_to)]
-
 pub mod asm;
 pub mod constants;
+pub mod float;
 pub mod misc;
@@ -41,7 +41,12 @@ impl fmt::Display for Disassembled {
 }
 
 pub fn disassemble(bin: &[u8]) -> Vec<Disassembled> {
-    assert!(bin.len() <= (u16::MAX as usize) + 1);
+    disassemble_at(bin, 0)
+}
+
+// bin is loaded at start.
+pub fn disassemble_at(bin: &[u8], start: u16) -> Vec<Disassembled> {
+    assert!(start as usize + bin.len() <= (u16::MAX as usize) + 1);
     let mut out = vec![];
     let mut addr: usize = 0;
     while addr < bin.len() {
@@ -49,7 +54,7 @@ pub fn disassemble(bin: &[u8]) -> Vec<Disassembled> {
         let ins = Ins::decode(cast_slice(&bin[addr..upper]));
         let size = ins.as_ref().map(|x| x.size()).unwrap_or(WORD_SIZE) as usize;
         out.push(Disassembled {
-            addr: addr as u16,
+            addr: start + addr as u16,
             repr: cast_slice(&bin[addr..addr + size]).into(),
             ins,
         });
@@ -51,10 +51,10 @@ fn main() {
 
     let mut prev: Option<Disassembled> = None;
     for dis in disassembly {
-        if let Some(p) = &prev {
-            if (p.addr as usize) + p.repr.len() * (WORD_SIZE as usize) != (dis.addr as usize) {
-                println!("...");
-            }
+        if let Some(p) = &prev
+            && (p.addr as usize) + p.repr.len() * (WORD_SIZE as usize) != (dis.addr as usize)
+        {
+            println!("...");
         }
         println!("{}", dis);
         prev = Some(dis);
@@ -1,15 +1,144 @@
 use aout::Aout;
-use emu_lib::Emulator;
+use common::asm::Reg;
+use emu_lib::gdb;
 use emu_lib::io::clock::Clock;
+use emu_lib::io::front_panel::{self, FrontPanel, FrontPanelHandle};
 use emu_lib::io::teletype::Teletype;
+use emu_lib::monitor::{self, Outcome};
+use emu_lib::{CpuModel, Emulator, Throttle, TraceFormat, Tracer};
 
 use clap::Parser;
+use crossterm::style::Print;
+use crossterm::{cursor, execute, terminal};
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
 
 /// PDP-11 Emulator
 #[derive(Parser)]
 struct Args {
     /// Binary to execute
-    bin: String,
+    #[arg(required_unless_present_any = ["load_snapshot", "monitor"])]
+    bin: Option<String>,
+
+    /// CPU model: generic, 11/20, 11/40, 11/45 or 11/70
+    #[arg(long, default_value_t)]
+    cpu: CpuModel,
+
+    /// Speed: unlimited, model (the CPU model's real speed) or instructions per second, up to 1e9
+    #[arg(long, default_value_t)]
+    speed: Throttle,
+
+    /// Line clock frequency, in Hz
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u32).range(1..))]
+    clock_hz: u32,
+
+    /// Resume from a snapshot, instead of starting a binary
+    #[arg(long, conflicts_with = "bin")]
+    load_snapshot: Option<String>,
+
+    /// Save a snapshot when the emulator stops
+    #[arg(long)]
+    save_snapshot: Option<String>,
+
+    /// Write a trace of every instruction executed to a file
+    #[arg(long)]
+    trace: Option<String>,
+
+    /// Trace layout: native, or simh (as SIMH's show history)
+    #[arg(long, default_value_t, requires = "trace")]
+    trace_format: TraceFormat,
+
+    /// Wait for GDB to connect on this localhost port, and run under its control
+    #[arg(long)]
+    gdb: Option<u16>,
+
+    /// Start at a SIMH style sim> prompt, and return to it whenever the guest stops
+    #[arg(long, conflicts_with = "gdb")]
+    monitor: bool,
+
+    /// With --monitor, Ctrl and this key drops from the running guest into the monitor
+    #[arg(long, default_value_t = 'e')]
+    escape: char,
+
+    /// Front panel switch register, in octal
+    #[arg(long, default_value = "0", value_parser = parse_octal)]
+    switches: u16,
+}
+
+fn parse_octal(s: &str) -> Result<u16, String> {
+    u16::from_str_radix(s, 8).map_err(|e| format!("{s} isn't an octal word: {e}"))
+}
+
+// Keeps the display register on the bottom line of the terminal, as a row of
+// lights, once the guest has put something there, whenever on is set.
+fn show_lights(panel: Arc<FrontPanelHandle>, on: Arc<AtomicBool>) {
+    let mut shown = 0;
+    loop {
+        thread::sleep(Duration::from_millis(50));
+        if !on.load(Ordering::Acquire) {
+            // Shown again when turned back on.
+            shown = 0;
+            continue;
+        }
+        let display = panel.display();
+        if display == shown {
+            continue;
+        }
+        shown = display;
+        let Ok((_, rows)) = terminal::size() else {
+            continue;
+        };
+        let status = format!("{} {display:06o}", front_panel::lights(display));
+        let _ = execute!(
+            io::stdout().lock(),
+            cursor::SavePosition,
+            cursor::MoveTo(0, rows.saturating_sub(1)),
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            Print(status),
+            cursor::RestorePosition,
+        );
+    }
+}
+
+// Commands are read with the terminal as normal, and the guest run with it
+// raw, as the teletype expects. The lights are only shown while it runs.
+fn run_monitor(emu: &mut Emulator, lights: &AtomicBool) {
+    let stdin = io::stdin();
+    loop {
+        terminal::disable_raw_mode().unwrap();
+        print!("sim> ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.read_line(&mut line).unwrap() == 0 {
+            return;
+        }
+
+        terminal::enable_raw_mode().unwrap();
+        lights.store(true, Ordering::Release);
+        let res = monitor::exec(emu, &line);
+        lights.store(false, Ordering::Release);
+        terminal::disable_raw_mode().unwrap();
+        match res {
+            Ok(Outcome::Output(out)) if out.is_empty() => (),
+            Ok(Outcome::Output(out)) => println!("{out}"),
+            Ok(Outcome::Quit) => return,
+            Err(e) => println!("{e}"),
+        }
+    }
+}
+
+fn serve_gdb(emu: &mut Emulator, port: u16) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    eprintln!("Waiting for GDB on port {port}");
+    let (stream, _) = listener.accept()?;
+    gdb::serve(emu, stream)
 }
 
 fn main() {
@@ -17,12 +146,65 @@ fn main() {
 
     let args = Args::parse();
 
-    let mut emu = Emulator::new();
-    emu.set_mmio_handler(Teletype::default());
-    emu.set_mmio_handler(Clock::default());
+    let mut emu = Emulator::with_model(args.cpu);
+    emu.set_throttle(args.speed);
+    let tty = if args.monitor {
+        Teletype::new_to_stdout_with_escape(emu.control(), args.escape)
+    } else {
+        Teletype::new_to_stdout(emu.control())
+    };
+    emu.set_mmio_handler(tty);
+    emu.set_mmio_handler(Clock::with_hz(args.clock_hz));
+    let panel = FrontPanel::new(args.switches);
+    let handle = panel.get_handle();
+    emu.set_mmio_handler(panel);
+    // Only over a guest on a terminal, and not under GDB, which drives it from
+    // elsewhere.
+    let lights = Arc::new(AtomicBool::new(!args.monitor));
+    if io::stdout().is_terminal() && args.gdb.is_none() {
+        let lights = lights.clone();
+        thread::spawn(move || show_lights(handle, lights));
+    }
+    if let Some(path) = &args.trace {
+        let file = BufWriter::new(File::create(path).unwrap());
+        emu.set_tracer(Tracer::new(file, args.trace_format));
+    }
+
+    if let Some(path) = args.load_snapshot {
+        let mut file = BufReader::new(File::open(&path).unwrap());
+        if let Err(e) = emu.load_snapshot(&mut file) {
+            eprintln!("Can't load snapshot {path}: {e}");
+            std::process::exit(1);
+        }
+    } else if let Some(bin) = args.bin {
+        let mut file = File::open(bin).unwrap();
+        let aout = Aout::read_from(&mut file);
+        emu.load_aout(&aout);
+        emu.reg_write_word(Reg::PC, aout.entry_point);
+    }
+    let res: Result<(), Box<dyn Error>> = match args.gdb {
+        Some(port) => {
+            serve_gdb(&mut emu, port).map_err(|e| format!("GDB on port {port}: {e}").into())
+        }
+        None if args.monitor => {
+            run_monitor(&mut emu, &lights);
+            Ok(())
+        }
+        None => emu.run().map(drop).map_err(Into::into),
+    };
+
+    emu.clear_tracer();
+
+    if let Some(path) = args.save_snapshot {
+        let mut file = BufWriter::new(File::create(path).unwrap());
+        emu.save_snapshot(&mut file).unwrap();
+        file.flush().unwrap();
+    }
 
-    let mut file = std::fs::File::open(args.bin).unwrap();
-    let aout = Aout::read_from(&mut file);
-    emu.load_aout(&aout);
-    emu.run_at(aout.entry_point);
+    if let Err(e) = res {
+        // Put the terminal back first.
+        drop(emu);
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,297 @@
+use as_lib::helpers::parse_condition;
+use as_lib::ir::{CondAtom, CondExpr};
+use common::asm::{Atom, NUM_REGS, Op, Reg};
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+// Which accesses to a watched address stop the emulator. A watch covers the
+// word containing the address, so byte accesses to either half count. Writes
+// to device registers that can't be read without side effects always count
+// as changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchAccess {
+    Read,
+    Write,
+    Change,
+}
+
+impl fmt::Display for WatchAccess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WatchAccess::Read => write!(f, "read"),
+            WatchAccess::Write => write!(f, "write"),
+            WatchAccess::Change => write!(f, "change"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    // Before the instruction at the address is executed.
+    Exec(u16),
+    // After an instruction accesses the address. Addresses are virtual, in
+    // whatever mode the access is made.
+    Mem(u16, WatchAccess),
+    // After an instruction changes the register.
+    Reg(Reg),
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Trigger::Exec(addr) => write!(f, "exec {addr:06o}"),
+            Trigger::Mem(addr, access) => write!(f, "{access} {addr:06o}"),
+            Trigger::Reg(reg) => write!(f, "change {reg}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub trigger: Trigger,
+    pub cond: Option<Condition>, // Only stops if this holds, if given.
+}
+
+impl Breakpoint {
+    pub fn exec(addr: u16) -> Self {
+        Self::new(Trigger::Exec(addr))
+    }
+
+    pub fn mem(addr: u16, access: WatchAccess) -> Self {
+        Self::new(Trigger::Mem(addr, access))
+    }
+
+    pub fn reg(reg: Reg) -> Self {
+        Self::new(Trigger::Reg(reg))
+    }
+
+    fn new(trigger: Trigger) -> Self {
+        Breakpoint {
+            trigger,
+            cond: None,
+        }
+    }
+
+    pub fn when(mut self, cond: Condition) -> Self {
+        self.cond = Some(cond);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchTarget {
+    Mem(u16), // The address accessed.
+    Reg(Reg),
+}
+
+// What set off a watchpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub id: usize,
+    pub pc: u16, // Of the instruction that made the access.
+    pub target: WatchTarget,
+    pub write: bool,
+    pub old: Option<u16>, // Before a write, if it could be read.
+    pub val: u16,         // Read or written; a byte for byte accesses.
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let access = if self.write { "write" } else { "read" };
+        match self.target {
+            WatchTarget::Mem(addr) => write!(f, "{access} of {addr:06o}")?,
+            WatchTarget::Reg(reg) => write!(f, "{access} of {reg}")?,
+        }
+        if let Some(old) = self.old {
+            write!(f, " from {old:06o}")?;
+        }
+        let to = if self.write { "to" } else { "as" };
+        write!(f, " {to} {:06o} at pc {:06o}", self.val, self.pc)
+    }
+}
+
+// The breakpoints and watchpoints set, by ID, with sets of what they watch
+// so the emulator can check cheaply whether any applies.
+#[derive(Debug, Default)]
+pub struct Breakpoints {
+    points: Vec<(usize, Breakpoint)>,
+    next_id: usize,
+    exec: HashSet<u16>,
+    words: HashSet<u16>, // Watched words, by even address.
+    regs: bool,
+}
+
+impl Breakpoints {
+    pub fn add(&mut self, bp: Breakpoint) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.points.push((id, bp));
+        self.rebuild();
+        id
+    }
+
+    pub fn remove(&mut self, id: usize) -> bool {
+        let len = self.points.len();
+        self.points.retain(|(i, _)| *i != id);
+        self.rebuild();
+        self.points.len() != len
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.rebuild();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Breakpoint)> {
+        self.points.iter().map(|(id, bp)| (*id, bp))
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Breakpoint> {
+        self.iter().find(|(i, _)| *i == id).map(|(_, bp)| bp)
+    }
+
+    fn rebuild(&mut self) {
+        self.exec.clear();
+        self.words.clear();
+        self.regs = false;
+        for (_, bp) in &self.points {
+            match bp.trigger {
+                Trigger::Exec(addr) => {
+                    self.exec.insert(addr);
+                }
+                Trigger::Mem(addr, _) => {
+                    self.words.insert(addr & !1);
+                }
+                Trigger::Reg(_) => self.regs = true,
+            }
+        }
+    }
+
+    pub fn is_exec(&self, pc: u16) -> bool {
+        !self.exec.is_empty() && self.exec.contains(&pc)
+    }
+
+    pub fn watches_mem(&self) -> bool {
+        !self.words.is_empty()
+    }
+
+    pub fn is_watched(&self, addr: u16) -> bool {
+        self.words.contains(&(addr & !1))
+    }
+
+    pub fn watches_regs(&self) -> bool {
+        self.regs
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Val(u16),
+    Reg(Reg),
+    Ps,
+    Mem(u16), // The word at the address.
+}
+
+// Operands joined by operators, evaluated left to right with no precedence,
+// as the assembler does.
+type Expr = Vec<(Op, Operand)>;
+
+// A condition on the machine, in the assembler's expression syntax, with
+// registers (r0 to r5, sp, pc and ps) and words of memory (@#addr) as
+// operands. Numbers are octal unless they end in a ".". With an =, the
+// expressions either side must be equal; otherwise the expression must be
+// non-zero. So "r0 = 12 & @#177564 & 200" holds when r0 is 12 and the
+// console's transmitter is ready, or when r0 is 0 and it isn't. Device
+// registers that can't be read without side effects make it false.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    text: String,
+    lhs: Expr,
+    rhs: Option<Expr>,
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn convert_atom(atom: Atom) -> Result<u16, String> {
+    match atom {
+        Atom::Val(val) => Ok(val),
+        Atom::SymbolRef(sym) => Err(format!("unknown name {sym:?}")),
+        Atom::Loc => Err("\".\" can't be used in conditions".into()),
+        Atom::TmpSymbolFRef(_) | Atom::TmpSymbolBRef(_) => {
+            Err("labels can't be used in conditions".into())
+        }
+    }
+}
+
+fn convert_expr(expr: CondExpr) -> Result<Expr, String> {
+    expr.into_iter()
+        .map(|(op, atom)| {
+            let operand = match atom {
+                CondAtom::Atom(Atom::SymbolRef(sym)) if sym == "ps" => Operand::Ps,
+                CondAtom::Atom(atom) => Operand::Val(convert_atom(atom)?),
+                CondAtom::Reg(reg) => Operand::Reg(reg),
+                CondAtom::Mem(addr) => Operand::Mem(convert_atom(addr)?),
+            };
+            Ok((op, operand))
+        })
+        .collect()
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cond = parse_condition(s)?;
+        Ok(Condition {
+            text: s.trim().to_string(),
+            lhs: convert_expr(cond.lhs)?,
+            rhs: cond.rhs.map(convert_expr).transpose()?,
+        })
+    }
+}
+
+impl Condition {
+    // Whether the condition holds, with the registers, PS and a way to read
+    // a word of memory. A word that can't be read makes it false.
+    pub fn eval(
+        &self,
+        regs: &[u16; NUM_REGS],
+        ps: u16,
+        mut read_word: impl FnMut(u16) -> Option<u16>,
+    ) -> bool {
+        let mut eval_expr = |expr: &Expr| -> Option<u16> {
+            let mut acc = 0u16;
+            for (op, operand) in expr {
+                let val = match *operand {
+                    Operand::Val(val) => val,
+                    Operand::Reg(reg) => regs[reg as usize],
+                    Operand::Ps => ps,
+                    Operand::Mem(addr) => read_word(addr)?,
+                };
+                acc = match op {
+                    Op::Add => acc.wrapping_add(val),
+                    Op::Sub => acc.wrapping_sub(val),
+                    Op::And => acc & val,
+                    Op::Or => acc | val,
+                    _ => unreachable!("{op:?} isn't in conditions"),
+                };
+            }
+            Some(acc)
+        };
+        let Some(lhs) = eval_expr(&self.lhs) else {
+            return false;
+        };
+        match &self.rhs {
+            Some(rhs) => eval_expr(rhs) == Some(lhs),
+            None => lhs != 0,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::stop::StopReason;
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+#[derive(Debug, Default)]
+struct Shared {
+    requests: AtomicU8,
+    lock: Mutex<()>,
+    resumed: Condvar,
+}
+
+// A handle for controlling an emulator from other threads, or from its
+// devices. Each emulator has its own, and requests only affect that one.
+// The emulator looks at them before each instruction.
+#[derive(Debug, Clone, Default)]
+pub struct Control {
+    shared: Arc<Shared>,
+}
+
+impl Control {
+    const STOP: u8 = 0x1;
+    const INTERRUPT: u8 = 0x2;
+    const PAUSE: u8 = 0x4;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn request(&self, bits: u8) {
+        let _guard = self.shared.lock.lock().unwrap();
+        self.shared.requests.fetch_or(bits, Ordering::AcqRel);
+        self.shared.resumed.notify_all();
+    }
+
+    // The emulator returns StopReason::HostStop. It can be run again
+    // afterwards. Ends a pause.
+    pub fn stop(&self) {
+        self.request(Self::STOP);
+    }
+
+    // Breaks into whatever is driving the emulator, as SIMH's Ctrl-E does:
+    // the emulator returns StopReason::HostInterrupt. Ends a pause.
+    pub fn interrupt(&self) {
+        self.request(Self::INTERRUPT);
+    }
+
+    // Drops an interrupt request that hasn't been taken.
+    pub fn cancel_interrupt(&self) {
+        let _guard = self.shared.lock.lock().unwrap();
+        self.shared
+            .requests
+            .fetch_and(!Self::INTERRUPT, Ordering::AcqRel);
+    }
+
+    // The thread running the emulator blocks until resume().
+    pub fn pause(&self) {
+        self.request(Self::PAUSE);
+    }
+
+    pub fn resume(&self) {
+        let _guard = self.shared.lock.lock().unwrap();
+        self.shared
+            .requests
+            .fetch_and(!Self::PAUSE, Ordering::AcqRel);
+        self.shared.resumed.notify_all();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.shared.requests.load(Ordering::Acquire) & Self::PAUSE != 0
+    }
+
+    // Whether anything has been requested. Cheap enough to call before every
+    // instruction.
+    pub fn requested(&self) -> bool {
+        self.shared.requests.load(Ordering::Relaxed) != 0
+    }
+
+    // Blocks while paused, returning whether it did.
+    pub fn wait_while_paused(&self) -> bool {
+        let mut guard = self.shared.lock.lock().unwrap();
+        let mut paused = false;
+        loop {
+            let requests = self.shared.requests.load(Ordering::Acquire);
+            if requests & Self::PAUSE == 0 || requests & (Self::STOP | Self::INTERRUPT) != 0 {
+                return paused;
+            }
+            paused = true;
+            guard = self.shared.resumed.wait(guard).unwrap();
+        }
+    }
+
+    // Takes a stop or interrupt request, along with any pause, so the
+    // emulator can be run again.
+    pub fn take_request(&self) -> Option<StopReason> {
+        let _guard = self.shared.lock.lock().unwrap();
+        let requests = self.shared.requests.load(Ordering::Acquire);
+        let (taken, ret) = if requests & Self::STOP != 0 {
+            (Self::STOP, StopReason::HostStop)
+        } else if requests & Self::INTERRUPT != 0 {
+            (Self::INTERRUPT, StopReason::HostInterrupt)
+        } else {
+            return None;
+        };
+        self.shared
+            .requests
+            .fetch_and(!(taken | Self::PAUSE), Ordering::AcqRel);
+        Some(ret)
+    }
+}
@@ -1,33 +1,40 @@
 use crate::EmulatorState;
 use crate::MMIOHandler;
-use crate::Status;
-use crate::io::Interrupt;
+use crate::breakpoint::{
+    Breakpoint, Breakpoints, Condition, Trigger, WatchAccess, WatchHit, WatchTarget,
+};
+use crate::control::Control;
+use crate::fpu::{self, FpError, Fpu};
+use crate::history::{History, Step};
+use crate::io::bus::Bus;
+use crate::io::cpu_error_access::CpuErrorAccess;
+use crate::io::mmu_access::MmuAccess;
+use crate::io::stack_limit_access::StackLimitAccess;
 use crate::io::status_access::StatusAccess;
+use crate::mmu::{Access, Mmu};
+use crate::model::CpuModel;
+use crate::snapshot::{self, SnapshotReader, SnapshotWriter};
+use crate::stop::{EmulatorError, StopReason};
+use crate::throttle::{Pacer, Throttle};
+use crate::timing;
+use crate::trace::{TraceRecord, Tracer};
+use crate::{ProcessorMode, Status};
 use aout::Aout;
 use common::asm::*;
 use common::constants::*;
+use common::float::{Float, Precision};
 
 use std::cmp::Ordering;
-use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::io::{self, Read, Write};
 use std::ops::{BitAnd, BitOr};
-use std::sync::atomic::{self, AtomicBool};
-use std::sync::{Arc, Mutex};
+use std::path::Path;
+use std::sync::Arc;
 
 use delegate::delegate;
-use log::{debug, trace};
+use log::{debug, error, trace};
 use num_traits::{FromPrimitive, ToPrimitive};
 
-static SHOULD_QUIT: AtomicBool = AtomicBool::new(false);
-
-pub fn quit() {
-    SHOULD_QUIT.store(true, atomic::Ordering::Relaxed);
-}
-
-fn should_quit() -> bool {
-    SHOULD_QUIT.load(atomic::Ordering::Relaxed)
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Size {
     Byte,
@@ -75,117 +82,615 @@ enum ResolvedOperand {
     Mem(u16),
 }
 
+// A resolved FP11 floating point operand.
+#[derive(Debug, Clone, Copy)]
+enum FpOperand {
+    Ac(usize),
+    Mem(u16, u16), // Address and number of words.
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExecRet {
     Ok,
     Halt,
     Wait,
-    Quit,
+    DoubleFault, // Halted, on a fault on the emergency stack.
+    Breakpoint { id: usize, pc: u16 },
+}
+
+impl From<ExecRet> for StopReason {
+    fn from(ret: ExecRet) -> Self {
+        match ret {
+            ExecRet::Ok | ExecRet::Wait => StopReason::Step,
+            ExecRet::Halt => StopReason::Halted,
+            ExecRet::DoubleFault => StopReason::DoubleFault,
+            ExecRet::Breakpoint { id, pc } => StopReason::Breakpoint { id, pc },
+        }
+    }
+}
+
+// Conditions that abort the current instruction and trap through a vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trap {
+    BusError(u16), // Cause, in CPU error register bits.
+    RedZone,
+    ReservedInstruction,
+    Trace, // T bit or BPT
+    Iot,
+    MmuAbort,
+    FloatingPoint,
+    Fis, // Uses the FP11's vector, but isn't affected by the FPS.
+}
+
+impl Trap {
+    const BUS_ERROR_VECTOR: u16 = 0o4;
+
+    fn vector(self) -> u16 {
+        match self {
+            Trap::BusError(_) | Trap::RedZone => Self::BUS_ERROR_VECTOR,
+            Trap::ReservedInstruction => 0o10,
+            Trap::Trace => 0o14,
+            Trap::Iot => 0o20,
+            Trap::MmuAbort => Mmu::ABORT_VECTOR,
+            Trap::FloatingPoint | Trap::Fis => Fpu::VECTOR,
+        }
+    }
+}
+
+type TrapResult<T> = Result<T, Trap>;
+
+// An access by the current instruction to a watched word.
+struct WatchedAccess {
+    addr: u16,
+    write: bool,
+    old: Option<u16>,
+    val: u16,
 }
 
 pub struct Emulator {
     state: EmulatorState,
-    mmio_handlers: HashMap<u16, Arc<Mutex<dyn MMIOHandler>>>,
+    model: CpuModel,
+    bus: Bus,
     waiting: bool,
+    yellow_zone: bool, // The current instruction made a yellow zone stack reference.
+    emergency_stack: bool, // Pushing onto the emergency stack, which isn't checked.
+    ins_pc: u16,       // Address of the current instruction.
+    throttle: Throttle,
+    pacer: Option<Pacer>,
+    history: History,
+    tracer: Option<Tracer>,
+    decode_cache: bool,
+    eas: Vec<Option<u16>>, // Effective addresses of the current instruction's operands, while tracing.
+    control: Control,
+    breakpoints: Breakpoints,
+    watched: Vec<WatchedAccess>,
+    resume_pc: Option<u16>, // A breakpoint here doesn't stop the next instruction.
 }
 
 impl Emulator {
+    const EMERGENCY_SP: u16 = 0o4;
+    const MAX_VECTOR: u16 = 0o774;
+
     pub fn new() -> Emulator {
+        Self::with_model(CpuModel::default())
+    }
+
+    // Only the CPU registers the model has are put in the I/O page.
+    pub fn with_model(model: CpuModel) -> Emulator {
         let mut emu = Emulator {
             state: EmulatorState::new(),
-            mmio_handlers: HashMap::new(),
+            model,
+            bus: Bus::new(),
             waiting: false,
+            yellow_zone: false,
+            emergency_stack: false,
+            ins_pc: 0,
+            throttle: Throttle::default(),
+            pacer: None,
+            history: History::default(),
+            tracer: None,
+            decode_cache: true,
+            eas: Vec::new(),
+            control: Control::new(),
+            breakpoints: Breakpoints::default(),
+            watched: Vec::new(),
+            resume_pc: None,
         };
         emu.set_mmio_handler(StatusAccess::default());
+        if model.has_cpu_error_reg() {
+            emu.set_mmio_handler(CpuErrorAccess::default());
+        }
+        if model.has_stack_limit_reg() {
+            emu.set_mmio_handler(StackLimitAccess::default());
+        }
+        if model.has_mmu() {
+            emu.set_mmio_handler(MmuAccess::default());
+        }
         emu
     }
 
-    // Run until a halt or quit.
-    pub fn run(&mut self) -> ExecRet {
+    pub fn get_model(&self) -> CpuModel {
+        self.model
+    }
+
+    pub fn set_throttle(&mut self, throttle: Throttle) {
+        self.throttle = throttle;
+        self.pacer =
+            (throttle != Throttle::Unlimited).then(|| Pacer::new(self.state.get_time_ns()));
+    }
+
+    pub fn get_throttle(&self) -> Throttle {
+        self.throttle
+    }
+
+    // A handle for stopping, pausing or interrupting this emulator.
+    pub fn control(&self) -> Control {
+        self.control.clone()
+    }
+
+    // Pacing starts afresh, so the guest doesn't try to catch up on time spent
+    // stopped.
+    fn restart_pacer(&mut self) {
+        if self.pacer.is_some() {
+            self.pacer = Some(Pacer::new(self.state.get_time_ns()));
+        }
+    }
+
+    // Breakpoints and watchpoints, by the ID add_breakpoint() returns.
+    pub fn add_breakpoint(&mut self, bp: Breakpoint) -> usize {
+        self.breakpoints.add(bp)
+    }
+
+    pub fn remove_breakpoint(&mut self, id: usize) -> bool {
+        self.breakpoints.remove(id)
+    }
+
+    pub fn breakpoints(&self) -> &Breakpoints {
+        &self.breakpoints
+    }
+
+    // Run until anything but a step. A breakpoint on the first instruction
+    // doesn't stop it, so running again continues from one.
+    pub fn run(&mut self) -> Result<StopReason, EmulatorError> {
+        self.restart_pacer();
+        self.resume_pc = Some(self.state.pc());
         loop {
-            let ret = self.run_ins();
-            if matches!(ret, ExecRet::Halt | ExecRet::Quit) {
-                return ret;
+            let reason = self.run_one()?;
+            if reason != StopReason::Step {
+                return Ok(reason);
+            }
+        }
+    }
+
+    // Run at most n instructions, counting steps spent waiting.
+    pub fn run_for(&mut self, n: u64) -> Result<StopReason, EmulatorError> {
+        self.restart_pacer();
+        self.resume_pc = Some(self.state.pc());
+        for _ in 0..n {
+            let reason = self.run_one()?;
+            if reason != StopReason::Step {
+                return Ok(reason);
+            }
+        }
+        Ok(StopReason::InsLimit)
+    }
+
+    // Run a single instruction, first waking any devices that are due and taking
+    // the highest priority interrupt requested. Like run(), it doesn't stop at
+    // a breakpoint on the current instruction, only on an interrupt handler's.
+    pub fn run_ins(&mut self) -> Result<StopReason, EmulatorError> {
+        self.resume_pc = Some(self.state.pc());
+        self.run_one()
+    }
+
+    fn run_one(&mut self) -> Result<StopReason, EmulatorError> {
+        if self.control.requested() {
+            if self.control.wait_while_paused() {
+                self.restart_pacer();
+            }
+            if let Some(reason) = self.control.take_request() {
+                return Ok(reason);
             }
         }
+        let regs = self.breakpoints.watches_regs().then(|| self.reg_file());
+        let ret = self.record_step();
+        let reason = match ret {
+            Ok(ret) => ret.into(),
+            Err(err) => {
+                self.watched.clear();
+                return Err(err);
+            }
+        };
+        if reason == StopReason::Step
+            && let Some(hit) = self.watch_hit(regs)
+        {
+            return Ok(StopReason::Watchpoint(hit));
+        }
+        self.watched.clear();
+        Ok(reason)
+    }
+
+    fn error(&self, pc: u16, message: String) -> EmulatorError {
+        EmulatorError {
+            pc,
+            ins: self.ins_at(pc).map(Box::new),
+            message,
+        }
     }
 
-    // Run a single instruction, letting each device get a time slice and potentially
-    // generating an interrupt.
-    pub fn run_ins(&mut self) -> ExecRet {
-        if should_quit() {
-            return ExecRet::Quit;
+    // The instruction in memory at an address, if it can be decoded.
+    fn ins_at(&self, pc: u16) -> Option<Ins> {
+        let mut words = [0u16; MAX_INS_WORDS as usize];
+        for (i, word) in words.iter_mut().enumerate() {
+            *word = self
+                .peek_word(pc.wrapping_add(i as u16 * WORD_SIZE))
+                .unwrap_or(0);
         }
+        Ins::decode(&words)
+    }
 
-        // TODO: better timing model
+    // A step, recorded in the history if that's on.
+    fn record_step(&mut self) -> Result<ExecRet, EmulatorError> {
+        if !self.history.enabled() {
+            return self.step();
+        }
+
+        let cpu = self.state.cpu_state();
+        let waiting = self.waiting;
+        self.state.start_mem_journal();
+        let ret = self.step();
+        let mem = self.state.take_mem_journal();
+        let ret = ret?;
+        // Time passing while waiting isn't worth a step of its own, nor is
+        // stopping at a breakpoint.
+        let idle = (waiting && self.waiting) || matches!(ret, ExecRet::Breakpoint { .. });
+        if !(idle && mem.is_empty()) {
+            self.history.push(Step { cpu, waiting, mem });
+        }
+        Ok(ret)
+    }
+
+    fn step(&mut self) -> Result<ExecRet, EmulatorError> {
         self.state.inc_ins();
+        if let Some(pacer) = &mut self.pacer {
+            pacer.pace(self.state.get_time_ns());
+        }
 
-        if let Some((dev, inter)) = self.tick_devices() {
-            if inter.prio > self.state.get_status().get_prio() {
-                self.waiting = false;
-                dev.lock().unwrap().interrupt_accepted();
-                self.interrupt(inter.vector);
+        self.bus.wake_due(&mut self.state);
+        // Checked before every instruction, so lowering the priority with RTI,
+        // SPL or a write to the PSW lets a pending request in straight away.
+        if let Some((dev, inter)) = self.bus.interrupt(self.state.get_status().get_prio()) {
+            // A vector holds a PC and PS, somewhere in the first 256 words.
+            if inter.vector & 0o3 != 0 || inter.vector > Self::MAX_VECTOR {
+                let name = self.bus.devices()[dev].name();
+                let message = format!("{name} requested bad interrupt vector {:o}", inter.vector);
+                return Err(self.error(self.state.pc(), message));
+            }
+            self.waiting = false;
+            self.bus.interrupt_accepted(dev, inter.vector);
+            let ret = self.interrupt(inter.vector);
+            if ret != ExecRet::Ok {
+                return Ok(ret);
             }
         }
 
+        // Nothing can happen until the next device event, so skip to it.
         if self.waiting {
-            return ExecRet::Wait;
+            let now = self.state.get_time_ns();
+            let idle_ns = timing::IDLE_NS as u64;
+            let ns = self
+                .bus
+                .next_event_ns()
+                .map_or(idle_ns, |time| time.saturating_sub(now).max(idle_ns));
+            self.elapse(ns);
+            return Ok(ExecRet::Wait);
         }
 
-        let ins = self.decode();
-        debug!(
-            "PC: 0o{:#o}: {}",
-            self.state.pc(),
-            ins.display_with_pc(self.state.pc())
-        );
-        self.reg_write_word(Reg::PC, self.state.pc() + 2);
+        // After any interrupt, so a breakpoint on its handler stops there.
+        let pc = self.state.pc();
+        let resuming = self.resume_pc.take() == Some(pc);
+        if !resuming
+            && self.breakpoints.is_exec(pc)
+            && let Some(id) = self.exec_breakpoint(pc)
+        {
+            return Ok(ExecRet::Breakpoint { id, pc });
+        }
+
+        self.ins_pc = pc;
+        self.state.get_mmu_mut().begin_ins(pc);
+        self.yellow_zone = false;
+        let ins = match self.fetch() {
+            Ok(ins) => ins,
+            Err(trap) => return Ok(self.trap(trap)),
+        };
+        debug!("PC: 0o{:#o}: {}", pc, ins.display_with_pc(pc));
+        let rec = self.tracer.is_some().then(|| self.begin_trace(pc, &ins));
+        self.reg_write_word(Reg::PC, pc.wrapping_add(WORD_SIZE));
 
         if matches!(
-            ins,
+            *ins,
             Ins::Misc(MiscIns {
                 op: MiscOpcode::Wait
             })
         ) {
+            self.charge_ins(&ins, pc);
             self.waiting = true;
-            return ExecRet::Wait;
+            if let Some(rec) = rec {
+                self.end_trace(rec);
+            }
+            return Ok(ExecRet::Wait);
+        }
+
+        let traced = self.state.get_status().get_t();
+        let res = self.exec(&ins);
+        self.charge_ins(&ins, pc);
+        if let Some(rec) = rec {
+            self.end_trace(rec);
         }
+        Ok(match res {
+            Ok(ExecRet::Ok) if self.yellow_zone => {
+                self.trap(Trap::BusError(CpuErrorAccess::YELLOW_ZONE))
+            }
+            Ok(ExecRet::Ok) if self.trace_after(&ins, traced) => self.trap(Trap::Trace),
+            Ok(ret) => ret,
+            Err(trap) => self.trap(trap),
+        })
+    }
 
-        self.exec(&ins)
+    // The first breakpoint on the instruction at pc whose condition holds.
+    fn exec_breakpoint(&mut self, pc: u16) -> Option<usize> {
+        let candidates: Vec<_> = self
+            .breakpoints
+            .iter()
+            .filter(|(_, bp)| bp.trigger == Trigger::Exec(pc))
+            .map(|(id, bp)| (id, bp.cond.clone()))
+            .collect();
+        candidates
+            .into_iter()
+            .find(|(_, cond)| self.cond_holds(cond.as_ref()))
+            .map(|(id, _)| id)
     }
 
-    // Continue after halt.
-    pub fn cont(&mut self) {
-        self.run();
-    }
-
-    fn tick_devices(&mut self) -> Option<(Arc<Mutex<dyn MMIOHandler>>, Interrupt)> {
-        let mut interrupt: Option<(Arc<Mutex<dyn MMIOHandler>>, Interrupt)> = None;
-        for dev in self.mmio_handlers.values_mut() {
-            if let Some(inter) = dev.lock().unwrap().tick(&mut self.state) {
-                match &interrupt {
-                    Some(max) => {
-                        if inter.prio > max.1.prio {
-                            interrupt = Some((dev.clone(), inter))
-                        }
-                    }
-                    _ => interrupt = Some((dev.clone(), inter)),
-                }
+    // The first watchpoint set off by the last step whose condition holds,
+    // given the registers before it if any are watched.
+    fn watch_hit(&mut self, regs: Option<[u16; NUM_REGS]>) -> Option<WatchHit> {
+        let accesses = std::mem::take(&mut self.watched);
+        let pc = self.ins_pc;
+        let mut hits = Vec::new();
+        for (id, bp) in self.breakpoints.iter() {
+            let hit = match bp.trigger {
+                Trigger::Exec(_) => None,
+                Trigger::Mem(addr, kind) => accesses
+                    .iter()
+                    .find(|a| {
+                        a.addr & !1 == addr & !1
+                            && match kind {
+                                WatchAccess::Read => !a.write,
+                                WatchAccess::Write => a.write,
+                                WatchAccess::Change => a.write && a.old != Some(a.val),
+                            }
+                    })
+                    .map(|a| WatchHit {
+                        id,
+                        pc,
+                        target: WatchTarget::Mem(a.addr),
+                        write: a.write,
+                        old: a.old,
+                        val: a.val,
+                    }),
+                Trigger::Reg(reg) => regs
+                    .map(|regs| (regs[reg as usize], self.reg_read_word(reg)))
+                    .filter(|(old, new)| old != new)
+                    .map(|(old, new)| WatchHit {
+                        id,
+                        pc,
+                        target: WatchTarget::Reg(reg),
+                        write: true,
+                        old: Some(old),
+                        val: new,
+                    }),
+            };
+            if let Some(hit) = hit {
+                hits.push((hit, bp.cond.clone()));
             }
         }
-        interrupt
+        hits.into_iter()
+            .find(|(_, cond)| self.cond_holds(cond.as_ref()))
+            .map(|(hit, _)| hit)
+    }
+
+    fn cond_holds(&mut self, cond: Option<&Condition>) -> bool {
+        let Some(cond) = cond else {
+            return true;
+        };
+        let regs = self.reg_file();
+        let ps = self.state.get_status().to_raw();
+        // Without side effects, so only device registers that allow it.
+        cond.eval(&regs, ps, |addr| {
+            (addr & 1 == 0)
+                .then(|| self.debug_peek_word(addr))
+                .flatten()
+        })
+    }
+
+    // Decoded instructions are cached by physical address, unless this is
+    // turned off. It's on by default.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache = enabled;
+    }
+
+    // Write a record of each instruction executed to the tracer, until
+    // clear_tracer().
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn clear_tracer(&mut self) {
+        if let Some(mut tracer) = self.tracer.take()
+            && let Err(e) = tracer.flush()
+        {
+            error!("Error flushing trace: {e}");
+        }
+    }
+
+    fn reg_file(&self) -> [u16; NUM_REGS] {
+        std::array::from_fn(|i| self.reg_read_word(Reg::from_usize(i).unwrap()))
     }
 
-    fn decode(&self) -> Ins {
-        let next_ins = self.state.next_ins();
-        let Some(ins) = Ins::decode(next_ins) else {
-            panic!("Invalid instruction 0{:o}", next_ins[0]);
+    // Called before the instruction at pc, which has been fetched, runs.
+    fn begin_trace(&mut self, pc: u16, ins: &Ins) -> TraceRecord {
+        self.eas.clear();
+        TraceRecord {
+            pc,
+            words: (0..ins.size() / WORD_SIZE)
+                .map(|i| self.peek_word(pc.wrapping_add(i * WORD_SIZE)).unwrap_or(0))
+                .collect(),
+            ins: ins.clone(),
+            regs_before: self.reg_file(),
+            ps_before: self.state.get_status().to_raw(),
+            regs: [0; NUM_REGS],
+            ps: 0,
+            eas: Vec::new(),
+        }
+    }
+
+    // Called after it runs, but before any trap it caused is taken.
+    fn end_trace(&mut self, mut rec: TraceRecord) {
+        rec.regs = self.reg_file();
+        rec.ps = self.state.get_status().to_raw();
+        rec.eas = std::mem::take(&mut self.eas);
+        if let Some(tracer) = &mut self.tracer
+            && let Err(e) = tracer.record(&rec)
+        {
+            error!("Error writing trace: {e}");
+        }
+    }
+
+    fn trace_ea(&mut self, ea: Option<u16>) {
+        if self.tracer.is_some() {
+            self.eas.push(ea);
+        }
+    }
+
+    // Record the last steps run, so they can be undone by step_back(). A limit
+    // of 0, the default, turns recording off. Any history is discarded.
+    pub fn set_history_limit(&mut self, steps: usize) {
+        self.history = History::new(steps);
+    }
+
+    // How many steps can be undone.
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    // Undo the last step, which is an instruction along with any trap or
+    // interrupt taken before it, restoring the CPU and memory. Devices aren't
+    // rewound: what they did is treated as input to the CPU, and isn't replayed
+    // when running forward again. Returns false if there's nothing to undo.
+    pub fn step_back(&mut self) -> bool {
+        let Some(step) = self.history.pop() else {
+            return false;
         };
-        ins
+        self.state.undo_mem_writes(&step.mem);
+        self.state.set_cpu_state(step.cpu);
+        self.waiting = step.waiting;
+        true
     }
 
-    pub fn run_at(&mut self, pc: u16) {
+    // Step back until the instruction at pc is about to run again. If it isn't
+    // in the history, this goes back as far as possible and returns false.
+    pub fn run_back_until(&mut self, pc: u16) -> bool {
+        while self.step_back() {
+            if self.state.pc() == pc && !self.waiting {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Whether to take a trace trap after an instruction, given whether T was
+    // set before it. RTI traps right away if it sets T, but RTT waits until
+    // after the next instruction, so a debugger can step.
+    fn trace_after(&self, ins: &Ins, traced: bool) -> bool {
+        match ins {
+            Ins::Misc(MiscIns {
+                op: MiscOpcode::Rti,
+            }) => self.state.get_status().get_t(),
+            Ins::Misc(MiscIns {
+                op: MiscOpcode::Rtt,
+            }) => false,
+            _ => traced,
+        }
+    }
+
+    // Let simulated time pass.
+    fn elapse(&mut self, ns: u64) {
+        self.state.advance_time(ns);
+    }
+
+    // Account for the time taken by the instruction at pc, which has just been
+    // executed.
+    fn charge_ins(&mut self, ins: &Ins, pc: u16) {
+        let branched = self.state.pc() != pc.wrapping_add(WORD_SIZE);
+        let double = self.state.get_fpu().precision() == Precision::Double;
+        let ns = self
+            .throttle
+            .ins_time_ns()
+            .unwrap_or_else(|| self.model.timing().ins_time(ins, branched, double));
+        self.elapse(ns as u64);
+    }
+
+    // Continue after halt.
+    pub fn cont(&mut self) -> Result<StopReason, EmulatorError> {
+        self.run()
+    }
+
+    // Only the first word is a real access, the rest are read again as the
+    // operands are resolved.
+    fn fetch(&mut self) -> TrapResult<Arc<Ins>> {
+        let pc = self.state.pc();
+        Self::check_aligned(pc)?;
+        let phys = self.translate(pc, self.curr_mode(), Access::Read)?;
+        if self.decode_cache
+            && let Some(ins) = self.state.get_decoded(phys)
+        {
+            return Ok(ins.clone());
+        }
+
+        let mut words = [0u16; MAX_INS_WORDS as usize];
+        let mut peeked = [true; MAX_INS_WORDS as usize];
+        words[0] = self.phys_read_word(phys)?;
+        for i in 1..words.len() {
+            let word = self.peek_word(pc.wrapping_add(i as u16 * WORD_SIZE));
+            words[i] = word.unwrap_or(0);
+            peeked[i] = word.is_some();
+        }
+        // Instructions the model doesn't have are reserved too.
+        let Some(ins) = Ins::decode(&words).filter(|ins| self.model.has_ins(ins)) else {
+            // Like any other instruction, the saved PC is past the opcode.
+            debug!("Reserved instruction {:#o} at pc {pc:#o}", words[0]);
+            self.reg_write_word(Reg::PC, pc.wrapping_add(WORD_SIZE));
+            return Err(Trap::ReservedInstruction);
+        };
+
+        // Only instructions within a page are cached, so all their words are
+        // at consecutive physical addresses, and invalidated by writes to
+        // them.
+        let ins = Arc::new(ins);
+        let num_words = (ins.size() / WORD_SIZE) as usize;
+        if self.decode_cache
+            && (pc as u32 % Mmu::PAGE_SIZE) + ins.size() as u32 <= Mmu::PAGE_SIZE
+            && peeked[..num_words].iter().all(|p| *p)
+        {
+            self.state.set_decoded(phys, ins.clone());
+        }
+        Ok(ins)
+    }
+
+    // For tests and tools that don't expect an emulator error: panics on one.
+    pub fn run_at(&mut self, pc: u16) -> StopReason {
         self.reg_write_word(Reg::PC, pc);
-        self.run();
+        self.run().unwrap_or_else(|e| panic!("{e}"))
     }
 
     pub fn load_aout(&mut self, aout: &Aout) {
@@ -201,69 +706,438 @@ impl Emulator {
         }
     }
 
+    // Saves the whole machine, so it can be resumed later by load_snapshot(),
+    // on an emulator of the same model with the same devices added in the
+    // same order.
+    pub fn save_snapshot(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut out = SnapshotWriter::new(out);
+        out.bytes(&snapshot::MAGIC)?;
+        out.u16(snapshot::VERSION)?;
+        out.u8(self.model_index())?;
+        out.bool(self.waiting)?;
+        self.state.save(&mut out)?;
+
+        // Each device's state is prefixed with its length, so a mismatch is
+        // caught rather than misread.
+        let devices = self.bus.devices();
+        out.u16(u16::try_from(devices.len()).unwrap())?;
+        for (i, dev) in devices.iter().enumerate() {
+            let mut buf = Vec::new();
+            dev.save_state(&mut SnapshotWriter::new(&mut buf))?;
+            out.u32(u32::try_from(buf.len()).unwrap())?;
+            out.bytes(&buf)?;
+
+            let granted = self.bus.granted(i);
+            out.u16(u16::try_from(granted.len()).unwrap())?;
+            for vector in granted {
+                out.u16(vector)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The snapshot is checked as far as possible before anything is changed,
+    // but a device rejecting its state leaves the machine half loaded.
+    pub fn load_snapshot(&mut self, input: &mut impl Read) -> io::Result<()> {
+        let mut input = SnapshotReader::new(input);
+        let mut magic = [0u8; 4];
+        input.bytes(&mut magic)?;
+        if magic != snapshot::MAGIC {
+            return Err(snapshot::invalid("not a snapshot"));
+        }
+        let version = input.u16()?;
+        if version != snapshot::VERSION {
+            return Err(snapshot::invalid(format!(
+                "unsupported snapshot version {version}"
+            )));
+        }
+        let model = input.u8()?;
+        if model != self.model_index() {
+            let saved = CpuModel::ALL.get(model as usize);
+            return Err(snapshot::invalid(format!(
+                "snapshot is of {}, not {}",
+                saved.map_or("an unknown model".into(), |m| m.to_string()),
+                self.model
+            )));
+        }
+        let waiting = input.bool()?;
+        let mut state = EmulatorState::new();
+        state.load(&mut input)?;
+
+        let num_devices = input.u16()? as usize;
+        if num_devices != self.bus.devices().len() {
+            return Err(snapshot::invalid(format!(
+                "snapshot has {num_devices} devices, not {}",
+                self.bus.devices().len()
+            )));
+        }
+        let mut dev_states = Vec::new();
+        let mut granted = Vec::new();
+        for i in 0..num_devices {
+            let mut buf = vec![0u8; input.u32()? as usize];
+            input.bytes(&mut buf)?;
+            dev_states.push(buf);
+            for _ in 0..input.u16()? {
+                granted.push((i, input.u16()?));
+            }
+        }
+
+        self.state = state;
+        self.history.clear();
+        self.waiting = waiting;
+        let devices = self.bus.devices_mut().iter_mut();
+        for (i, (dev, buf)) in devices.zip(dev_states).enumerate() {
+            let mut slice = buf.as_slice();
+            dev.load_state(&mut SnapshotReader::new(&mut slice))?;
+            if !slice.is_empty() {
+                return Err(snapshot::invalid(format!(
+                    "snapshot state of device {i} doesn't match"
+                )));
+            }
+        }
+        self.bus.refresh();
+        for (i, vector) in granted {
+            self.bus.interrupt_accepted(i, vector);
+        }
+        Ok(())
+    }
+
+    fn model_index(&self) -> u8 {
+        CpuModel::ALL.iter().position(|m| *m == self.model).unwrap() as u8
+    }
+
     pub fn set_mmio_handler_for<M, I>(&mut self, handler: M, addrs: I)
     where
         M: MMIOHandler + 'static,
         I: IntoIterator<Item = u16>,
     {
-        let handler = Arc::new(Mutex::new(handler));
-        for addr in addrs.into_iter() {
-            self.register_handler(handler.clone(), addr);
-        }
+        self.bus.add(Box::new(handler), addrs);
     }
 
     pub fn set_mmio_handler(&mut self, handler: impl MMIOHandler + 'static) {
-        let handler = Arc::new(Mutex::new(handler));
-        for addr in handler.lock().unwrap().default_addrs() {
-            self.register_handler(handler.clone(), *addr);
+        let addrs = handler.default_addrs().to_vec();
+        self.bus.add(Box::new(handler), addrs);
+    }
+
+    // Each device, with the addresses it answers at.
+    pub fn devices(&self) -> Vec<(&dyn MMIOHandler, Vec<u16>)> {
+        let devices = self.bus.devices().iter().enumerate();
+        devices
+            .map(|(i, dev)| (dev.as_ref(), self.bus.addrs(i)))
+            .collect()
+    }
+
+    // Attaches a file to the device with the name.
+    pub fn attach(&mut self, device: &str, path: &Path) -> io::Result<()> {
+        self.bus
+            .with_device(device, |dev| dev.attach(path))
+            .unwrap_or_else(|| Err(Self::no_such_device(device)))
+    }
+
+    pub fn detach(&mut self, device: &str) -> io::Result<()> {
+        self.bus
+            .with_device(device, |dev| dev.detach())
+            .unwrap_or_else(|| Err(Self::no_such_device(device)))
+    }
+
+    fn no_such_device(name: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, format!("no device {name}"))
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    // Physical memory, including the I/O page.
+
+    fn io_addr(phys: u32) -> u16 {
+        (phys - MMIO_PHYS_START) as u16 + MMIO_START
+    }
+
+    // Nothing responding is a bus timeout.
+    fn no_device(addr: u16) -> Trap {
+        debug!("No MMIO register at {addr:#o}");
+        Trap::BusError(CpuErrorAccess::UNIBUS_TIMEOUT)
+    }
+
+    fn phys_read_byte(&mut self, phys: u32) -> TrapResult<u8> {
+        if phys >= MMIO_PHYS_START {
+            let addr = Self::io_addr(phys);
+            self.bus
+                .read_byte(&mut self.state, addr)
+                .ok_or_else(|| Self::no_device(addr))
+        } else {
+            Ok(self.state.mem_read_byte(phys))
         }
     }
 
-    fn register_handler(&mut self, handler: Arc<Mutex<dyn MMIOHandler>>, addr: u16) {
-        assert!(addr >= MMIO_START);
-        assert!(addr & 0x1 == 0, "MMIOHandler addr {addr:o} not aligned");
-        let prev = self.mmio_handlers.insert(addr, handler);
-        assert!(prev.is_none(), "Duplicate MMIOHandler for {addr:o}");
+    fn phys_write_byte(&mut self, phys: u32, val: u8) -> TrapResult<()> {
+        if phys >= MMIO_PHYS_START {
+            let addr = Self::io_addr(phys);
+            self.bus
+                .write_byte(&mut self.state, addr, val)
+                .ok_or_else(|| Self::no_device(addr))?;
+        } else {
+            self.state.mem_write_byte(phys, val)
+        }
+        Ok(())
+    }
+
+    fn phys_read_word(&mut self, phys: u32) -> TrapResult<u16> {
+        if phys >= MMIO_PHYS_START {
+            let addr = Self::io_addr(phys);
+            self.bus
+                .read_word(&mut self.state, addr)
+                .ok_or_else(|| Self::no_device(addr))
+        } else {
+            Ok(self.state.mem_read_word(phys))
+        }
+    }
+
+    fn phys_write_word(&mut self, phys: u32, val: u16) -> TrapResult<()> {
+        if phys >= MMIO_PHYS_START {
+            let addr = Self::io_addr(phys);
+            self.bus
+                .write_word(&mut self.state, addr, val)
+                .ok_or_else(|| Self::no_device(addr))?;
+        } else {
+            self.state.mem_write_word(phys, val)
+        }
+        Ok(())
     }
 
     ///////////////////////////////////////////////////////////////////////////
+    // Virtual memory, as seen by the running program.
 
-    pub fn mem_read_byte(&mut self, addr: u16) -> u8 {
-        if addr >= MMIO_START {
-            if let Some(handler) = self.mmio_handlers.get_mut(&addr) {
-                return handler.lock().unwrap().read_byte(&mut self.state, addr);
+    fn curr_mode(&self) -> ProcessorMode {
+        self.state.get_status().get_curr_mode()
+    }
+
+    fn translate(&mut self, addr: u16, mode: ProcessorMode, access: Access) -> TrapResult<u32> {
+        let mmu = self.state.get_mmu_mut();
+        match mmu.map(addr, mode, access) {
+            Ok(phys) => {
+                if access == Access::Write {
+                    mmu.mark_written(addr, mode);
+                }
+                Ok(phys)
+            }
+            Err(fault) => {
+                debug!(
+                    "MMU abort {:#o} accessing {addr:#o} in {mode:?} mode",
+                    fault.0
+                );
+                mmu.record_abort(fault, mode, addr);
+                Err(Trap::MmuAbort)
             }
-            panic!("Invalid MMIO register {}", addr);
-        } else {
-            self.state.mem_read_byte(addr)
         }
     }
 
-    pub fn mem_write_byte(&mut self, addr: u16, val: u8) {
-        if addr >= MMIO_START {
-            if let Some(handler) = self.mmio_handlers.get_mut(&addr) {
-                handler
-                    .lock()
-                    .unwrap()
-                    .write_byte(&mut self.state, addr, val);
-                return;
-            }
-            panic!("Invalid MMIO register {}", addr);
+    fn read_byte(&mut self, addr: u16) -> TrapResult<u8> {
+        let phys = self.translate(addr, self.curr_mode(), Access::Read)?;
+        let val = self.phys_read_byte(phys)?;
+        if self.breakpoints.watches_mem() {
+            self.note_read(addr, val as u16);
+        }
+        Ok(val)
+    }
+
+    fn write_byte(&mut self, addr: u16, val: u8) -> TrapResult<()> {
+        let phys = self.translate(addr, self.curr_mode(), Access::Write)?;
+        if !self.breakpoints.watches_mem() {
+            return self.phys_write_byte(phys, val);
+        }
+        let old = self.watched_old(addr, phys, Size::Byte);
+        self.phys_write_byte(phys, val)?;
+        self.note_write(addr, old, val as u16);
+        Ok(())
+    }
+
+    // Accesses to watched words are noted, to check the watchpoints after the
+    // instruction.
+    fn note_read(&mut self, addr: u16, val: u16) {
+        if self.breakpoints.is_watched(addr) {
+            self.watched.push(WatchedAccess {
+                addr,
+                write: false,
+                old: None,
+                val,
+            });
+        }
+    }
+
+    // The value about to be overwritten, if the word's watched and can be
+    // read without side effects.
+    fn watched_old(&self, addr: u16, phys: u32, size: Size) -> Option<u16> {
+        if !self.breakpoints.is_watched(addr) {
+            return None;
+        }
+        let word = self.phys_peek_word(phys & !1)?;
+        Some(match size {
+            Size::Word => word,
+            _ if addr & 1 == 0 => word & 0xff,
+            _ => word >> u8::BITS,
+        })
+    }
+
+    fn note_write(&mut self, addr: u16, old: Option<u16>, val: u16) {
+        if self.breakpoints.is_watched(addr) {
+            self.watched.push(WatchedAccess {
+                addr,
+                write: true,
+                old,
+                val,
+            });
+        }
+    }
+
+    // Word accesses must be aligned; this is checked before relocation.
+    fn check_aligned(addr: u16) -> TrapResult<()> {
+        if addr & 0x1 != 0 {
+            debug!("Odd address {addr:#o}");
+            return Err(Trap::BusError(CpuErrorAccess::ODD_ADDRESS));
+        }
+        Ok(())
+    }
+
+    fn read_word(&mut self, addr: u16) -> TrapResult<u16> {
+        self.read_word_as(addr, self.curr_mode())
+    }
+
+    fn read_word_as(&mut self, addr: u16, mode: ProcessorMode) -> TrapResult<u16> {
+        Self::check_aligned(addr)?;
+        let phys = self.translate(addr, mode, Access::Read)?;
+        let val = self.phys_read_word(phys)?;
+        if self.breakpoints.watches_mem() {
+            self.note_read(addr, val);
+        }
+        Ok(val)
+    }
+
+    fn write_word(&mut self, addr: u16, val: u16) -> TrapResult<()> {
+        self.write_word_as(addr, val, self.curr_mode())
+    }
+
+    fn write_word_as(&mut self, addr: u16, val: u16, mode: ProcessorMode) -> TrapResult<()> {
+        Self::check_aligned(addr)?;
+        let phys = self.translate(addr, mode, Access::Write)?;
+        if !self.breakpoints.watches_mem() {
+            return self.phys_write_word(phys, val);
+        }
+        let old = self.watched_old(addr, phys, Size::Word);
+        self.phys_write_word(phys, val)?;
+        self.note_write(addr, old, val);
+        Ok(())
+    }
+
+    // Read a word of memory without side effects; None if it isn't mapped or
+    // is in the I/O page.
+    fn peek_word(&self, addr: u16) -> Option<u16> {
+        let phys = self
+            .state
+            .get_mmu()
+            .map(addr, self.curr_mode(), Access::Read)
+            .ok()?;
+        (phys < MMIO_PHYS_START).then(|| self.state.mem_read_word(phys))
+    }
+
+    // As peek_word(), but device registers can be read too, if the device
+    // can do it without side effects. For debuggers.
+    fn debug_peek_word(&self, addr: u16) -> Option<u16> {
+        let phys = self
+            .state
+            .get_mmu()
+            .map(addr, self.curr_mode(), Access::Read)
+            .ok()?;
+        self.phys_peek_word(phys)
+    }
+
+    fn phys_peek_word(&self, phys: u32) -> Option<u16> {
+        if phys >= MMIO_PHYS_START {
+            self.bus.peek(&self.state, Self::io_addr(phys))
         } else {
-            self.state.mem_write_byte(addr, val)
+            Some(self.state.mem_read_word(phys))
         }
     }
 
+    ///////////////////////////////////////////////////////////////////////////
+    // Memory access for users of the emulator. Addresses are virtual, in the
+    // current mode, but accesses aren't checked and can't abort. The plain
+    // accessors panic if the address isn't mapped, nothing answers there or a
+    // word isn't aligned; the mem_try_*() ones give None instead.
+
+    fn try_host_translate(&self, addr: u16) -> Option<u32> {
+        self.state
+            .get_mmu()
+            .map(addr, self.curr_mode(), Access::Read)
+            .ok()
+    }
+
+    fn host_translate(&self, addr: u16) -> u32 {
+        let mode = self.curr_mode();
+        let phys = self
+            .state
+            .get_mmu()
+            .map(addr, mode, Access::Read)
+            .map_err(|fault| fault.0);
+        phys.unwrap_or_else(|fault| {
+            panic!("Address {addr:#o} not mapped in {mode:?} mode (SR0 abort {fault:#o})")
+        })
+    }
+
+    pub fn mem_read_byte(&mut self, addr: u16) -> u8 {
+        let phys = self.host_translate(addr);
+        self.phys_read_byte(phys)
+            .unwrap_or_else(|_| panic!("Invalid MMIO register {addr:o}"))
+    }
+
+    // As mem_read_byte() and the like, but None if the address isn't mapped
+    // or there's no device there, for debuggers poking about.
+    pub fn mem_try_read_byte(&mut self, addr: u16) -> Option<u8> {
+        let phys = self.try_host_translate(addr)?;
+        self.phys_read_byte(phys).ok()
+    }
+
+    // As mem_try_read_byte(), but without side effects, so None for device
+    // registers that can't be read without them.
+    pub fn mem_peek_byte(&self, addr: u16) -> Option<u8> {
+        let word = self.debug_peek_word(addr & !1)?;
+        Some(if addr & 1 == 0 {
+            word as u8
+        } else {
+            (word >> 8) as u8
+        })
+    }
+
+    pub fn mem_try_write_byte(&mut self, addr: u16, val: u8) -> Option<()> {
+        let phys = self.try_host_translate(addr)?;
+        self.phys_write_byte(phys, val).ok()
+    }
+
+    pub fn mem_write_byte(&mut self, addr: u16, val: u8) {
+        let phys = self.host_translate(addr);
+        self.phys_write_byte(phys, val)
+            .unwrap_or_else(|_| panic!("Invalid MMIO register {addr:o}"));
+    }
+
     pub fn mem_read_word(&mut self, addr: u16) -> u16 {
         assert!(addr & 1 == 0, "Word read of 0o{addr:o} not aligned");
-        if addr >= MMIO_START {
-            if let Some(handler) = self.mmio_handlers.get_mut(&addr) {
-                return handler.lock().unwrap().read_word(&mut self.state, addr);
-            }
-            panic!("Invalid MMIO register {}", addr);
-        } else {
-            self.state.mem_read_word(addr)
+        let phys = self.host_translate(addr);
+        self.phys_read_word(phys)
+            .unwrap_or_else(|_| panic!("Invalid MMIO register {addr:o}"))
+    }
+
+    pub fn mem_try_read_word(&mut self, addr: u16) -> Option<u16> {
+        if addr & 1 != 0 {
+            return None;
         }
+        let phys = self.try_host_translate(addr)?;
+        self.phys_read_word(phys).ok()
+    }
+
+    pub fn mem_try_write_word(&mut self, addr: u16, val: u16) -> Option<()> {
+        if addr & 1 != 0 {
+            return None;
+        }
+        let phys = self.try_host_translate(addr)?;
+        self.phys_write_word(phys, val).ok()
     }
 
     pub fn mem_write_word(&mut self, addr: u16, val: u16) {
@@ -271,18 +1145,9 @@ impl Emulator {
             addr & 1 == 0,
             "Word write of {val:#o} to {addr:#o} not aligned"
         );
-        if addr >= MMIO_START {
-            if let Some(handler) = self.mmio_handlers.get_mut(&addr) {
-                handler
-                    .lock()
-                    .unwrap()
-                    .write_word(&mut self.state, addr, val);
-                return;
-            }
-            panic!("Invalid MMIO register {}", addr);
-        } else {
-            self.state.mem_write_word(addr, val)
-        }
+        let phys = self.host_translate(addr);
+        self.phys_write_word(phys, val)
+            .unwrap_or_else(|_| panic!("Invalid MMIO register {addr:o}"));
     }
 
     pub fn get_state(&self) -> &EmulatorState {
@@ -293,40 +1158,47 @@ impl Emulator {
         &mut self.state
     }
 
-    fn write_resolved_word(&mut self, res: ResolvedOperand, val: u16) {
+    fn write_resolved_word(&mut self, res: ResolvedOperand, val: u16) -> TrapResult<()> {
         match res {
             ResolvedOperand::Reg(r) => self.reg_write_word(r, val),
-            ResolvedOperand::Mem(addr) => self.mem_write_word(addr, val),
+            ResolvedOperand::Mem(addr) => self.write_word(addr, val)?,
         }
+        Ok(())
     }
 
-    fn write_resolved_byte(&mut self, res: ResolvedOperand, val: u8) {
+    fn write_resolved_byte(&mut self, res: ResolvedOperand, val: u8) -> TrapResult<()> {
         match res {
             ResolvedOperand::Reg(r) => self.reg_write_byte(r, val),
-            ResolvedOperand::Mem(addr) => self.mem_write_byte(addr, val),
+            ResolvedOperand::Mem(addr) => self.write_byte(addr, val)?,
         }
+        Ok(())
     }
-    fn read_resolved_byte(&mut self, res: ResolvedOperand) -> u8 {
+    fn read_resolved_byte(&mut self, res: ResolvedOperand) -> TrapResult<u8> {
         match res {
-            ResolvedOperand::Reg(r) => self.reg_read_byte(r),
-            ResolvedOperand::Mem(addr) => self.mem_read_byte(addr),
+            ResolvedOperand::Reg(r) => Ok(self.reg_read_byte(r)),
+            ResolvedOperand::Mem(addr) => self.read_byte(addr),
         }
     }
-    fn read_resolved_word(&mut self, res: ResolvedOperand) -> u16 {
+    fn read_resolved_word(&mut self, res: ResolvedOperand) -> TrapResult<u16> {
         match res {
-            ResolvedOperand::Reg(r) => self.reg_read_word(r),
-            ResolvedOperand::Mem(addr) => self.mem_read_word(addr),
+            ResolvedOperand::Reg(r) => Ok(self.reg_read_word(r)),
+            ResolvedOperand::Mem(addr) => self.read_word(addr),
         }
     }
 
-    fn read_resolved_widen(&mut self, res: ResolvedOperand, size: Size) -> u32 {
-        match size {
-            Size::Word => self.read_resolved_word(res) as u32,
-            Size::Byte => self.read_resolved_byte(res) as u32,
-        }
+    fn read_resolved_widen(&mut self, res: ResolvedOperand, size: Size) -> TrapResult<u32> {
+        Ok(match size {
+            Size::Word => self.read_resolved_word(res)? as u32,
+            Size::Byte => self.read_resolved_byte(res)? as u32,
+        })
     }
 
-    fn write_resolved_narrow(&mut self, res: ResolvedOperand, val: u32, size: Size) {
+    fn write_resolved_narrow(
+        &mut self,
+        res: ResolvedOperand,
+        val: u32,
+        size: Size,
+    ) -> TrapResult<()> {
         match size {
             Size::Word => self.write_resolved_word(res, val as u16),
             Size::Byte => self.write_resolved_byte(res, val as u8),
@@ -361,21 +1233,28 @@ impl Emulator {
     ///////////////////////////////////////////////////////////////////////////
     // Execute
     ///////////////////////////////////////////////////////////////////////////
-    // Returns the address, not the value
-    fn exec_auto(&mut self, reg: Reg, inc: bool, mut size: Size) -> u16 {
+    // Returns the address, not the value. len is the size of the operand in
+    // bytes.
+    fn exec_auto(&mut self, reg: Reg, inc: bool, mut len: u16) -> u16 {
         if reg == Reg::PC {
-            // Special case for literals for byte instructions
-            size = Size::Word;
+            // Special case for literals for byte (and floating point) instructions
+            len = WORD_SIZE;
         }
         let mut val = self.reg_read_word(reg);
         if !inc {
-            val = val.wrapping_sub(size.bytes());
+            val = val.wrapping_sub(len);
         }
         let ret = val;
         if inc {
-            val = val.wrapping_add(size.bytes());
+            val = val.wrapping_add(len);
         }
         self.reg_write_word(reg, val);
+        if reg != Reg::PC {
+            let delta = if inc { len } else { len.wrapping_neg() };
+            self.state
+                .get_mmu_mut()
+                .record_reg_change(reg, delta as i16);
+        }
         ret
     }
 
@@ -388,8 +1267,10 @@ impl Emulator {
 
     #[inline]
     fn debug_check_extra_addr(&self, arg: &Operand, addr: u16) {
-        if arg.needs_extra() {
-            debug_assert_eq!(arg.extra.unwrap_val(), self.state.mem_read_word(addr));
+        if arg.needs_extra()
+            && let Some(val) = self.peek_word(addr)
+        {
+            debug_assert_eq!(arg.extra.unwrap_val(), val);
         }
     }
 
@@ -403,67 +1284,104 @@ impl Emulator {
     // on side effects on the emulator state, but this is not convenient for other
     // tools, e.g., disassembers. the debug_check_extra_* functions make sure
     // they are equivalent (in debug mode).
-    fn resolve(&mut self, arg: &Operand, size: Size) -> ResolvedOperand {
+    fn resolve(&mut self, arg: &Operand, size: Size) -> TrapResult<ResolvedOperand> {
+        self.resolve_len(arg, size.bytes())
+    }
+
+    // Resolve an operand of len bytes, which only matters for autoincrement
+    // and autodecrement.
+    fn resolve_len(&mut self, arg: &Operand, len: u16) -> TrapResult<ResolvedOperand> {
         let loc = match arg.mode {
-            AddrMode::Gen => return ResolvedOperand::Reg(arg.reg),
+            AddrMode::Gen => {
+                self.trace_ea(None);
+                return Ok(ResolvedOperand::Reg(arg.reg));
+            }
             AddrMode::Def => self.reg_read_word(arg.reg),
             AddrMode::AutoInc => {
-                let addr = self.exec_auto(arg.reg, true, size);
+                let addr = self.exec_auto(arg.reg, true, len);
                 self.debug_check_extra_addr(arg, addr);
                 addr
             }
             AddrMode::AutoIncDef => {
-                let addr = self.exec_auto(arg.reg, true, Size::Word);
+                let addr = self.exec_auto(arg.reg, true, WORD_SIZE);
                 self.debug_check_extra_addr(arg, addr);
-                self.mem_read_word(addr)
+                self.read_word(addr)?
             }
             AddrMode::AutoDec => {
-                let addr = self.exec_auto(arg.reg, false, size);
+                let addr = self.exec_auto(arg.reg, false, len);
                 self.debug_check_extra_addr(arg, addr);
+                if arg.reg == Reg::SP {
+                    self.check_stack(addr)?;
+                }
                 addr
             }
             AddrMode::AutoDecDef => {
-                let addr = self.exec_auto(arg.reg, false, Size::Word);
+                let addr = self.exec_auto(arg.reg, false, WORD_SIZE);
                 self.debug_check_extra_addr(arg, addr);
-                self.mem_read_word(addr)
+                self.read_word(addr)?
             }
             AddrMode::Index => {
-                let imm_addr = self.exec_auto(Reg::PC, true, Size::Word);
-                let imm = self.mem_read_word(imm_addr);
+                let imm_addr = self.exec_auto(Reg::PC, true, WORD_SIZE);
+                let imm = self.read_word(imm_addr)?;
                 let reg_val = self.reg_read_word(arg.reg);
                 Self::debug_check_extra_val(arg, imm);
                 reg_val.wrapping_add(imm)
             }
             AddrMode::IndexDef => {
-                let imm_addr = self.exec_auto(Reg::PC, true, Size::Word);
-                let imm = self.mem_read_word(imm_addr);
+                let imm_addr = self.exec_auto(Reg::PC, true, WORD_SIZE);
+                let imm = self.read_word(imm_addr)?;
                 let reg_val = self.reg_read_word(arg.reg);
                 Self::debug_check_extra_val(arg, imm);
-                self.mem_read_word(reg_val.wrapping_add(imm))
+                self.read_word(reg_val.wrapping_add(imm))?
             }
         };
 
-        ResolvedOperand::Mem(loc)
+        self.trace_ea(Some(loc));
+        Ok(ResolvedOperand::Mem(loc))
+    }
+
+    // Read the source of a double operand instruction. On some models, a
+    // register source that the destination autoincrements or autodecrements
+    // is read after the update.
+    fn read_src(&mut self, src: &Operand, dst: &Operand, size: Size) -> TrapResult<u32> {
+        let resolved = self.resolve(src, size)?;
+        let val = self.read_resolved_widen(resolved, size)?;
+        if !self.model.uses_updated_reg() || src.mode != AddrMode::Gen || src.reg != dst.reg {
+            return Ok(val);
+        }
+        let len = if src.reg == Reg::PC {
+            WORD_SIZE
+        } else {
+            size.bytes()
+        };
+        let delta = match dst.mode {
+            AddrMode::AutoInc => len,
+            AddrMode::AutoIncDef => WORD_SIZE,
+            AddrMode::AutoDec => len.wrapping_neg(),
+            AddrMode::AutoDecDef => WORD_SIZE.wrapping_neg(),
+            _ => 0,
+        };
+        Ok(val.wrapping_add(delta as u32) & size.mask())
     }
 
-    fn do_mov(&mut self, src: &Operand, dst: &Operand, size: Size) {
-        let src = self.resolve(src, size);
-        let val = self.read_resolved_widen(src, size);
-        let dst = self.resolve(dst, size);
+    fn do_mov(&mut self, src: &Operand, dst: &Operand, size: Size) -> TrapResult<()> {
+        let val = self.read_src(src, dst, size)?;
+        let dst = self.resolve(dst, size)?;
 
         if size == Size::Byte {
             if matches!(dst, ResolvedOperand::Reg(_)) {
                 let val = val as u8 as i8 as i16 as u16;
-                self.write_resolved_word(dst, val);
+                self.write_resolved_word(dst, val)?;
             } else {
-                self.write_resolved_narrow(dst, val, size);
+                self.write_resolved_narrow(dst, val, size)?;
             }
         } else {
-            self.write_resolved_word(dst, val as u16);
+            self.write_resolved_word(dst, val as u16)?;
         }
         self.set_zero(val == 0);
         self.set_negative(size.sign_bit(val) != 0);
         self.set_overflow(false);
+        Ok(())
     }
 
     // TODO: combine these?
@@ -474,11 +1392,10 @@ impl Emulator {
         dst: &Operand,
         size: Size,
         discard: bool,
-    ) {
-        let src = self.resolve(src, size);
-        let src_val = self.read_resolved_widen(src, size);
-        let dst = self.resolve(dst, size);
-        let dst_val = self.read_resolved_widen(dst, size);
+    ) -> TrapResult<()> {
+        let src_val = self.read_src(src, dst, size)?;
+        let dst = self.resolve(dst, size)?;
+        let dst_val = self.read_resolved_widen(dst, size)?;
         let res = op(src_val, dst_val);
         let res_sign = size.sign_bit(res);
 
@@ -488,17 +1405,17 @@ impl Emulator {
         self.set_overflow(false);
 
         if !discard {
-            self.write_resolved_narrow(dst, res, size);
+            self.write_resolved_narrow(dst, res, size)?;
         }
+        Ok(())
     }
 
-    fn do_add(&mut self, src: &Operand, dst: &Operand, size: Size) {
+    fn do_add(&mut self, src: &Operand, dst: &Operand, size: Size) -> TrapResult<()> {
         assert!(size == Size::Word);
-        let src = self.resolve(src, size);
-        let src_val = self.read_resolved_widen(src, size);
+        let src_val = self.read_src(src, dst, size)?;
         let src_sign = size.sign_bit(src_val);
-        let dst = self.resolve(dst, size);
-        let dst_val = self.read_resolved_widen(dst, size);
+        let dst = self.resolve(dst, size)?;
+        let dst_val = self.read_resolved_widen(dst, size)?;
         let dst_sign = size.sign_bit(dst_val);
         let res = src_val + dst_val;
         let res_sign = size.sign_bit(res);
@@ -507,15 +1424,15 @@ impl Emulator {
         self.set_negative(res_sign != 0);
         self.set_carry(res >> size.bits() != 0);
         self.set_overflow(src_sign == dst_sign && dst_sign != res_sign);
-        self.write_resolved_narrow(dst, res, size);
+        self.write_resolved_narrow(dst, res, size)?;
+        Ok(())
     }
 
-    fn do_sub(&mut self, src: &Operand, dst: &Operand, size: Size) {
-        let src = self.resolve(src, size);
-        let src_val = self.read_resolved_widen(src, size);
+    fn do_sub(&mut self, src: &Operand, dst: &Operand, size: Size) -> TrapResult<()> {
+        let src_val = self.read_src(src, dst, size)?;
         let src_sign = size.sign_bit(src_val);
-        let dst = self.resolve(dst, size);
-        let dst_val = self.read_resolved_widen(dst, size);
+        let dst = self.resolve(dst, size)?;
+        let dst_val = self.read_resolved_widen(dst, size)?;
         let dst_sign = size.sign_bit(dst_val);
         let res = dst_val.wrapping_add((!src_val).wrapping_add(1) & size.mask());
         let res_sign = size.sign_bit(res);
@@ -525,16 +1442,16 @@ impl Emulator {
         self.set_carry(dst_val < src_val);
         self.set_overflow(src_sign != dst_sign && src_sign == res_sign);
 
-        self.write_resolved_narrow(dst, res, size);
+        self.write_resolved_narrow(dst, res, size)?;
+        Ok(())
     }
 
     // NB: args are swapped compared to sub!
-    fn do_cmp(&mut self, src: &Operand, dst: &Operand, size: Size) {
-        let src = self.resolve(src, size);
-        let src_val = self.read_resolved_widen(src, size);
+    fn do_cmp(&mut self, src: &Operand, dst: &Operand, size: Size) -> TrapResult<()> {
+        let src_val = self.read_src(src, dst, size)?;
         let src_sign = size.sign_bit(src_val);
-        let dst = self.resolve(dst, size);
-        let dst_val = self.read_resolved_widen(dst, size);
+        let dst = self.resolve(dst, size)?;
+        let dst_val = self.read_resolved_widen(dst, size)?;
         let dst_sign = size.sign_bit(dst_val);
         let res = src_val.wrapping_add((!dst_val).wrapping_add(1) & size.mask());
         let res_sign = size.sign_bit(res);
@@ -543,42 +1460,53 @@ impl Emulator {
         self.set_negative(res_sign != 0);
         self.set_carry(src_val < dst_val);
         self.set_overflow(src_sign != dst_sign && dst_sign == res_sign);
+        Ok(())
     }
 
-    fn exec_double_operand_ins(&mut self, ins: &DoubleOperandIns) {
+    fn exec_double_operand_ins(&mut self, ins: &DoubleOperandIns) -> TrapResult<()> {
         use DoubleOperandOpcode::*;
         match ins.op {
-            Mov => self.do_mov(&ins.src, &ins.dst, Size::Word),
-            Cmp => self.do_cmp(&ins.src, &ins.dst, Size::Word),
-            Bis => self.do_bitwise(&ins.src, u32::bitor, &ins.dst, Size::Word, false),
-            Bic => self.do_bitwise(&ins.src, not_and, &ins.dst, Size::Word, false),
-            Bit => self.do_bitwise(&ins.src, u32::bitand, &ins.dst, Size::Word, true),
+            Mov => self.do_mov(&ins.src, &ins.dst, Size::Word)?,
+            Cmp => self.do_cmp(&ins.src, &ins.dst, Size::Word)?,
+            Bis => self.do_bitwise(&ins.src, u32::bitor, &ins.dst, Size::Word, false)?,
+            Bic => self.do_bitwise(&ins.src, not_and, &ins.dst, Size::Word, false)?,
+            Bit => self.do_bitwise(&ins.src, u32::bitand, &ins.dst, Size::Word, true)?,
 
-            Add => self.do_add(&ins.src, &ins.dst, Size::Word),
+            Add => self.do_add(&ins.src, &ins.dst, Size::Word)?,
 
-            MovB => self.do_mov(&ins.src, &ins.dst, Size::Byte),
-            CmpB => self.do_cmp(&ins.src, &ins.dst, Size::Byte),
-            BisB => self.do_bitwise(&ins.src, u32::bitor, &ins.dst, Size::Byte, false),
-            BicB => self.do_bitwise(&ins.src, not_and, &ins.dst, Size::Byte, false),
-            BitB => self.do_bitwise(&ins.src, u32::bitand, &ins.dst, Size::Byte, true),
+            MovB => self.do_mov(&ins.src, &ins.dst, Size::Byte)?,
+            CmpB => self.do_cmp(&ins.src, &ins.dst, Size::Byte)?,
+            BisB => self.do_bitwise(&ins.src, u32::bitor, &ins.dst, Size::Byte, false)?,
+            BicB => self.do_bitwise(&ins.src, not_and, &ins.dst, Size::Byte, false)?,
+            BitB => self.do_bitwise(&ins.src, u32::bitand, &ins.dst, Size::Byte, true)?,
 
-            Sub => self.do_sub(&ins.src, &ins.dst, Size::Word),
+            Sub => self.do_sub(&ins.src, &ins.dst, Size::Word)?,
         }
+        Ok(())
     }
 
-    fn exec_misc_ins(&mut self, ins: &MiscIns) -> ExecRet {
+    fn exec_misc_ins(&mut self, ins: &MiscIns) -> TrapResult<ExecRet> {
         match ins.op {
-            MiscOpcode::Halt => {
-                return ExecRet::Halt;
+            // HALT is illegal outside of kernel mode, and traps instead.
+            MiscOpcode::Halt if self.curr_mode() != ProcessorMode::Kernel => {
+                return Err(Trap::BusError(CpuErrorAccess::ILLEGAL_HALT));
             }
-            MiscOpcode::Rti => self.exec_rti_ins(),
-            _ => panic!(
-                "Instruction {ins:?} (0o{:o}) at pc 0o{:o} not yet implemented",
-                ins.op as u16,
-                self.reg_read_word(Reg::PC)
-            ),
+            MiscOpcode::Halt => return Ok(ExecRet::Halt),
+            MiscOpcode::Rti | MiscOpcode::Rtt => self.exec_rti_ins()?,
+            MiscOpcode::Bpt => return Err(Trap::Trace),
+            MiscOpcode::Iot => return Err(Trap::Iot),
+            MiscOpcode::Reset => self.reset_devices(),
+            MiscOpcode::Wait => unreachable!(), // Handled in run_ins().
         }
-        ExecRet::Ok
+        Ok(ExecRet::Ok)
+    }
+
+    // RESET only has an effect in kernel mode.
+    fn reset_devices(&mut self) {
+        if self.curr_mode() != ProcessorMode::Kernel {
+            return;
+        }
+        self.bus.reset(&mut self.state);
     }
 
     fn exec_branch_ins(&mut self, ins: &BranchIns) {
@@ -612,120 +1540,247 @@ impl Emulator {
         }
     }
 
-    fn exec_jmp_ins(&mut self, ins: &JmpIns) {
-        assert_eq!(ins.op, JmpOpcode::Jmp);
+    fn exec_sob_ins(&mut self, ins: &SobIns) {
+        let val = self.reg_read_word(ins.reg).wrapping_sub(1);
+        self.reg_write_word(ins.reg, val);
+        if val != 0 {
+            let off = (ins.target.unwrap_offset() as i8 as i16) * 2;
+            let pc = self.state.pc().wrapping_add(off as u16);
+            self.reg_write_word(Reg::PC, pc);
+        }
+    }
 
-        let dst = self.resolve(&ins.dst, Size::Word);
-        assert!(!matches!(dst, ResolvedOperand::Reg(_)));
-        let new_pc = match dst {
-            ResolvedOperand::Mem(loc) => loc,
-            dst => self.read_resolved_word(dst),
-        };
-        assert_eq!(new_pc & 0x1, 0);
+    // The count of words, pushed as parameters, are popped along with the MARK
+    // instruction itself, which was pushed just above them.
+    fn exec_mark_ins(&mut self, ins: &MarkIns) -> TrapResult<()> {
+        let count = ins.count.unwrap_val();
+        let sp = self.state.pc().wrapping_add(2 * count);
+        self.reg_write_word(Reg::SP, sp);
+        let r5 = self.reg_read_word(Reg::R5);
+        self.reg_write_word(Reg::PC, r5);
+        let val = self.pop_word()?;
+        self.reg_write_word(Reg::R5, val);
+        Ok(())
+    }
+
+    // SPL is a nop outside of kernel mode.
+    fn exec_spl_ins(&mut self, ins: &SplIns) {
+        if self.curr_mode() == ProcessorMode::Kernel {
+            self.state.get_status_mut().set_prio(ins.prio.unwrap_val());
+        }
+    }
+
+    // The address JMP or JSR jumps to. A register destination is illegal. On
+    // some models, (R)+ jumps to the updated R.
+    fn resolve_jmp_dst(&mut self, dst: &Operand) -> TrapResult<u16> {
+        match self.resolve(dst, Size::Word)? {
+            ResolvedOperand::Reg(_) if self.model.reg_jmp_bus_error() => Err(Trap::BusError(0)),
+            ResolvedOperand::Reg(_) => Err(Trap::ReservedInstruction),
+            ResolvedOperand::Mem(_)
+                if self.model.uses_updated_reg() && dst.mode == AddrMode::AutoInc =>
+            {
+                Ok(self.reg_read_word(dst.reg))
+            }
+            ResolvedOperand::Mem(loc) => Ok(loc),
+        }
+    }
 
+    fn exec_jmp_ins(&mut self, ins: &JmpIns) -> TrapResult<()> {
+        assert_eq!(ins.op, JmpOpcode::Jmp);
+
+        let new_pc = self.resolve_jmp_dst(&ins.dst)?;
         trace!("PC: 0o{:o}: JMP to 0o{new_pc:o}", self.state.pc());
         self.reg_write_word(Reg::PC, new_pc);
+        Ok(())
     }
 
-    fn push_word(&mut self, val: u16) {
-        let sp = self.reg_read_word(Reg::SP) - 2;
+    // Kernel stack references at or below the limit (plus 0o377) are yellow
+    // zone violations, which trap after the instruction completes. 16 words
+    // further down is the red zone, which aborts the instruction and switches
+    // to the emergency stack. The check is only made on pushes and
+    // autodecrements of SP, in kernel mode, as on the 11/45.
+    fn check_stack(&mut self, addr: u16) -> TrapResult<()> {
+        if self.curr_mode() != ProcessorMode::Kernel || self.emergency_stack {
+            return Ok(());
+        }
+        let boundary = self.state.get_stack_limit() | 0o377;
+        if addr > boundary {
+            Ok(())
+        } else if addr > boundary - 0o40 {
+            debug!("Yellow zone stack reference to {addr:#o}");
+            self.yellow_zone = true;
+            Ok(())
+        } else {
+            debug!("Red zone stack reference to {addr:#o}");
+            Err(Trap::RedZone)
+        }
+    }
+
+    fn push_word(&mut self, val: u16) -> TrapResult<()> {
+        let sp = self.reg_read_word(Reg::SP).wrapping_sub(2);
+        self.check_stack(sp)?;
         self.reg_write_word(Reg::SP, sp);
-        self.mem_write_word(sp, val);
+        self.write_word(sp, val)?;
+        Ok(())
     }
 
-    fn pop_word(&mut self) -> u16 {
+    fn pop_word(&mut self) -> TrapResult<u16> {
         let sp = self.reg_read_word(Reg::SP);
-        let val = self.mem_read_word(sp);
-        self.reg_write_word(Reg::SP, sp + 2);
-        val
+        let val = self.read_word(sp)?;
+        self.reg_write_word(Reg::SP, sp.wrapping_add(2));
+        Ok(val)
     }
 
-    fn exec_jsr_ins(&mut self, ins: &JsrIns) {
+    fn exec_jsr_ins(&mut self, ins: &JsrIns) -> TrapResult<()> {
         assert_eq!(ins.op, JsrOpcode::Jsr);
 
-        let dst = self.resolve(&ins.dst, Size::Word);
-        assert!(!matches!(dst, ResolvedOperand::Reg(_)));
-        let new_pc = match dst {
-            ResolvedOperand::Mem(loc) => loc,
-            dst => self.read_resolved_word(dst),
-        };
-        assert_eq!(new_pc & 0x1, 0);
+        let new_pc = self.resolve_jmp_dst(&ins.dst)?;
         let old_val = self.reg_read_word(ins.reg);
-        self.push_word(old_val);
+        self.push_word(old_val)?;
 
         self.reg_write_word(ins.reg, self.state.pc());
         self.reg_write_word(Reg::PC, new_pc);
+        Ok(())
     }
 
-    fn exec_rts_ins(&mut self, ins: &RtsIns) {
+    fn exec_rts_ins(&mut self, ins: &RtsIns) -> TrapResult<()> {
         assert_eq!(ins.op, RtsOpcode::Rts);
         let new_pc = self.reg_read_word(ins.reg);
         self.reg_write_word(Reg::PC, new_pc);
 
-        let old_val = self.pop_word();
+        let old_val = self.pop_word()?;
         self.reg_write_word(ins.reg, old_val);
+        Ok(())
     }
 
-    fn exec_single_operand_ins(&mut self, ins: &SingleOperandIns) {
+    // MFPI/MFPD. The operand's address is computed in the current mode, but
+    // it's read from the previous mode's space. There's no separate I and D
+    // space, so the two are the same.
+    fn exec_move_from_prev(&mut self, src: &Operand) -> TrapResult<()> {
+        let prev = self.state.get_status().get_prev_mode();
+        let src = self.resolve(src, Size::Word)?;
+        let val = match src {
+            ResolvedOperand::Reg(Reg::SP) => self.state.sp_for(prev),
+            ResolvedOperand::Reg(r) => self.reg_read_word(r),
+            ResolvedOperand::Mem(addr) => self.read_word_as(addr, prev)?,
+        };
+        self.push_word(val)?;
+
+        self.set_zero(val == 0);
+        self.set_negative((val >> 15) != 0);
+        self.set_overflow(false);
+        Ok(())
+    }
+
+    // MTPI/MTPD. The value is popped before the destination is resolved.
+    fn exec_move_to_prev(&mut self, dst: &Operand) -> TrapResult<()> {
+        let prev = self.state.get_status().get_prev_mode();
+        let val = self.pop_word()?;
+        let dst = self.resolve(dst, Size::Word)?;
+        match dst {
+            ResolvedOperand::Reg(Reg::SP) => self.state.set_sp_for(prev, val),
+            ResolvedOperand::Reg(r) => self.reg_write_word(r, val),
+            ResolvedOperand::Mem(addr) => self.write_word_as(addr, val, prev)?,
+        }
+
+        self.set_zero(val == 0);
+        self.set_negative((val >> 15) != 0);
+        self.set_overflow(false);
+        Ok(())
+    }
+
+    // Like MOVB, the value is sign extended when the destination is a register.
+    fn exec_mfps(&mut self, dst: &Operand) -> TrapResult<()> {
+        let val = self.state.get_status().to_raw() as u8;
+        let dst = self.resolve(dst, Size::Byte)?;
+        match dst {
+            ResolvedOperand::Reg(r) => self.reg_write_word(r, val as i8 as i16 as u16),
+            ResolvedOperand::Mem(addr) => self.write_byte(addr, val)?,
+        }
+
+        self.set_zero(val == 0);
+        self.set_negative((val >> 7) != 0);
+        self.set_overflow(false);
+        Ok(())
+    }
+
+    fn exec_mtps(&mut self, src: &Operand) -> TrapResult<()> {
+        let src = self.resolve(src, Size::Byte)?;
+        let val = self.read_resolved_byte(src)?;
+        let old = self.state.get_status();
+        let status = old.explicit_update((old.to_raw() & !0xff) | val as u16);
+        self.state.set_status(status);
+        Ok(())
+    }
+
+    fn exec_single_operand_ins(&mut self, ins: &SingleOperandIns) -> TrapResult<()> {
+        use SingleOperandOpcode::*;
+        match ins.op {
+            Mfpi | Mfpd => return self.exec_move_from_prev(&ins.dst),
+            Mtpi | Mtpd => return self.exec_move_to_prev(&ins.dst),
+            Mfps => return self.exec_mfps(&ins.dst),
+            Mtps => return self.exec_mtps(&ins.dst),
+            _ => (),
+        }
+
         let size = if ins.is_byte() {
             Size::Byte
         } else {
             Size::Word
         };
-        let dst = self.resolve(&ins.dst, Size::Word);
-        use SingleOperandOpcode::*;
+        let dst = self.resolve(&ins.dst, Size::Word)?;
         match ins.op {
             Swab => {
-                let val = self.read_resolved_word(dst);
+                let val = self.read_resolved_word(dst)?;
                 let upper = val >> 8;
                 let lower = val & ((1u16 << 8) - 1);
                 let res = (lower << 8) | upper;
 
-                self.write_resolved_word(dst, res);
+                self.write_resolved_word(dst, res)?;
                 self.set_zero((res & 0xff) == 0);
                 self.set_negative((res >> 7) & 0x1 == 1);
                 self.set_carry(false);
                 self.set_overflow(false);
             }
             Clr | ClrB => {
-                self.write_resolved_narrow(dst, 0, size);
+                self.write_resolved_narrow(dst, 0, size)?;
                 self.set_zero(true);
                 self.set_negative(false);
                 self.set_carry(false);
                 self.set_overflow(false);
             }
             Inc | IncB => {
-                let val = self.read_resolved_widen(dst, size);
+                let val = self.read_resolved_widen(dst, size)?;
                 let (res, _) = val.overflowing_add(1);
 
-                self.write_resolved_narrow(dst, res, size);
+                self.write_resolved_narrow(dst, res, size)?;
                 self.set_zero((res & size.mask()) == 0);
                 self.set_negative(size.sign_bit(res) != 0);
                 // Carry not affected
                 self.set_overflow(val == (size.mask() >> 1));
             }
             Dec | DecB => {
-                let val = self.read_resolved_widen(dst, size);
+                let val = self.read_resolved_widen(dst, size)?;
                 let (res, _) = val.overflowing_sub(1);
 
-                self.write_resolved_narrow(dst, res, size);
+                self.write_resolved_narrow(dst, res, size)?;
                 self.set_zero(res & size.mask() == 0);
                 self.set_negative(size.sign_bit(res) != 0);
                 // Carry not affected
                 self.set_overflow(val == size.smallest_signed());
             }
             Neg | NegB => {
-                let val = self.read_resolved_widen(dst, size);
+                let val = self.read_resolved_widen(dst, size)?;
                 let res = (!val).wrapping_add(1);
 
-                self.write_resolved_narrow(dst, res, size);
+                self.write_resolved_narrow(dst, res, size)?;
                 self.set_zero(res & size.mask() == 0);
                 self.set_negative(size.sign_bit(res) != 0);
                 self.set_carry(res & size.mask() != 0);
                 self.set_overflow(val == size.smallest_signed());
             }
             Tst | TstB => {
-                let val = self.read_resolved_widen(dst, size);
+                let val = self.read_resolved_widen(dst, size)?;
                 let (res, _) = 0u32.overflowing_sub(val);
 
                 self.set_zero(res == 0);
@@ -734,10 +1789,10 @@ impl Emulator {
                 self.set_overflow(false);
             }
             Com | ComB => {
-                let val = self.read_resolved_widen(dst, size);
+                let val = self.read_resolved_widen(dst, size)?;
                 let res = !val;
 
-                self.write_resolved_narrow(dst, res, size);
+                self.write_resolved_narrow(dst, res, size)?;
                 self.set_zero(res & size.mask() == 0);
                 self.set_negative(size.sign_bit(res) != 0);
                 self.set_carry(true);
@@ -745,10 +1800,10 @@ impl Emulator {
             }
             Adc | AdcB => {
                 let carry = self.get_carry();
-                let val = self.read_resolved_widen(dst, size);
+                let val = self.read_resolved_widen(dst, size)?;
                 let res = val.wrapping_add(carry as u32);
 
-                self.write_resolved_narrow(dst, res, size);
+                self.write_resolved_narrow(dst, res, size)?;
                 self.set_zero(res & size.mask() == 0);
                 self.set_negative(size.sign_bit(res) != 0);
                 self.set_carry(val == size.mask() && carry);
@@ -756,22 +1811,22 @@ impl Emulator {
             }
             Sbc | SbcB => {
                 let carry = self.get_carry();
-                let val = self.read_resolved_widen(dst, size);
+                let val = self.read_resolved_widen(dst, size)?;
                 let res = val.wrapping_sub(carry as u32);
 
-                self.write_resolved_narrow(dst, res, size);
+                self.write_resolved_narrow(dst, res, size)?;
                 self.set_zero(res & size.mask() == 0);
                 self.set_negative(size.sign_bit(res) != 0);
                 self.set_carry(!((res & size.mask()) == 0 && carry));
                 self.set_overflow(res == size.smallest_signed());
             }
             Ror | RorB => {
-                let val = self.read_resolved_widen(dst, size);
+                let val = self.read_resolved_widen(dst, size)?;
                 let carry = self.get_carry() as u32;
                 let new_carry = val & 0x1;
                 let res = (val >> 1) | (carry << (size.bits() - 1));
 
-                self.write_resolved_narrow(dst, res, size);
+                self.write_resolved_narrow(dst, res, size)?;
                 self.set_zero((res & size.mask()) == 0);
                 self.set_negative(size.sign_bit(res) != 0);
                 self.set_carry(new_carry != 0);
@@ -780,12 +1835,12 @@ impl Emulator {
                 self.set_overflow((n ^ new_carry) != 0);
             }
             Rol | RolB => {
-                let val = self.read_resolved_widen(dst, size);
+                let val = self.read_resolved_widen(dst, size)?;
                 let carry = self.get_carry() as u32;
                 let new_carry = (val >> (size.bits() - 1)) & 0x1;
                 let res = (val << 1) | carry;
 
-                self.write_resolved_narrow(dst, res, size);
+                self.write_resolved_narrow(dst, res, size)?;
                 self.set_zero((res & size.mask()) == 0);
                 self.set_negative(size.sign_bit(res) != 0);
                 self.set_carry(new_carry != 0);
@@ -794,11 +1849,11 @@ impl Emulator {
                 self.set_overflow((n ^ new_carry) != 0);
             }
             Asr => {
-                let val = self.read_resolved_word(dst);
+                let val = self.read_resolved_word(dst)?;
                 let new_carry = val & 0x1;
                 let res = (val as i16) >> 1; // i16 for arithmetic shift
 
-                self.write_resolved_word(dst, res as u16);
+                self.write_resolved_word(dst, res as u16)?;
                 self.set_zero(res == 0);
                 self.set_negative(res >> 15 != 0);
                 self.set_carry(new_carry != 0);
@@ -807,11 +1862,11 @@ impl Emulator {
                 self.set_overflow((n ^ new_carry) != 0);
             }
             AsrB => {
-                let val = self.read_resolved_byte(dst);
+                let val = self.read_resolved_byte(dst)?;
                 let new_carry = val & 0x1;
                 let res = (val as i8) >> 1; // i16 for arithmetic shift
 
-                self.write_resolved_byte(dst, res as u8);
+                self.write_resolved_byte(dst, res as u8)?;
                 self.set_zero(res == 0);
                 self.set_negative(res >> 7 != 0);
                 self.set_carry(new_carry != 0);
@@ -820,11 +1875,11 @@ impl Emulator {
                 self.set_overflow((n ^ new_carry) != 0);
             }
             Asl | AslB => {
-                let val = self.read_resolved_widen(dst, size);
+                let val = self.read_resolved_widen(dst, size)?;
                 let res = val << 1;
                 let new_carry = size.sign_bit(val);
 
-                self.write_resolved_narrow(dst, res, size);
+                self.write_resolved_narrow(dst, res, size)?;
                 self.set_zero(res & size.mask() == 0);
                 self.set_negative(size.sign_bit(res) != 0);
                 self.set_carry(new_carry != 0);
@@ -832,12 +1887,20 @@ impl Emulator {
                 let n = self.get_negative() as u32;
                 self.set_overflow((n ^ new_carry) != 0);
             }
+            Sxt => {
+                let n = self.get_negative();
+                self.write_resolved_word(dst, if n { 0xffff } else { 0 })?;
+                self.set_zero(!n);
+                self.set_overflow(false);
+            }
+            Mfpi | Mtpi | Mfpd | Mtpd | Mfps | Mtps => unreachable!(),
         }
+        Ok(())
     }
 
-    fn exec_eis_ins(&mut self, ins: &EisIns) {
-        let operand = self.resolve(&ins.operand, Size::Word);
-        let operand_val = self.read_resolved_word(operand);
+    fn exec_eis_ins(&mut self, ins: &EisIns) -> TrapResult<()> {
+        let operand = self.resolve(&ins.operand, Size::Word)?;
+        let operand_val = self.read_resolved_word(operand)?;
 
         use EisOpcode::*;
         if ins.op == Xor {
@@ -846,13 +1909,13 @@ impl Emulator {
 
             let val = self.reg_read_word(ins.reg);
             let res = dst_val ^ val;
-            self.write_resolved_word(dst, res);
+            self.write_resolved_word(dst, res)?;
 
             self.set_negative(Size::Word.sign_bit(res as u32) != 0);
             self.set_zero(res == 0);
             self.set_overflow(false);
             // Carry unaffected
-            return;
+            return Ok(());
         }
 
         let src_val = operand_val;
@@ -876,16 +1939,18 @@ impl Emulator {
                 }
             }
             Div => {
+                // With an odd register, it's both halves, and the remainder
+                // is what's left in it.
                 let reg_num = ins.reg.to_u16().unwrap();
-                assert_eq!(reg_num & 0x1, 0);
-                let upper_reg = Reg::from_u16(reg_num + 1).unwrap();
+                let upper_reg = Reg::from_u16(reg_num | 0x1).unwrap();
                 let upper = self.reg_read_word(upper_reg);
                 let dividend = ((upper as i32) << u16::BITS) | (reg_val as i32);
                 let divisor = src_val as i32;
 
                 if divisor != 0 {
-                    let quot = dividend / divisor;
-                    let rem = dividend % divisor;
+                    // Wide enough for the largest negative over -1.
+                    let quot = dividend as i64 / divisor as i64;
+                    let rem = dividend.wrapping_rem(divisor);
                     if rem != 0 {
                         debug_assert_eq!(rem < 0, dividend < 0);
                     }
@@ -927,7 +1992,7 @@ impl Emulator {
                         } else {
                             false
                         };
-                        let new_val = reg_val << shift;
+                        let new_val = reg_val.checked_shl(shift as u32).unwrap_or(0);
                         (new_val, carry)
                     }
                     Ordering::Equal => (reg_val, false),
@@ -935,7 +2000,7 @@ impl Emulator {
                         // Right
                         shift *= -1;
                         let carry = ((reg_val >> (shift - 1)) & 0x1) != 0;
-                        let new_val = ((reg_val as i16) >> shift) as u16;
+                        let new_val = ((reg_val as i16) >> shift.min(15)) as u16;
                         (new_val, carry)
                     }
                 };
@@ -956,13 +2021,10 @@ impl Emulator {
                 let mut shift: i16 = ((shift as i16) << NONSIG_BITS) >> NONSIG_BITS; // Sign extend
                 assert!((-32i16..=31i16).contains(&shift));
 
+                // With an odd register, the register is both halves, and ends
+                // up with the low half of the result.
                 let reg_raw = ins.reg.to_u16().unwrap();
-                let reg_upper = Reg::from_u16(reg_raw + 1).unwrap();
-
-                // The manual actually specifies for odd registers and a negative shift, its a
-                // rotate; I can't see using it and think it would be a cause for errors.
-                assert!((reg_raw & 0x1) == 0, "Odd registers not supported for Ashc");
-                assert!(ins.reg != Reg::PC);
+                let reg_upper = Reg::from_u16(reg_raw | 0x1).unwrap();
                 let upper = self.reg_read_word(reg_upper);
                 let wide_val = (reg_val as u32) | ((upper as u32) << u16::BITS);
 
@@ -974,7 +2036,7 @@ impl Emulator {
                         } else {
                             false
                         };
-                        let new_val = wide_val << shift;
+                        let new_val = wide_val.checked_shl(shift as u32).unwrap_or(0);
                         (new_val, carry)
                     }
                     Ordering::Equal => (wide_val, false),
@@ -982,12 +2044,12 @@ impl Emulator {
                         // Right
                         shift *= -1;
                         let carry = ((wide_val >> (shift - 1)) & 0x1) != 0;
-                        let new_val = ((wide_val as i32) >> shift) as u32;
+                        let new_val = ((wide_val as i32) >> shift.min(31)) as u32;
                         (new_val, carry)
                     }
                 };
-                self.reg_write_word(ins.reg, (new_val & ((0x1u32 << u16::BITS) - 1)) as u16);
                 self.reg_write_word(reg_upper, (new_val >> u16::BITS) as u16);
+                self.reg_write_word(ins.reg, (new_val & ((0x1u32 << u16::BITS) - 1)) as u16);
 
                 self.set_negative(((new_val >> (u32::BITS - 1)) & 0x1) != 0);
                 self.set_zero(new_val == 0);
@@ -998,68 +2060,523 @@ impl Emulator {
             }
             Xor => unreachable!(),
         }
+        Ok(())
     }
 
     fn exec_cc_ins(&mut self, ins: &CCIns) {
-        let op = ins.op as u16;
-        let bits = op & 0xf;
-        let set = ((op >> 4) & 0x1) != 0;
-        if set {
-            let curr = self.get_flags();
-            self.set_flags(curr | bits);
+        let curr = self.get_flags();
+        match ins.op {
+            CCOpcode::Set => self.set_flags(curr | ins.flags),
+            CCOpcode::Clear => self.set_flags(curr & !ins.flags),
+        }
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    // FP11 floating point
+    ///////////////////////////////////////////////////////////////////////////
+
+    // Record an error, and abandon the rest of the instruction. This traps,
+    // unless FID is set.
+    fn fp_error(&mut self, err: FpError) -> Trap {
+        debug!("Floating point error {err:?} at pc {:#o}", self.ins_pc);
+        let pc = self.ins_pc;
+        self.state.get_fpu_mut().record_error(err, pc);
+        Trap::FloatingPoint
+    }
+
+    fn is_immediate(arg: &Operand) -> bool {
+        arg.mode == AddrMode::AutoInc && arg.reg == Reg::PC
+    }
+
+    // Mode 0 is an accumulator; AC6 and AC7 don't exist. Immediates are a
+    // single word, with the rest zero.
+    fn resolve_fp(&mut self, arg: &Operand, prec: Precision) -> TrapResult<FpOperand> {
+        let words = prec.words();
+        match self.resolve_len(arg, words * WORD_SIZE)? {
+            ResolvedOperand::Reg(r) => {
+                let ac = r.to_usize().unwrap();
+                if ac >= Ac::NUM_ACS {
+                    return Err(self.fp_error(FpError::OpCode));
+                }
+                Ok(FpOperand::Ac(ac))
+            }
+            ResolvedOperand::Mem(addr) if Self::is_immediate(arg) => Ok(FpOperand::Mem(addr, 1)),
+            ResolvedOperand::Mem(addr) => Ok(FpOperand::Mem(addr, words)),
+        }
+    }
+
+    fn read_fp(&mut self, loc: FpOperand, prec: Precision) -> TrapResult<u64> {
+        match loc {
+            FpOperand::Ac(ac) => Ok(Fpu::narrow(self.state.get_fpu().get_ac(ac), prec)),
+            FpOperand::Mem(addr, words) => {
+                let mut raw = 0;
+                for i in 0..words {
+                    let word = self.read_word(addr.wrapping_add(i * WORD_SIZE))?;
+                    raw |= (word as u64) << (48 - 16 * i);
+                }
+                Ok(raw)
+            }
+        }
+    }
+
+    fn write_fp(&mut self, loc: FpOperand, raw: u64, prec: Precision) -> TrapResult<()> {
+        match loc {
+            FpOperand::Ac(ac) => {
+                let raw = Fpu::narrow(raw, prec);
+                self.state.get_fpu_mut().set_ac(ac, raw);
+            }
+            FpOperand::Mem(addr, words) => {
+                for i in 0..words {
+                    let word = (raw >> (48 - 16 * i)) as u16;
+                    self.write_word(addr.wrapping_add(i * WORD_SIZE), word)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // With FIUV set, using -0 as a source traps.
+    fn check_undefined(&mut self, raw: u64) -> TrapResult<()> {
+        let fpu = self.state.get_fpu();
+        if Fpu::is_undefined(raw) && fpu.error_enabled(FpError::UndefinedVariable) {
+            return Err(self.fp_error(FpError::UndefinedVariable));
+        }
+        Ok(())
+    }
+
+    fn read_fsrc(&mut self, arg: &Operand, prec: Precision) -> TrapResult<Float> {
+        let loc = self.resolve_fp(arg, prec)?;
+        let raw = self.read_fp(loc, prec)?;
+        self.check_undefined(raw)?;
+        Ok(Float::unpack(raw))
+    }
+
+    // Integers are a word, or with FL set, a long with the high word first. A
+    // register or an immediate is only the high word of a long.
+    fn read_fp_int(&mut self, arg: &Operand, long: bool) -> TrapResult<i32> {
+        let res = self.resolve_len(arg, if long { 2 * WORD_SIZE } else { WORD_SIZE })?;
+        let high = self.read_resolved_word(res)?;
+        if !long {
+            return Ok(high as i16 as i32);
+        }
+        let low = match res {
+            ResolvedOperand::Mem(addr) if !Self::is_immediate(arg) => {
+                self.read_word(addr.wrapping_add(WORD_SIZE))?
+            }
+            _ => 0,
+        };
+        Ok((((high as u32) << 16) | low as u32) as i32)
+    }
+
+    fn write_fp_int(&mut self, arg: &Operand, val: i32, long: bool) -> TrapResult<()> {
+        let res = self.resolve_len(arg, if long { 2 * WORD_SIZE } else { WORD_SIZE })?;
+        if !long {
+            return self.write_resolved_word(res, val as u16);
+        }
+        self.write_resolved_word(res, (val >> 16) as u16)?;
+        if let ResolvedOperand::Mem(addr) = res
+            && !Self::is_immediate(arg)
+        {
+            self.write_word(addr.wrapping_add(WORD_SIZE), val as u16)?;
+        }
+        Ok(())
+    }
+
+    // What to store for a result, whether it overflowed, and the error to
+    // trap with once it's stored. Out of range results are stored with their
+    // exponent wrapped if they trap, and as zero otherwise.
+    fn fp_range(&self, f: Float) -> (Float, bool, Option<FpError>) {
+        let err = if f.overflowed() {
+            FpError::Overflow
+        } else if f.underflowed() {
+            FpError::Underflow
+        } else {
+            return (f, false, None);
+        };
+        let overflow = err == FpError::Overflow;
+        if self.state.get_fpu().error_enabled(err) {
+            (f, overflow, Some(err))
         } else {
-            let curr = self.get_flags();
-            self.set_flags(curr & !bits);
+            (Float::ZERO, overflow, None)
         }
     }
 
-    fn interrupt(&mut self, vector: u16) {
-        let old_ps = self.state.get_status().to_raw();
-        let old_pc = self.state.pc();
-        self.push_word(old_ps);
-        self.push_word(old_pc);
+    fn set_fp_flags(&mut self, raw: u64, v: bool, c: bool) {
+        let f = Float::unpack(raw);
+        self.state
+            .get_fpu_mut()
+            .set_flags(f.neg && !f.is_zero(), f.is_zero(), v, c);
+    }
+
+    fn fp_store_result(&mut self, loc: FpOperand, f: Float, prec: Precision) -> TrapResult<()> {
+        let (f, overflow, err) = self.fp_range(f);
+        let raw = Fpu::narrow(f.pack(), prec);
+        self.write_fp(loc, raw, prec)?;
+        self.set_fp_flags(raw, overflow, false);
+        match err {
+            Some(err) => Err(self.fp_error(err)),
+            None => Ok(()),
+        }
+    }
 
-        let new_pc = self.mem_read_word(vector);
-        let new_ps = self.mem_read_word(vector + 2);
+    // The integer conversions also set the CPU's condition codes.
+    fn copy_fp_flags(&mut self) {
+        let flags = self.state.get_fpu().get_flags();
+        self.set_flags(flags);
+    }
+
+    fn exec_fp_misc_ins(&mut self, ins: &FpMiscIns) {
+        use FpMiscOpcode::*;
+        let fpu = self.state.get_fpu_mut();
+        match ins.op {
+            Cfcc => self.copy_fp_flags(),
+            Setf => fpu.set_double(false),
+            Setd => fpu.set_double(true),
+            Seti => fpu.set_long_ints(false),
+            Setl => fpu.set_long_ints(true),
+        }
+    }
+
+    fn exec_fp_single_operand_ins(&mut self, ins: &FpSingleOperandIns) -> TrapResult<()> {
+        use FpSingleOperandOpcode::*;
+        let prec = self.state.get_fpu().precision();
+        match ins.op {
+            Ldfps => {
+                let src = self.resolve(&ins.operand, Size::Word)?;
+                let val = self.read_resolved_word(src)?;
+                self.state.get_fpu_mut().set_fps(val);
+            }
+            Stfps => {
+                let dst = self.resolve(&ins.operand, Size::Word)?;
+                let fps = self.state.get_fpu().get_fps();
+                self.write_resolved_word(dst, fps)?;
+            }
+            // The FEC, then the FEA, unless the destination is a register or
+            // immediate.
+            Stst => {
+                let dst = self.resolve_len(&ins.operand, 2 * WORD_SIZE)?;
+                let fpu = self.state.get_fpu();
+                let (fec, fea) = (fpu.get_fec(), fpu.get_fea());
+                self.write_resolved_word(dst, fec)?;
+                if let ResolvedOperand::Mem(addr) = dst
+                    && !Self::is_immediate(&ins.operand)
+                {
+                    self.write_word(addr.wrapping_add(WORD_SIZE), fea)?;
+                }
+            }
+            Clrf => {
+                let dst = self.resolve_fp(&ins.operand, prec)?;
+                self.write_fp(dst, 0, prec)?;
+                self.set_fp_flags(0, false, false);
+            }
+            Tstf => {
+                let src = self.read_fsrc(&ins.operand, prec)?;
+                self.set_fp_flags(src.pack(), false, false);
+            }
+            Absf | Negf => {
+                let dst = self.resolve_fp(&ins.operand, prec)?;
+                let raw = self.read_fp(dst, prec)?;
+                self.check_undefined(raw)?;
+                let val = Float::unpack(raw);
+                let res = if ins.op == Absf {
+                    fpu::abs(val)
+                } else {
+                    fpu::negate(val)
+                };
+                self.write_fp(dst, res.pack(), prec)?;
+                self.set_fp_flags(res.pack(), false, false);
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_fp_ins(&mut self, ins: &FpIns) -> TrapResult<()> {
+        use FpOpcode::*;
+        let fpu = self.state.get_fpu();
+        let prec = fpu.precision();
+        let other = fpu.other_precision();
+        let truncate = fpu.truncate();
+        let long = fpu.long_ints();
+        let ac = ins.ac.to_usize().unwrap();
+        let ac_raw = Fpu::narrow(fpu.get_ac(ac), prec);
+        let ac_val = Float::unpack(ac_raw);
+
+        match ins.op {
+            Ldf => {
+                let src = self.read_fsrc(&ins.operand, prec)?;
+                self.fp_store_result(FpOperand::Ac(ac), src, prec)?;
+            }
+            Stf => {
+                let dst = self.resolve_fp(&ins.operand, prec)?;
+                self.write_fp(dst, ac_raw, prec)?;
+            }
+            Addf | Subf | Mulf | Divf => {
+                let src = self.read_fsrc(&ins.operand, prec)?;
+                let res = match ins.op {
+                    Addf => fpu::add(ac_val, src, prec, truncate),
+                    Subf => fpu::sub(ac_val, src, prec, truncate),
+                    Mulf => fpu::mul(ac_val, src, prec, truncate),
+                    Divf => {
+                        if src.is_zero() {
+                            return Err(self.fp_error(FpError::DivideByZero));
+                        }
+                        fpu::div(ac_val, src, prec, truncate)
+                    }
+                    _ => unreachable!(),
+                };
+                self.fp_store_result(FpOperand::Ac(ac), res, prec)?;
+            }
+            // The fraction goes in the AC, and with an even AC, the integer part
+            // in the next one.
+            Modf => {
+                let src = self.read_fsrc(&ins.operand, prec)?;
+                let (int, frac) = fpu::modf(ac_val, src, prec, truncate);
+                let (int, overflow, err) = self.fp_range(int);
+                if ac.is_multiple_of(2) {
+                    self.write_fp(FpOperand::Ac(ac + 1), int.pack(), prec)?;
+                }
+                self.fp_store_result(FpOperand::Ac(ac), frac, prec)?;
+                if overflow {
+                    let flags = self.state.get_fpu().get_flags();
+                    self.state
+                        .get_fpu_mut()
+                        .set_flags(false, flags & Fpu::FZ != 0, true, false);
+                }
+                if let Some(err) = err {
+                    return Err(self.fp_error(err));
+                }
+            }
+            Cmpf => {
+                let src = self.read_fsrc(&ins.operand, prec)?;
+                let ord = fpu::cmp(src, ac_val);
+                self.state
+                    .get_fpu_mut()
+                    .set_flags(ord.is_lt(), ord.is_eq(), false, false);
+            }
+            Ldcdf => {
+                let src = self.read_fsrc(&ins.operand, other)?;
+                let res = fpu::convert(src, prec, truncate);
+                self.fp_store_result(FpOperand::Ac(ac), res, prec)?;
+            }
+            Stcfd => {
+                let res = fpu::convert(ac_val, other, truncate);
+                let dst = self.resolve_fp(&ins.operand, other)?;
+                self.fp_store_result(dst, res, other)?;
+            }
+            Ldcif => {
+                let src = self.read_fp_int(&ins.operand, long)?;
+                let res = fpu::from_int(src, prec, truncate);
+                self.fp_store_result(FpOperand::Ac(ac), res, prec)?;
+            }
+            // Out of range values store 0 and set C.
+            Stcfi => {
+                let (val, err) = match fpu::to_int(ac_val, long) {
+                    Some(val) => (val, false),
+                    None => (0, true),
+                };
+                self.write_fp_int(&ins.operand, val, long)?;
+                self.state
+                    .get_fpu_mut()
+                    .set_flags(val < 0, val == 0, false, err);
+                self.copy_fp_flags();
+                if err && self.state.get_fpu().error_enabled(FpError::IntConversion) {
+                    return Err(self.fp_error(FpError::IntConversion));
+                }
+            }
+            Stexp => {
+                let exp = (ac_val.exp - Float::EXP_BIAS) as i16;
+                let dst = self.resolve(&ins.operand, Size::Word)?;
+                self.write_resolved_word(dst, exp as u16)?;
+                self.state
+                    .get_fpu_mut()
+                    .set_flags(exp < 0, exp == 0, false, false);
+                self.copy_fp_flags();
+            }
+            Ldexp => {
+                let src = self.resolve(&ins.operand, Size::Word)?;
+                let exp = self.read_resolved_word(src)? as i16;
+                let res = Float {
+                    exp: exp as i32 + Float::EXP_BIAS,
+                    frac: ac_val.frac | Float::FRAC_TOP,
+                    ..ac_val
+                };
+                self.fp_store_result(FpOperand::Ac(ac), res, prec)?;
+            }
+        }
+        Ok(())
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    // FIS floating point
+    ///////////////////////////////////////////////////////////////////////////
+
+    // The register points to B, with A above it. A op B replaces A, and B is
+    // popped. On an error, the operands and register are left alone, and the
+    // condition codes give the cause.
+    fn exec_fis_ins(&mut self, ins: &FisIns) -> TrapResult<()> {
+        use FisOpcode::*;
+        let prec = Precision::Single;
+        let b_addr = self.reg_read_word(ins.reg);
+        let a_addr = b_addr.wrapping_add(2 * WORD_SIZE);
+        let b = Float::unpack(self.read_fp(FpOperand::Mem(b_addr, 2), prec)?);
+        let a = Float::unpack(self.read_fp(FpOperand::Mem(a_addr, 2), prec)?);
+
+        let res = match ins.op {
+            Fadd => fpu::add(a, b, prec, false),
+            Fsub => fpu::sub(a, b, prec, false),
+            Fmul => fpu::mul(a, b, prec, false),
+            Fdiv => {
+                if b.is_zero() {
+                    return Err(self.fis_error(false, true));
+                }
+                fpu::div(a, b, prec, false)
+            }
+        };
+        if res.overflowed() {
+            return Err(self.fis_error(false, false));
+        }
+        if res.underflowed() {
+            return Err(self.fis_error(true, false));
+        }
+
+        self.write_fp(FpOperand::Mem(a_addr, 2), res.pack(), prec)?;
+        self.reg_write_word(ins.reg, a_addr);
+        self.set_negative(res.neg && !res.is_zero());
+        self.set_zero(res.is_zero());
+        self.set_overflow(false);
+        self.set_carry(false);
+        Ok(())
+    }
+
+    // V is always set; N means underflow, and C divide by zero.
+    fn fis_error(&mut self, underflow: bool, divide_by_zero: bool) -> Trap {
+        debug!("FIS error at pc {:#o}", self.ins_pc);
+        self.set_negative(underflow);
+        self.set_zero(false);
+        self.set_overflow(true);
+        self.set_carry(divide_by_zero);
+        Trap::Fis
+    }
+
+    fn trap(&mut self, trap: Trap) -> ExecRet {
+        debug!("Trap {trap:?} at pc {:#o}", self.state.pc());
+        self.record_trap_cause(trap);
+        if trap == Trap::FloatingPoint && self.state.get_fpu().interrupts_disabled() {
+            return ExecRet::Ok;
+        }
+        if trap == Trap::RedZone {
+            let old_ps = self.state.get_status().to_raw();
+            let old_pc = self.state.pc();
+            return self.emergency_trap(old_ps, old_pc);
+        }
+        self.interrupt(trap.vector())
+    }
+
+    fn record_trap_cause(&mut self, trap: Trap) {
+        let cause = match trap {
+            Trap::BusError(cause) => cause,
+            Trap::RedZone => CpuErrorAccess::RED_ZONE,
+            _ => return,
+        };
+        let err = self.state.get_cpu_error();
+        self.state.set_cpu_error(err | cause);
+    }
+
+    // Vectors are always fetched from kernel space; the old PC and PS are then
+    // pushed onto the stack of the mode being entered.
+    fn try_interrupt(&mut self, vector: u16, old_ps: u16, old_pc: u16) -> TrapResult<()> {
+        let new_pc = self.read_word_as(vector, ProcessorMode::Kernel)?;
+        let new_ps = self.read_word_as(vector + 2, ProcessorMode::Kernel)?;
         debug!(
             "Interrupt; saving pc {old_pc:#o} and ps {old_ps:#o}; loading pc {new_pc:#o}, ps {new_ps:#o}"
         );
+        let mut status = Status::from_raw(new_ps);
+        status.set_prev_mode(Status::from_raw(old_ps).get_curr_mode());
+        self.state.set_status(status);
+        self.push_word(old_ps)?;
+        self.push_word(old_pc)?;
         self.reg_write_word(Reg::PC, new_pc);
-        self.state.set_status(Status::from_raw(new_ps));
+        Ok(())
+    }
+
+    // A fault while taking an interrupt or trap is a double fault, handled
+    // with the emergency stack.
+    fn interrupt(&mut self, vector: u16) -> ExecRet {
+        if self.throttle.ins_time_ns().is_none() {
+            self.elapse(self.model.timing().trap as u64);
+        }
+        let old_ps = self.state.get_status().to_raw();
+        let old_pc = self.state.pc();
+        let Err(trap) = self.try_interrupt(vector, old_ps, old_pc) else {
+            return ExecRet::Ok;
+        };
+
+        debug!("Double fault ({trap:?}) while taking interrupt through {vector:#o}");
+        self.record_trap_cause(trap);
+        self.emergency_trap(old_ps, old_pc)
     }
 
-    fn exec_trap_ins(&mut self, ins: &TrapIns) {
+    // The old PC and PS are pushed onto an emergency stack at 4 (so they end
+    // up in 0 and 2), and it traps through vector 4. If even that fails, the
+    // processor halts.
+    fn emergency_trap(&mut self, old_ps: u16, old_pc: u16) -> ExecRet {
+        self.state.set_status(Status::new());
+        self.reg_write_word(Reg::SP, Self::EMERGENCY_SP);
+        self.emergency_stack = true;
+        let res = self.try_interrupt(Trap::BUS_ERROR_VECTOR, old_ps, old_pc);
+        self.emergency_stack = false;
+        match res {
+            Ok(()) => ExecRet::Ok,
+            Err(trap) => {
+                debug!("Fault ({trap:?}) on emergency stack; halting");
+                ExecRet::DoubleFault
+            }
+        }
+    }
+
+    fn exec_trap_ins(&mut self, ins: &TrapIns) -> ExecRet {
         match ins.op {
             TrapOpcode::Emt => self.interrupt(0o30),
             TrapOpcode::Trap => self.interrupt(0o34),
         }
     }
 
-    fn exec_rti_ins(&mut self) {
-        let new_pc = self.pop_word();
-        let new_ps = self.pop_word();
-        debug!("RTI to pc {new_pc:#o}, ps {new_ps:#o}");
+    fn exec_rti_ins(&mut self) -> TrapResult<()> {
+        let new_pc = self.pop_word()?;
+        let new_ps = self.pop_word()?;
+        debug!("RTI/RTT to pc {new_pc:#o}, ps {new_ps:#o}");
         self.reg_write_word(Reg::PC, new_pc);
-        self.state.set_status(Status::from_raw(new_ps));
+        let status = self.state.get_status().protected_update(new_ps);
+        self.state.set_status(status);
+        Ok(())
     }
 
-    fn exec(&mut self, ins: &Ins) -> ExecRet {
+    fn exec(&mut self, ins: &Ins) -> TrapResult<ExecRet> {
         match ins {
-            Ins::DoubleOperand(ins) => self.exec_double_operand_ins(ins),
+            Ins::DoubleOperand(ins) => self.exec_double_operand_ins(ins)?,
             Ins::Branch(ins) => self.exec_branch_ins(ins),
-            Ins::Jmp(ins) => self.exec_jmp_ins(ins),
-            Ins::Jsr(ins) => self.exec_jsr_ins(ins),
-            Ins::Rts(ins) => self.exec_rts_ins(ins),
-            Ins::SingleOperand(ins) => self.exec_single_operand_ins(ins),
-            Ins::Eis(ins) => self.exec_eis_ins(ins),
+            Ins::Jmp(ins) => self.exec_jmp_ins(ins)?,
+            Ins::Jsr(ins) => self.exec_jsr_ins(ins)?,
+            Ins::Rts(ins) => self.exec_rts_ins(ins)?,
+            Ins::SingleOperand(ins) => self.exec_single_operand_ins(ins)?,
+            Ins::Eis(ins) => self.exec_eis_ins(ins)?,
             Ins::CC(ins) => self.exec_cc_ins(ins),
             Ins::Misc(ins) => {
                 return self.exec_misc_ins(ins);
             }
-            Ins::Trap(ins) => self.exec_trap_ins(ins),
+            Ins::Trap(ins) => {
+                return Ok(self.exec_trap_ins(ins));
+            }
+            Ins::Sob(ins) => self.exec_sob_ins(ins),
+            Ins::Mark(ins) => self.exec_mark_ins(ins)?,
+            Ins::Spl(ins) => self.exec_spl_ins(ins),
+            Ins::FpMisc(ins) => self.exec_fp_misc_ins(ins),
+            Ins::FpSingleOperand(ins) => self.exec_fp_single_operand_ins(ins)?,
+            Ins::Fp(ins) => self.exec_fp_ins(ins)?,
+            Ins::Fis(ins) => self.exec_fis_ins(ins)?,
         }
 
-        ExecRet::Ok
+        Ok(ExecRet::Ok)
     }
 }
 
@@ -1069,6 +2586,10 @@ impl Default for Emulator {
     }
 }
 
+fn not_and(src: u32, dst: u32) -> u32 {
+    !src & dst
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -1215,7 +2736,3 @@ mod tests {
         assert_eq!(emu.reg_read_word(Reg::R2), 0);
     }
 }
-
-fn not_and(src: u32, dst: u32) -> u32 {
-    !src & dst
-}
@@ -1,10 +1,25 @@
-use common::asm::{NUM_REGS, Reg};
+use crate::fpu::Fpu;
+use crate::mmu::Mmu;
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
+use common::asm::{Ins, NUM_REGS, Reg};
+use common::constants::{MAX_INS_WORDS, MEM_END, MMIO_PHYS_START};
 
-use bytemuck::cast_slice;
 use log::trace;
-use num_traits::ToPrimitive;
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::{FromPrimitive, ToPrimitive};
+
+use std::io;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+pub enum ProcessorMode {
+    Kernel = 0,
+    Supervisor,
+    Illegal,
+    User,
+}
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Status(u16);
 
 impl Status {
@@ -24,6 +39,10 @@ impl Status {
     const PRIO: u16 = 5;
     const PRIO_MASK: u16 = 0x7;
 
+    const CURR_MODE: u16 = 14;
+    const PREV_MODE: u16 = 12;
+    const MODE_MASK: u16 = 0x3;
+
     pub fn new() -> Status {
         Default::default()
     }
@@ -100,23 +119,92 @@ impl Status {
         self.0 &= !(Self::PRIO_MASK << Self::PRIO);
         self.0 |= val << Self::PRIO;
     }
+
+    pub fn get_curr_mode(&self) -> ProcessorMode {
+        ProcessorMode::from_u16((self.0 >> Self::CURR_MODE) & Self::MODE_MASK).unwrap()
+    }
+
+    pub fn set_curr_mode(&mut self, mode: ProcessorMode) {
+        self.0 &= !(Self::MODE_MASK << Self::CURR_MODE);
+        self.0 |= mode.to_u16().unwrap() << Self::CURR_MODE;
+    }
+
+    pub fn get_prev_mode(&self) -> ProcessorMode {
+        ProcessorMode::from_u16((self.0 >> Self::PREV_MODE) & Self::MODE_MASK).unwrap()
+    }
+
+    pub fn set_prev_mode(&mut self, mode: ProcessorMode) {
+        self.0 &= !(Self::MODE_MASK << Self::PREV_MODE);
+        self.0 |= mode.to_u16().unwrap() << Self::PREV_MODE;
+    }
+
+    // Outside of kernel mode, a new PS (from RTI, or a write to the PSW) can't
+    // lower the mode or change the priority: the mode bits can only be set, and
+    // the priority is kept.
+    pub fn protected_update(&self, new: u16) -> Status {
+        if self.get_curr_mode() == ProcessorMode::Kernel {
+            return Status(new);
+        }
+        let modes = (Self::MODE_MASK << Self::CURR_MODE) | (Self::MODE_MASK << Self::PREV_MODE);
+        let prio = Self::PRIO_MASK << Self::PRIO;
+        let kept = modes | prio;
+        Status((new & !kept) | ((self.0 | new) & modes) | (self.0 & prio))
+    }
+
+    // An explicit write, to the PSW or by MTPS, is protected like RTI, and
+    // also can't change the T bit.
+    pub fn explicit_update(&self, new: u16) -> Status {
+        let mut status = self.protected_update(new);
+        status.set_t(self.get_t());
+        status
+    }
 }
 
 // This is separate so a mutable borrow can be passed to the MMIO handlers.
 pub struct EmulatorState {
     num_ins: usize,
+    time_ns: u64,
     mem: Vec<u8>,
     regs: [u16; NUM_REGS],
+    sps: [u16; 4], // Stack pointers of the modes not running; the current one is in regs.
+    status: Status,
+    cpu_error: u16,
+    stack_limit: u16,
+    mmu: Mmu,
+    fpu: Fpu,
+    mem_journal: Option<Vec<(u32, u8)>>, // Old values of the bytes written, while journaling.
+    decoded: Vec<Option<Arc<Ins>>>, // Decoded instructions, by physical word, below the I/O page.
+}
+
+// Everything in EmulatorState but memory, so an instruction can be undone.
+#[derive(Debug, Clone)]
+pub struct CpuState {
+    num_ins: usize,
+    time_ns: u64,
+    regs: [u16; NUM_REGS],
+    sps: [u16; 4],
     status: Status,
+    cpu_error: u16,
+    stack_limit: u16,
+    mmu: Mmu,
+    fpu: Fpu,
 }
 
 impl EmulatorState {
     pub fn new() -> Self {
         EmulatorState {
             num_ins: 0usize,
-            mem: vec![0; (u16::MAX as usize) + 1],
+            time_ns: 0,
+            mem: vec![0; MEM_END as usize],
             regs: [0; NUM_REGS],
+            sps: [0; 4],
             status: Status::new(),
+            cpu_error: 0,
+            stack_limit: 0,
+            mmu: Mmu::new(),
+            fpu: Fpu::new(),
+            mem_journal: None,
+            decoded: vec![None; (MMIO_PHYS_START / 2) as usize],
         }
     }
 
@@ -124,33 +212,145 @@ impl EmulatorState {
         self.num_ins += 1;
     }
 
-    pub fn mem_read_byte(&self, addr: u16) -> u8 {
+    // Simulated time since the emulator started.
+    pub fn get_time_ns(&self) -> u64 {
+        self.time_ns
+    }
+
+    pub fn advance_time(&mut self, ns: u64) {
+        self.time_ns += ns;
+    }
+
+    // The PSW is restored as is, since the saved SP already belongs to its
+    // mode.
+    pub fn save(&self, out: &mut SnapshotWriter) -> io::Result<()> {
+        out.u64(self.num_ins as u64)?;
+        out.u64(self.time_ns)?;
+        out.bytes(&self.mem)?;
+        for reg in self.regs.iter().chain(&self.sps) {
+            out.u16(*reg)?;
+        }
+        out.u16(self.status.to_raw())?;
+        out.u16(self.cpu_error)?;
+        out.u16(self.stack_limit)?;
+        self.mmu.save(out)?;
+        self.fpu.save(out)
+    }
+
+    pub fn load(&mut self, input: &mut SnapshotReader) -> io::Result<()> {
+        self.num_ins = input.u64()? as usize;
+        self.time_ns = input.u64()?;
+        input.bytes(&mut self.mem)?;
+        for reg in self.regs.iter_mut().chain(&mut self.sps) {
+            *reg = input.u16()?;
+        }
+        self.status = Status::from_raw(input.u16()?);
+        self.cpu_error = input.u16()?;
+        self.stack_limit = input.u16()?;
+        self.mmu.load(input)?;
+        self.fpu.load(input)
+    }
+
+    // Memory is accessed by (18-bit) physical address.
+    pub fn mem_read_byte(&self, addr: u32) -> u8 {
         self.mem[addr as usize]
     }
 
-    pub fn mem_write_byte(&mut self, addr: u16, val: u8) {
+    pub fn mem_write_byte(&mut self, addr: u32, val: u8) {
         trace!("Mem: writing {val:#o} to 0o{addr:o} (byte)");
+        self.journal_write(addr, 1);
+        self.invalidate_decoded(addr);
         self.mem[addr as usize] = val;
     }
 
-    pub fn mem_read_word(&self, addr: u16) -> u16 {
+    pub fn mem_read_word(&self, addr: u32) -> u16 {
         assert!(addr & 1 == 0);
         (self.mem[addr as usize] as u16) | ((self.mem[(addr + 1) as usize] as u16) << 8)
     }
 
-    pub fn mem_write_word(&mut self, addr: u16, val: u16) {
+    pub fn mem_write_word(&mut self, addr: u32, val: u16) {
         trace!("Mem: writing {val:#o} to 0o{addr:o} (word)");
         assert!(addr & 1 == 0);
+        self.journal_write(addr, 2);
+        self.invalidate_decoded(addr);
         self.mem[addr as usize] = val as u8;
         self.mem[(addr + 1) as usize] = (val >> 8) as u8;
     }
 
+    fn journal_write(&mut self, addr: u32, len: u32) {
+        if let Some(journal) = &mut self.mem_journal {
+            journal.extend((addr..addr + len).map(|addr| (addr, self.mem[addr as usize])));
+        }
+    }
+
+    // Records the old value of every memory byte written, until
+    // take_mem_journal().
+    pub fn start_mem_journal(&mut self) {
+        self.mem_journal = Some(Vec::new());
+    }
+
+    pub fn take_mem_journal(&mut self) -> Vec<(u32, u8)> {
+        self.mem_journal.take().unwrap_or_default()
+    }
+
+    // Puts back the bytes from a journal, latest first.
+    pub fn undo_mem_writes(&mut self, journal: &[(u32, u8)]) {
+        for (addr, val) in journal.iter().rev() {
+            self.invalidate_decoded(*addr);
+            self.mem[*addr as usize] = *val;
+        }
+    }
+
+    // The instruction decoded from the words at phys, if they haven't been
+    // written since.
+    pub fn get_decoded(&self, phys: u32) -> Option<&Arc<Ins>> {
+        self.decoded.get((phys / 2) as usize)?.as_ref()
+    }
+
+    pub fn set_decoded(&mut self, phys: u32, ins: Arc<Ins>) {
+        if let Some(entry) = self.decoded.get_mut((phys / 2) as usize) {
+            *entry = Some(ins);
+        }
+    }
+
+    // A write can change any instruction that might include the word it's to.
+    fn invalidate_decoded(&mut self, addr: u32) {
+        let word = (addr / 2) as usize;
+        let first = word.saturating_sub(MAX_INS_WORDS as usize - 1);
+        let end = (word + 1).min(self.decoded.len());
+        if let Some(entries) = self.decoded.get_mut(first..end) {
+            entries.fill(None);
+        }
+    }
+
+    pub fn cpu_state(&self) -> CpuState {
+        CpuState {
+            num_ins: self.num_ins,
+            time_ns: self.time_ns,
+            regs: self.regs,
+            sps: self.sps,
+            status: self.status.clone(),
+            cpu_error: self.cpu_error,
+            stack_limit: self.stack_limit,
+            mmu: self.mmu.clone(),
+            fpu: self.fpu.clone(),
+        }
+    }
+
+    pub fn set_cpu_state(&mut self, cpu: CpuState) {
+        self.num_ins = cpu.num_ins;
+        self.time_ns = cpu.time_ns;
+        self.regs = cpu.regs;
+        self.sps = cpu.sps;
+        self.status = cpu.status;
+        self.cpu_error = cpu.cpu_error;
+        self.stack_limit = cpu.stack_limit;
+        self.mmu = cpu.mmu;
+        self.fpu = cpu.fpu;
+    }
+
     pub fn reg_write_word(&mut self, reg: Reg, val: u16) {
         trace!("Reg: writing {val:#o} to {reg:?} (word)");
-        if reg == Reg::SP && val < 0o400 {
-            // Should really trap.
-            panic!("Stack overflow");
-        }
         self.regs[reg.to_usize().unwrap()] = val;
     }
 
@@ -174,18 +374,33 @@ impl EmulatorState {
         self.reg_read_word(Reg::PC)
     }
 
-    // Returns next instruction and word after for
-    pub fn next_ins(&self) -> &[u16] {
-        let pc = self.pc() as usize;
-        if pc & 0x1 != 0 {
-            panic!("PC 0o{pc:o} not aligned");
+    // Changes of mode must go through here, so the stack pointer is switched.
+    pub fn set_status(&mut self, status: Status) {
+        let old_mode = self.status.get_curr_mode();
+        let new_mode = status.get_curr_mode();
+        if old_mode != new_mode {
+            let sp = Reg::SP.to_usize().unwrap();
+            self.sps[old_mode.to_usize().unwrap()] = self.regs[sp];
+            self.regs[sp] = self.sps[new_mode.to_usize().unwrap()];
         }
-        let mem = &self.mem.as_slice()[pc..pc + 6];
-        cast_slice(mem)
+        self.status = status;
     }
 
-    pub fn set_status(&mut self, status: Status) {
-        self.status = status;
+    // The stack pointer of a mode, whether or not it's the current one.
+    pub fn sp_for(&self, mode: ProcessorMode) -> u16 {
+        if mode == self.status.get_curr_mode() {
+            self.reg_read_word(Reg::SP)
+        } else {
+            self.sps[mode.to_usize().unwrap()]
+        }
+    }
+
+    pub fn set_sp_for(&mut self, mode: ProcessorMode, val: u16) {
+        if mode == self.status.get_curr_mode() {
+            self.reg_write_word(Reg::SP, val);
+        } else {
+            self.sps[mode.to_usize().unwrap()] = val;
+        }
     }
 
     pub fn get_status(&self) -> &Status {
@@ -195,6 +410,38 @@ impl EmulatorState {
     pub fn get_status_mut(&mut self) -> &mut Status {
         &mut self.status
     }
+
+    pub fn get_cpu_error(&self) -> u16 {
+        self.cpu_error
+    }
+
+    pub fn set_cpu_error(&mut self, val: u16) {
+        self.cpu_error = val;
+    }
+
+    pub fn get_stack_limit(&self) -> u16 {
+        self.stack_limit
+    }
+
+    pub fn set_stack_limit(&mut self, val: u16) {
+        self.stack_limit = val;
+    }
+
+    pub fn get_mmu(&self) -> &Mmu {
+        &self.mmu
+    }
+
+    pub fn get_mmu_mut(&mut self) -> &mut Mmu {
+        &mut self.mmu
+    }
+
+    pub fn get_fpu(&self) -> &Fpu {
+        &self.fpu
+    }
+
+    pub fn get_fpu_mut(&mut self) -> &mut Fpu {
+        &mut self.fpu
+    }
 }
 
 impl Default for EmulatorState {
@@ -0,0 +1,335 @@
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
+use common::asm::Ac;
+use common::float::{Float, Precision};
+
+use std::cmp::Ordering;
+use std::io;
+
+// Floating exception codes, as stored in the FEC register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FpError {
+    OpCode = 2, // Also a reference to AC6 or AC7.
+    DivideByZero = 4,
+    IntConversion = 6,
+    Overflow = 8,
+    Underflow = 10,
+    UndefinedVariable = 12,
+}
+
+// FP11 floating point processor state: six 64 bit accumulators, in the raw
+// format of common::float, the FPS status register, and the exception code and
+// address of the last error.
+#[derive(Debug, Default, Clone)]
+pub struct Fpu {
+    ac: [u64; Ac::NUM_ACS],
+    fps: u16,
+    fec: u16,
+    fea: u16,
+}
+
+impl Fpu {
+    pub const VECTOR: u16 = 0o244;
+
+    // FPS bits.
+    pub const FER: u16 = 0x1 << 15; // Error
+    pub const FID: u16 = 0x1 << 14; // Interrupts disabled
+    pub const FIUV: u16 = 0x1 << 11; // Interrupt on undefined variable
+    pub const FIU: u16 = 0x1 << 10; // Interrupt on underflow
+    pub const FIV: u16 = 0x1 << 9; // Interrupt on overflow
+    pub const FIC: u16 = 0x1 << 8; // Interrupt on integer conversion error
+    pub const FD: u16 = 0x1 << 7; // Double precision
+    pub const FL: u16 = 0x1 << 6; // Long integers
+    pub const FT: u16 = 0x1 << 5; // Truncate, rather than round
+    pub const FN: u16 = 0x1 << 3;
+    pub const FZ: u16 = 0x1 << 2;
+    pub const FV: u16 = 0x1 << 1;
+    pub const FC: u16 = 0x1;
+    const FLAGS_MASK: u16 = 0xf;
+    const FPS_WRITABLE: u16 = 0o147777;
+
+    // The low words of single precision values.
+    const SINGLE_MASK: u64 = 0xffff_ffff_0000_0000;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn save(&self, out: &mut SnapshotWriter) -> io::Result<()> {
+        for ac in self.ac {
+            out.u64(ac)?;
+        }
+        out.u16(self.fps)?;
+        out.u16(self.fec)?;
+        out.u16(self.fea)
+    }
+
+    pub fn load(&mut self, input: &mut SnapshotReader) -> io::Result<()> {
+        for ac in &mut self.ac {
+            *ac = input.u64()?;
+        }
+        self.fps = input.u16()?;
+        self.fec = input.u16()?;
+        self.fea = input.u16()?;
+        Ok(())
+    }
+
+    pub fn get_ac(&self, ac: usize) -> u64 {
+        self.ac[ac]
+    }
+
+    pub fn set_ac(&mut self, ac: usize, raw: u64) {
+        self.ac[ac] = raw;
+    }
+
+    pub fn get_fps(&self) -> u16 {
+        self.fps
+    }
+
+    pub fn set_fps(&mut self, val: u16) {
+        self.fps = val & Self::FPS_WRITABLE;
+    }
+
+    pub fn get_fec(&self) -> u16 {
+        self.fec
+    }
+
+    pub fn get_fea(&self) -> u16 {
+        self.fea
+    }
+
+    fn fps_bit(&self, bit: u16) -> bool {
+        (self.fps & bit) != 0
+    }
+
+    fn set_fps_bit(&mut self, bit: u16, val: bool) {
+        if val {
+            self.fps |= bit;
+        } else {
+            self.fps &= !bit;
+        }
+    }
+
+    pub fn precision(&self) -> Precision {
+        if self.fps_bit(Self::FD) {
+            Precision::Double
+        } else {
+            Precision::Single
+        }
+    }
+
+    // The precision used by the conversions between single and double.
+    pub fn other_precision(&self) -> Precision {
+        match self.precision() {
+            Precision::Single => Precision::Double,
+            Precision::Double => Precision::Single,
+        }
+    }
+
+    pub fn set_double(&mut self, val: bool) {
+        self.set_fps_bit(Self::FD, val);
+    }
+
+    pub fn long_ints(&self) -> bool {
+        self.fps_bit(Self::FL)
+    }
+
+    pub fn set_long_ints(&mut self, val: bool) {
+        self.set_fps_bit(Self::FL, val);
+    }
+
+    pub fn truncate(&self) -> bool {
+        self.fps_bit(Self::FT)
+    }
+
+    pub fn interrupts_disabled(&self) -> bool {
+        self.fps_bit(Self::FID)
+    }
+
+    // Whether the error interrupts, other than because of FID. Op code and
+    // divide by zero errors always do.
+    pub fn error_enabled(&self, err: FpError) -> bool {
+        match err {
+            FpError::OpCode | FpError::DivideByZero => true,
+            FpError::IntConversion => self.fps_bit(Self::FIC),
+            FpError::Overflow => self.fps_bit(Self::FIV),
+            FpError::Underflow => self.fps_bit(Self::FIU),
+            FpError::UndefinedVariable => self.fps_bit(Self::FIUV),
+        }
+    }
+
+    pub fn record_error(&mut self, err: FpError, pc: u16) {
+        self.fps |= Self::FER;
+        self.fec = err as u16;
+        self.fea = pc;
+    }
+
+    pub fn get_flags(&self) -> u16 {
+        self.fps & Self::FLAGS_MASK
+    }
+
+    pub fn set_flags(&mut self, n: bool, z: bool, v: bool, c: bool) {
+        self.fps &= !Self::FLAGS_MASK;
+        self.fps |= ((n as u16) * Self::FN)
+            | ((z as u16) * Self::FZ)
+            | ((v as u16) * Self::FV)
+            | ((c as u16) * Self::FC);
+    }
+
+    // Drop any bits beyond the precision.
+    pub fn narrow(raw: u64, prec: Precision) -> u64 {
+        match prec {
+            Precision::Single => raw & Self::SINGLE_MASK,
+            Precision::Double => raw,
+        }
+    }
+
+    // A negative zero is an undefined variable.
+    pub fn is_undefined(raw: u64) -> bool {
+        let f = Float::unpack(raw);
+        f.neg && f.is_zero()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+// Arithmetic. Results are rounded (or truncated) to the given precision, but
+// their exponents aren't range checked.
+
+// The value of a float is frac * 2^scale(exp).
+fn scale(f: &Float) -> i32 {
+    f.exp - Float::EXP_BIAS - Precision::Double.frac_bits() as i32
+}
+
+pub fn add(a: Float, b: Float, prec: Precision, truncate: bool) -> Float {
+    if a.is_zero() {
+        return b;
+    }
+    if b.is_zero() {
+        return a;
+    }
+
+    // Align the smaller onto the larger, with enough guard bits that anything
+    // shifted out is far below the last place of the result.
+    const GUARD: u32 = 64;
+    let (big, small) = if a.exp >= b.exp { (a, b) } else { (b, a) };
+    let diff = (big.exp - small.exp) as u32;
+    let big_mant = (big.frac as u128) << GUARD;
+    let small_mant = ((small.frac as u128) << GUARD)
+        .checked_shr(diff)
+        .unwrap_or(0);
+    let scale = scale(&big) - GUARD as i32;
+
+    if big.neg == small.neg {
+        Float::round(big.neg, big_mant + small_mant, scale, prec, truncate)
+    } else if big_mant >= small_mant {
+        Float::round(big.neg, big_mant - small_mant, scale, prec, truncate)
+    } else {
+        Float::round(small.neg, small_mant - big_mant, scale, prec, truncate)
+    }
+}
+
+pub fn sub(a: Float, b: Float, prec: Precision, truncate: bool) -> Float {
+    add(a, negate(b), prec, truncate)
+}
+
+pub fn mul(a: Float, b: Float, prec: Precision, truncate: bool) -> Float {
+    if a.is_zero() || b.is_zero() {
+        return Float::ZERO;
+    }
+    let mant = a.frac as u128 * b.frac as u128;
+    Float::round(a.neg != b.neg, mant, scale(&a) + scale(&b), prec, truncate)
+}
+
+// b must not be zero.
+pub fn div(a: Float, b: Float, prec: Precision, truncate: bool) -> Float {
+    assert!(!b.is_zero());
+    if a.is_zero() {
+        return Float::ZERO;
+    }
+    const EXTRA: u32 = 64;
+    let num = (a.frac as u128) << EXTRA;
+    let den = b.frac as u128;
+    // Keep a sticky bit, so an inexact quotient doesn't look like a tie.
+    let mant = ((num / den) << 1) | (!num.is_multiple_of(den) as u128);
+    let scale = scale(&a) - scale(&b) - EXTRA as i32 - 1;
+    Float::round(a.neg != b.neg, mant, scale, prec, truncate)
+}
+
+// Multiply, and split the product into its integer and fractional parts, both
+// with the sign of the product.
+pub fn modf(a: Float, b: Float, prec: Precision, truncate: bool) -> (Float, Float) {
+    if a.is_zero() || b.is_zero() {
+        return (Float::ZERO, Float::ZERO);
+    }
+    let neg = a.neg != b.neg;
+    let mant = a.frac as u128 * b.frac as u128;
+    let scale = scale(&a) + scale(&b);
+    if scale >= 0 {
+        return (Float::round(neg, mant, scale, prec, truncate), Float::ZERO);
+    }
+
+    let shift = (-scale) as u32;
+    let int = mant.checked_shr(shift).unwrap_or(0);
+    let frac = mant - int.checked_shl(shift).unwrap_or(0);
+    (
+        Float::round(neg, int, 0, prec, true),
+        Float::round(neg, frac, scale, prec, truncate),
+    )
+}
+
+pub fn negate(f: Float) -> Float {
+    if f.is_zero() {
+        return Float::ZERO;
+    }
+    Float { neg: !f.neg, ..f }
+}
+
+pub fn abs(f: Float) -> Float {
+    if f.is_zero() {
+        return Float::ZERO;
+    }
+    Float { neg: false, ..f }
+}
+
+pub fn cmp(a: Float, b: Float) -> Ordering {
+    let key = |f: &Float| {
+        if f.is_zero() {
+            0
+        } else {
+            let mag = ((f.exp as i128) << 64) | f.frac as i128;
+            if f.neg { -mag } else { mag }
+        }
+    };
+    key(&a).cmp(&key(&b))
+}
+
+// Truncates toward zero. None if out of range for a word, or for a long.
+pub fn to_int(f: Float, long: bool) -> Option<i32> {
+    if f.is_zero() {
+        return Some(0);
+    }
+    let scale = scale(&f);
+    let mag = if scale >= 0 {
+        (f.frac as u128).checked_shl(scale as u32)?
+    } else {
+        (f.frac as u128).checked_shr((-scale) as u32).unwrap_or(0)
+    };
+    let val = if f.neg { -(mag as i128) } else { mag as i128 };
+    let (min, max) = if long {
+        (i32::MIN as i128, i32::MAX as i128)
+    } else {
+        (i16::MIN as i128, i16::MAX as i128)
+    };
+    (min..=max).contains(&val).then_some(val as i32)
+}
+
+pub fn from_int(val: i32, prec: Precision, truncate: bool) -> Float {
+    Float::round(val < 0, val.unsigned_abs() as u128, 0, prec, truncate)
+}
+
+// Round to a different precision.
+pub fn convert(f: Float, prec: Precision, truncate: bool) -> Float {
+    if f.is_zero() {
+        return Float::ZERO;
+    }
+    Float::round(f.neg, f.frac as u128, scale(&f), prec, truncate)
+}
@@ -0,0 +1,354 @@
+use crate::breakpoint::{Breakpoint, WatchAccess, WatchTarget};
+use crate::{Control, Emulator, EmulatorError, Status, StopReason};
+use common::asm::Reg;
+use num_traits::FromPrimitive;
+
+use log::{debug, error};
+
+use std::collections::HashMap;
+use std::io::{self, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+const SIGABRT: u8 = 6;
+const SIGBUS: u8 = 10;
+const SIGTERM: u8 = 15;
+
+// r0 to r5, sp and pc, then ps.
+const NUM_GDB_REGS: usize = 9;
+const PS_REG: usize = 8;
+
+// Sent GDB's way by the thread reading the connection.
+enum Event {
+    Packet(String),
+    BadChecksum,
+}
+
+// A GDB remote serial protocol stub. GDB drives the emulator through it:
+// registers and memory, stepping, continuing, breakpoints and watchpoints,
+// and Ctrl-C, which interrupts a running guest.
+pub struct GdbStub<'a> {
+    emu: &'a mut Emulator,
+    stream: TcpStream,
+    events: Receiver<Event>,
+    no_ack: bool,
+    done: bool,
+    last_stop: String,
+    breakpoints: HashMap<(u8, u16, u16), Vec<usize>>, // By Z packet type, address and length.
+}
+
+// Serves a GDB connection until GDB detaches or kills the program, or the
+// connection closes. The emulator is left as GDB left it.
+pub fn serve(emu: &mut Emulator, stream: TcpStream) -> io::Result<()> {
+    GdbStub::new(emu, stream)?.run()
+}
+
+impl<'a> GdbStub<'a> {
+    pub fn new(emu: &'a mut Emulator, stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let (send, events) = mpsc::channel();
+        let reader = stream.try_clone()?;
+        let control = emu.control();
+        thread::spawn(move || read_packets(reader, control, send));
+        Ok(GdbStub {
+            emu,
+            stream,
+            events,
+            no_ack: false,
+            done: false,
+            last_stop: format!("S{SIGTRAP:02x}"),
+            breakpoints: HashMap::new(),
+        })
+    }
+
+    pub fn run(mut self) -> io::Result<()> {
+        while !self.done {
+            let Ok(event) = self.events.recv() else {
+                break;
+            };
+            let packet = match event {
+                Event::Packet(packet) => packet,
+                Event::BadChecksum => {
+                    self.ack(b'-')?;
+                    continue;
+                }
+            };
+            self.ack(b'+')?;
+            debug!("GDB: {packet}");
+            if let Some(reply) = self.handle(&packet) {
+                self.send(&reply)?;
+            }
+            if packet == "QStartNoAckMode" {
+                self.no_ack = true;
+            }
+        }
+        for ids in self.breakpoints.values() {
+            for id in ids {
+                self.emu.remove_breakpoint(*id);
+            }
+        }
+        Ok(())
+    }
+
+    fn ack(&mut self, ack: u8) -> io::Result<()> {
+        if self.no_ack {
+            return Ok(());
+        }
+        self.stream.write_all(&[ack])
+    }
+
+    fn send(&mut self, data: &str) -> io::Result<()> {
+        let sum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(self.stream, "${data}#{sum:02x}")?;
+        self.stream.flush()
+    }
+
+    // The reply to a packet, if there is one.
+    fn handle(&mut self, packet: &str) -> Option<String> {
+        let mut chars = packet.chars();
+        let Some(cmd) = chars.next() else {
+            return Some(String::new());
+        };
+        let args = chars.as_str();
+        let reply = match cmd {
+            '?' => self.last_stop.clone(),
+            'g' => (0..NUM_GDB_REGS)
+                .map(|i| hex_word(self.read_reg(i)))
+                .collect(),
+            'G' => self.write_regs(args).unwrap_or_else(|| "E01".into()),
+            'p' => usize::from_str_radix(args, 16)
+                .ok()
+                .filter(|&i| i < NUM_GDB_REGS)
+                .map_or_else(|| "E01".into(), |i| hex_word(self.read_reg(i))),
+            'P' => self.write_reg(args).unwrap_or_else(|| "E01".into()),
+            'm' => self.read_mem(args).unwrap_or_else(|| "E01".into()),
+            'M' => self.write_mem(args).unwrap_or_else(|| "E01".into()),
+            's' | 'c' => {
+                if !args.is_empty() {
+                    let Ok(pc) = u16::from_str_radix(args, 16) else {
+                        return Some("E01".into());
+                    };
+                    self.emu.reg_write_word(Reg::PC, pc);
+                }
+                // A Ctrl-C that came once the guest had already stopped.
+                self.emu.control().cancel_interrupt();
+                let res = if cmd == 's' {
+                    self.emu.run_ins()
+                } else {
+                    self.emu.run()
+                };
+                self.last_stop = self.stop_reply(res);
+                self.last_stop.clone()
+            }
+            'Z' | 'z' => self
+                .set_breakpoint(cmd == 'Z', args)
+                .unwrap_or_else(|| "E01".into()),
+            'q' => match packet.split(':').next().unwrap() {
+                "qSupported" => "PacketSize=1000;QStartNoAckMode+;swbreak+".into(),
+                "qAttached" => "1".into(),
+                _ => String::new(),
+            },
+            'Q' if packet == "QStartNoAckMode" => "OK".into(),
+            'H' => "OK".into(),
+            'D' => {
+                self.done = true;
+                "OK".into()
+            }
+            'k' => {
+                self.done = true;
+                return None;
+            }
+            _ => String::new(),
+        };
+        Some(reply)
+    }
+
+    fn read_reg(&self, i: usize) -> u16 {
+        if i == PS_REG {
+            self.emu.get_state().get_status().to_raw()
+        } else {
+            self.emu.reg_read_word(Reg::from_usize(i).unwrap())
+        }
+    }
+
+    fn write_reg_val(&mut self, i: usize, val: u16) {
+        if i == PS_REG {
+            self.emu.get_state_mut().set_status(Status::from_raw(val));
+        } else {
+            self.emu.reg_write_word(Reg::from_usize(i).unwrap(), val);
+        }
+    }
+
+    fn write_regs(&mut self, args: &str) -> Option<String> {
+        let vals = parse_hex_bytes(args)?;
+        if vals.len() < NUM_GDB_REGS * 2 {
+            return None;
+        }
+        // PS first, as changing mode switches stack pointers, and the SP
+        // given is the new mode's.
+        for i in std::iter::once(PS_REG).chain(0..PS_REG) {
+            self.write_reg_val(i, u16::from_le_bytes([vals[i * 2], vals[i * 2 + 1]]));
+        }
+        Some("OK".into())
+    }
+
+    fn write_reg(&mut self, args: &str) -> Option<String> {
+        let (reg, val) = args.split_once('=')?;
+        let i = usize::from_str_radix(reg, 16).ok()?;
+        let val = parse_hex_bytes(val)?;
+        if i >= NUM_GDB_REGS || val.len() != 2 {
+            return None;
+        }
+        self.write_reg_val(i, u16::from_le_bytes([val[0], val[1]]));
+        Some("OK".into())
+    }
+
+    // As much as can be read, up to the first address that can't.
+    fn read_mem(&mut self, args: &str) -> Option<String> {
+        let (addr, len) = parse_addr_len(args)?;
+        let mut reply = String::new();
+        for i in 0..len {
+            let Some(byte) = self.emu.mem_peek_byte(addr.wrapping_add(i)) else {
+                break;
+            };
+            reply += &format!("{byte:02x}");
+        }
+        (len == 0 || !reply.is_empty()).then_some(reply)
+    }
+
+    fn write_mem(&mut self, args: &str) -> Option<String> {
+        let (range, data) = args.split_once(':')?;
+        let (addr, len) = parse_addr_len(range)?;
+        let data = parse_hex_bytes(data)?;
+        if data.len() != len as usize {
+            return None;
+        }
+        for (i, byte) in data.into_iter().enumerate() {
+            self.emu
+                .mem_try_write_byte(addr.wrapping_add(i as u16), byte)?;
+        }
+        Some("OK".into())
+    }
+
+    // Z0 and Z1 are breakpoints; Z2, Z3 and Z4 are write, read and access
+    // watchpoints, over every word in the range.
+    fn set_breakpoint(&mut self, insert: bool, args: &str) -> Option<String> {
+        let mut fields = args.split(',');
+        let kind: u8 = fields.next()?.parse().ok()?;
+        let addr = u16::from_str_radix(fields.next()?, 16).ok()?;
+        let len = u16::from_str_radix(fields.next()?, 16).ok()?;
+        let key = (kind, addr, len);
+        if !insert {
+            for id in self.breakpoints.remove(&key).unwrap_or_default() {
+                self.emu.remove_breakpoint(id);
+            }
+            return Some("OK".into());
+        }
+
+        let accesses: &[WatchAccess] = match kind {
+            0 | 1 => &[],
+            2 => &[WatchAccess::Write],
+            3 => &[WatchAccess::Read],
+            4 => &[WatchAccess::Read, WatchAccess::Write],
+            _ => return Some(String::new()),
+        };
+        let points: Vec<_> = if accesses.is_empty() {
+            vec![Breakpoint::exec(addr)]
+        } else {
+            let words = (addr & !1..addr.saturating_add(len.max(1))).step_by(2);
+            words
+                .flat_map(|word| accesses.iter().map(move |a| Breakpoint::mem(word, *a)))
+                .collect()
+        };
+        let ids = points
+            .into_iter()
+            .map(|bp| self.emu.add_breakpoint(bp))
+            .collect();
+        if let Some(old) = self.breakpoints.insert(key, ids) {
+            for id in old {
+                self.emu.remove_breakpoint(id);
+            }
+        }
+        Some("OK".into())
+    }
+
+    fn stop_reply(&mut self, res: Result<StopReason, EmulatorError>) -> String {
+        let signal = match res {
+            Ok(StopReason::Breakpoint { .. }) => return format!("T{SIGTRAP:02x}swbreak:;"),
+            Ok(StopReason::Watchpoint(hit)) => {
+                let WatchTarget::Mem(addr) = hit.target else {
+                    return format!("S{SIGTRAP:02x}");
+                };
+                let kind = if hit.write { "watch" } else { "rwatch" };
+                return format!("T{SIGTRAP:02x}{kind}:{addr:x};");
+            }
+            Ok(StopReason::Step | StopReason::Halted | StopReason::InsLimit) => SIGTRAP,
+            Ok(StopReason::DoubleFault) => SIGBUS,
+            Ok(StopReason::HostInterrupt) => SIGINT,
+            Ok(StopReason::HostStop) => {
+                self.done = true;
+                return format!("X{SIGTERM:02x}");
+            }
+            Err(e) => {
+                error!("{e}");
+                SIGABRT
+            }
+        };
+        format!("S{signal:02x}")
+    }
+}
+
+// Passes packets on, and Ctrl-C straight to the emulator, as it arrives
+// while the guest runs. Acks are ignored: the connection is reliable.
+fn read_packets(stream: TcpStream, control: Control, events: Sender<Event>) {
+    let mut bytes = BufReader::new(stream).bytes().map_while(Result::ok);
+    while let Some(byte) = bytes.next() {
+        match byte {
+            0x03 => control.interrupt(),
+            b'$' => {
+                let data: Vec<u8> = bytes.by_ref().take_while(|&b| b != b'#').collect();
+                let sum: Vec<u8> = bytes.by_ref().take(2).collect();
+                let expected = data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+                let event = match std::str::from_utf8(&sum)
+                    .ok()
+                    .and_then(|sum| u8::from_str_radix(sum, 16).ok())
+                {
+                    Some(sum) if sum == expected => {
+                        Event::Packet(String::from_utf8_lossy(&data).into_owned())
+                    }
+                    _ => Event::BadChecksum,
+                };
+                if events.send(event).is_err() {
+                    return;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Words go little endian, as they are in memory.
+fn hex_word(val: u16) -> String {
+    format!("{:02x}{:02x}", val & 0xff, val >> 8)
+}
+
+fn parse_hex_bytes(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_addr_len(s: &str) -> Option<(u16, u16)> {
+    let (addr, len) = s.split_once(',')?;
+    Some((
+        u16::from_str_radix(addr, 16).ok()?,
+        u16::from_str_radix(len, 16).ok()?,
+    ))
+}
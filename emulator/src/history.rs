@@ -0,0 +1,56 @@
+use crate::emulator_state::CpuState;
+
+use std::collections::VecDeque;
+
+// What's needed to undo one step of the emulator: the CPU as it was before,
+// and the old values of the memory bytes it wrote.
+#[derive(Debug)]
+pub struct Step {
+    pub cpu: CpuState,
+    pub waiting: bool,
+    pub mem: Vec<(u32, u8)>,
+}
+
+// The most recent steps, up to a limit, oldest first. A limit of 0 turns
+// recording off.
+#[derive(Debug, Default)]
+pub struct History {
+    steps: VecDeque<Step>,
+    limit: usize,
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        History {
+            steps: VecDeque::new(),
+            limit,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.limit > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn push(&mut self, step: Step) {
+        if self.steps.len() == self.limit {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(step);
+    }
+
+    pub fn pop(&mut self) -> Option<Step> {
+        self.steps.pop_back()
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+}
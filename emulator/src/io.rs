@@ -1,28 +1,85 @@
+pub mod bus;
 pub mod clock;
+pub mod cpu_error_access;
+pub mod front_panel;
+pub mod interrupt_controller;
+pub mod mmu_access;
+pub mod stack_limit_access;
 pub mod status_access;
 pub mod teletype;
 
 use crate::EmulatorState;
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
 
-#[derive(Debug, Clone, Copy)]
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Interrupt {
     pub prio: u8, // 0o0 through 0o7
     pub vector: u16,
+    pub edge: bool, // Cleared by being granted, rather than by the device.
 }
 
 pub trait MMIOHandler: Send {
+    // As the monitor shows it, and finds it for attach and detach.
+    fn name(&self) -> &str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
+    // Connects a file to a device that takes one, in the manner of SIMH.
+    fn attach(&mut self, _path: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} can't be attached", self.name()),
+        ))
+    }
+    fn detach(&mut self) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} can't be detached", self.name()),
+        ))
+    }
+
     fn reset(&mut self, _emu: &mut EmulatorState) {}
-    fn tick(&mut self, _emu: &mut EmulatorState) -> Option<Interrupt> {
+    // Called once the simulated time reaches the time given by next_wake().
+    fn tick(&mut self, _emu: &mut EmulatorState) {}
+    // After every call into the device, the emulator asks when it next needs
+    // to be ticked, as an absolute simulated time, and what interrupts it's
+    // requesting. A device with nothing to do is never ticked.
+    fn next_wake(&self) -> Option<u64> {
         None
     }
-    fn interrupt_accepted(&mut self) {}
+    // Pushes each pending request, a device with several vectors giving the
+    // one it wants served first first.
+    fn interrupts(&self, _requests: &mut Vec<Interrupt>) {}
     fn default_addrs(&self) -> &[u16] {
         &[]
     }
 
-    fn read_byte(&mut self, emu: &mut EmulatorState, addr: u16) -> u8;
-    fn read_word(&mut self, emu: &mut EmulatorState, addr: u16) -> u16;
+    // Device state for snapshots. Handlers that only give access to
+    // EmulatorState have nothing of their own to save. load_state must read
+    // exactly what save_state wrote.
+    fn save_state(&self, _out: &mut SnapshotWriter) -> io::Result<()> {
+        Ok(())
+    }
+    fn load_state(&mut self, _input: &mut SnapshotReader) -> io::Result<()> {
+        Ok(())
+    }
+
+    // A word at an even address, as read_word() would give it but without any
+    // side effects, for debuggers. None if the device doesn't respond there,
+    // or can't be read without side effects, as by default.
+    fn peek(&self, _emu: &EmulatorState, _addr: u16) -> Option<u16> {
+        None
+    }
+
+    // Each gives None if the device doesn't respond at the address, which is
+    // a bus timeout, as if nothing were there.
+    fn read_byte(&mut self, emu: &mut EmulatorState, addr: u16) -> Option<u8>;
+    fn read_word(&mut self, emu: &mut EmulatorState, addr: u16) -> Option<u16>;
 
-    fn write_byte(&mut self, emu: &mut EmulatorState, addr: u16, val: u8);
-    fn write_word(&mut self, emu: &mut EmulatorState, addr: u16, val: u16);
+    fn write_byte(&mut self, emu: &mut EmulatorState, addr: u16, val: u8) -> Option<()>;
+    fn write_word(&mut self, emu: &mut EmulatorState, addr: u16, val: u16) -> Option<()>;
 }
@@ -0,0 +1,226 @@
+use crate::EmulatorState;
+use crate::io::interrupt_controller::InterruptController;
+use crate::io::{Interrupt, MMIOHandler};
+use common::constants::MMIO_START;
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+// The devices, each held once, in the order they were added, and a table of
+// which of them responds at each word of the I/O page. The emulator owns them
+// all, so no locking is needed to access one.
+//
+// Devices are only called when they're accessed or when the time they asked
+// to be woken at comes, so an idle device costs nothing. After each call the
+// bus asks the device when it next wants waking and what interrupt it's
+// requesting.
+pub struct Bus {
+    devices: Vec<Box<dyn MMIOHandler>>,
+    io_page: Vec<Option<u16>>, // Index into devices, by word.
+    wakes: Vec<Option<u64>>,   // When each device asked to be woken.
+    // Wake times, soonest first. An entry that no longer matches its device's
+    // wake time is stale, and skipped.
+    events: BinaryHeap<Reverse<(u64, usize)>>,
+    requests: Vec<Vec<Interrupt>>, // Each device's pending requests.
+    new_requests: Vec<Interrupt>,
+    interrupts: InterruptController,
+    due: Vec<usize>,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus {
+    pub const IO_PAGE_WORDS: usize = 4096;
+
+    pub fn new() -> Self {
+        Bus {
+            devices: Vec::new(),
+            io_page: vec![None; Self::IO_PAGE_WORDS],
+            wakes: Vec::new(),
+            events: BinaryHeap::new(),
+            requests: Vec::new(),
+            new_requests: Vec::new(),
+            interrupts: InterruptController::new(),
+            due: Vec::new(),
+        }
+    }
+
+    fn word(addr: u16) -> usize {
+        assert!(addr >= MMIO_START);
+        ((addr - MMIO_START) / 2) as usize
+    }
+
+    pub fn add(&mut self, device: Box<dyn MMIOHandler>, addrs: impl IntoIterator<Item = u16>) {
+        let index = u16::try_from(self.devices.len()).unwrap();
+        for addr in addrs {
+            assert!(addr & 0x1 == 0, "MMIOHandler addr {addr:o} not aligned");
+            let entry = &mut self.io_page[Self::word(addr)];
+            assert!(entry.is_none(), "Duplicate MMIOHandler for {addr:o}");
+            *entry = Some(index);
+        }
+        self.devices.push(device);
+        self.wakes.push(None);
+        self.requests.push(Vec::new());
+        self.update(index as usize);
+    }
+
+    // Picks up a change to a device's wake time or interrupt request.
+    fn update(&mut self, index: usize) {
+        let dev = &self.devices[index];
+        let wake = dev.next_wake();
+        if wake != self.wakes[index] {
+            self.wakes[index] = wake;
+            if let Some(time) = wake {
+                self.events.push(Reverse((time, index)));
+            }
+        }
+
+        self.new_requests.clear();
+        dev.interrupts(&mut self.new_requests);
+        if self.new_requests != self.requests[index] {
+            self.requests[index].clone_from(&self.new_requests);
+            self.interrupts.set_requests(index, &self.new_requests);
+        }
+    }
+
+    // After changing devices through devices_mut(). Their requests are made
+    // afresh, so none have been granted.
+    pub fn refresh(&mut self) {
+        self.events.clear();
+        self.wakes.fill(None);
+        self.requests.iter_mut().for_each(Vec::clear);
+        self.interrupts = InterruptController::new();
+        for i in 0..self.devices.len() {
+            self.update(i);
+        }
+    }
+
+    fn index_at(&self, addr: u16) -> Option<usize> {
+        self.io_page[Self::word(addr)].map(|i| i as usize)
+    }
+
+    // Accesses to the I/O page, or None if no device responds at the address.
+    // Both bytes of a word go to the same device.
+    pub fn read_byte(&mut self, emu: &mut EmulatorState, addr: u16) -> Option<u8> {
+        let i = self.index_at(addr)?;
+        let val = self.devices[i].read_byte(emu, addr);
+        self.update(i);
+        val
+    }
+
+    pub fn read_word(&mut self, emu: &mut EmulatorState, addr: u16) -> Option<u16> {
+        let i = self.index_at(addr)?;
+        let val = self.devices[i].read_word(emu, addr);
+        self.update(i);
+        val
+    }
+
+    pub fn peek(&self, emu: &EmulatorState, addr: u16) -> Option<u16> {
+        self.devices[self.index_at(addr)?].peek(emu, addr)
+    }
+
+    pub fn write_byte(&mut self, emu: &mut EmulatorState, addr: u16, val: u8) -> Option<()> {
+        let i = self.index_at(addr)?;
+        let ret = self.devices[i].write_byte(emu, addr, val);
+        self.update(i);
+        ret
+    }
+
+    pub fn write_word(&mut self, emu: &mut EmulatorState, addr: u16, val: u16) -> Option<()> {
+        let i = self.index_at(addr)?;
+        let ret = self.devices[i].write_word(emu, addr, val);
+        self.update(i);
+        ret
+    }
+
+    pub fn reset(&mut self, emu: &mut EmulatorState) {
+        for i in 0..self.devices.len() {
+            self.devices[i].reset(emu);
+            self.update(i);
+        }
+    }
+
+    // The time of the next wake, if any device wants one.
+    pub fn next_event_ns(&mut self) -> Option<u64> {
+        while let Some(&Reverse((time, i))) = self.events.peek() {
+            if self.wakes[i] == Some(time) {
+                return Some(time);
+            }
+            self.events.pop();
+        }
+        None
+    }
+
+    // Wakes the devices whose time has come, in time order. A device that
+    // asks for a time that has already passed is woken again next time.
+    pub fn wake_due(&mut self, emu: &mut EmulatorState) {
+        let now = emu.get_time_ns();
+        self.due.clear();
+        while let Some(&Reverse((time, i))) = self.events.peek()
+            && time <= now
+        {
+            self.events.pop();
+            if self.wakes[i] == Some(time) {
+                self.wakes[i] = None;
+                self.due.push(i);
+            }
+        }
+        for j in 0..self.due.len() {
+            let i = self.due[j];
+            self.devices[i].tick(emu);
+            self.update(i);
+        }
+    }
+
+    // The request to grant with the CPU at a priority, and the index of the
+    // device making it.
+    pub fn interrupt(&self, cpu_prio: u8) -> Option<(usize, Interrupt)> {
+        self.interrupts.highest_above(cpu_prio)
+    }
+
+    pub fn interrupt_accepted(&mut self, index: usize, vector: u16) {
+        self.interrupts.grant(index, vector);
+    }
+
+    // A device's edge triggered requests that were granted, and are still
+    // being made.
+    pub fn granted(&self, index: usize) -> Vec<u16> {
+        self.interrupts.granted(index)
+    }
+
+    pub fn devices(&self) -> &[Box<dyn MMIOHandler>] {
+        &self.devices
+    }
+
+    pub fn devices_mut(&mut self) -> &mut [Box<dyn MMIOHandler>] {
+        &mut self.devices
+    }
+
+    // The word addresses a device answers at.
+    pub fn addrs(&self, index: usize) -> Vec<u16> {
+        let index = Some(index as u16);
+        (0..Self::IO_PAGE_WORDS)
+            .filter(|&word| self.io_page[word] == index)
+            .map(|word| MMIO_START + word as u16 * 2)
+            .collect()
+    }
+
+    // Finds a device by name, ignoring case, and lets it be changed.
+    pub fn with_device<T>(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(&mut dyn MMIOHandler) -> T,
+    ) -> Option<T> {
+        let index = self
+            .devices
+            .iter()
+            .position(|dev| dev.name().eq_ignore_ascii_case(name))?;
+        let ret = f(self.devices[index].as_mut());
+        self.update(index);
+        Some(ret)
+    }
+}
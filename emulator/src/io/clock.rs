@@ -1,13 +1,16 @@
 use crate::EmulatorState;
 use crate::io::{Interrupt, MMIOHandler};
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
 
+use std::io;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 pub struct Clock {
     interrupt_enable: bool,
     clock: bool,
-    ticks_until_ready: usize,
+    next_tick_ns: u64, // Simulated time of the next tick.
+    period_ns: u64,
 }
 
 impl Default for Clock {
@@ -15,7 +18,8 @@ impl Default for Clock {
         Self {
             interrupt_enable: false,
             clock: false,
-            ticks_until_ready: Self::DELAY_TICKS,
+            next_tick_ns: Self::PERIOD_NS,
+            period_ns: Self::PERIOD_NS,
         }
     }
 }
@@ -31,78 +35,122 @@ impl Clock {
     const PRIO: u8 = 0o6;
     const VECTOR: u16 = 0o100;
 
-    // Ticks every 16.6 ms
-    // I'm going to arbitrarily choose a fixed 5 us per instruction.
-    const DELAY_TICKS: usize = 3_320;
+    // Ticks at the line frequency, 60 Hz by default.
+    const PERIOD_NS: u64 = 1_000_000_000 / 60;
 
     #[allow(dead_code)]
     fn new() -> Self {
         Self::default()
     }
 
+    pub fn with_hz(hz: u32) -> Self {
+        let period_ns = 1_000_000_000 / hz as u64;
+        Self {
+            next_tick_ns: period_ns,
+            period_ns,
+            ..Self::default()
+        }
+    }
+
     fn lks_write(&mut self, val: u8) {
         self.interrupt_enable = (val & Self::INT_ENB_MASK) != 0;
     }
 
+    fn lks_peek(&self) -> u8 {
+        ((self.interrupt_enable as u8) << Self::INT_ENB_SHIFT)
+            | ((self.clock as u8) << Self::CLOCK_SHIFT)
+    }
+
+    // Reading clears the clock bit.
     fn lks_read(&mut self) -> u8 {
-        let val = ((self.interrupt_enable as u8) << Self::INT_ENB_SHIFT)
-            | ((self.clock as u8) << Self::CLOCK_SHIFT);
+        let val = self.lks_peek();
         self.clock = false;
         val
     }
 }
 
 impl MMIOHandler for Clock {
-    fn reset(&mut self, _emu: &mut EmulatorState) {
+    fn reset(&mut self, emu: &mut EmulatorState) {
         self.interrupt_enable = false;
         self.clock = false;
-        self.ticks_until_ready = Self::DELAY_TICKS;
+        self.next_tick_ns = emu.get_time_ns() + self.period_ns;
     }
 
-    fn tick(&mut self, _emu: &mut EmulatorState) -> Option<Interrupt> {
-        self.ticks_until_ready -= 1;
-        if self.ticks_until_ready == 0 {
-            self.clock = true;
-            self.ticks_until_ready = Self::DELAY_TICKS;
-        }
+    // Ticks that are missed, because they came close together, are lost, but
+    // the clock doesn't drift.
+    fn tick(&mut self, emu: &mut EmulatorState) {
+        let now = emu.get_time_ns();
+        self.clock = true;
+        let late = (now - self.next_tick_ns) % self.period_ns;
+        self.next_tick_ns = now + self.period_ns - late;
+    }
+
+    fn next_wake(&self) -> Option<u64> {
+        Some(self.next_tick_ns)
+    }
 
+    fn interrupts(&self, requests: &mut Vec<Interrupt>) {
         if self.clock && self.interrupt_enable {
-            Some(Interrupt {
+            requests.push(Interrupt {
                 prio: Self::PRIO,
                 vector: Self::VECTOR,
-            })
-        } else {
-            None
+                edge: false,
+            });
         }
     }
 
-    fn read_byte(&mut self, _: &mut EmulatorState, addr: u16) -> u8 {
+    fn peek(&self, _: &EmulatorState, addr: u16) -> Option<u16> {
+        (addr == Self::LKS).then(|| self.lks_peek() as u16)
+    }
+
+    fn read_byte(&mut self, _: &mut EmulatorState, addr: u16) -> Option<u8> {
         match addr {
-            Self::LKS => self.lks_read(),
-            Self::LKS_UPPER => 0,
-            _ => panic!("Clock doesn't handle address {addr:o}"),
+            Self::LKS => Some(self.lks_read()),
+            Self::LKS_UPPER => Some(0),
+            _ => None,
         }
     }
 
-    fn read_word(&mut self, emu: &mut EmulatorState, addr: u16) -> u16 {
-        self.read_byte(emu, addr) as u16
+    fn read_word(&mut self, emu: &mut EmulatorState, addr: u16) -> Option<u16> {
+        if addr != Self::LKS {
+            return None;
+        }
+        self.read_byte(emu, addr).map(u16::from)
     }
 
-    fn write_byte(&mut self, _: &mut EmulatorState, addr: u16, val: u8) {
+    fn write_byte(&mut self, _: &mut EmulatorState, addr: u16, val: u8) -> Option<()> {
         match addr {
             Self::LKS => self.lks_write(val),
             Self::LKS_UPPER => (),
-            _ => panic!("Clock doesn't handle address {addr:o}"),
+            _ => return None,
         }
+        Some(())
     }
 
-    fn write_word(&mut self, emu: &mut EmulatorState, addr: u16, val: u16) {
-        self.write_byte(emu, addr, val as u8);
+    fn write_word(&mut self, emu: &mut EmulatorState, addr: u16, val: u16) -> Option<()> {
+        if addr != Self::LKS {
+            return None;
+        }
+        self.write_byte(emu, addr, val as u8)
     }
 
     fn default_addrs(&self) -> &[u16] {
         &[Self::LKS]
     }
+
+    // The period is configuration, not state.
+    fn save_state(&self, out: &mut SnapshotWriter) -> io::Result<()> {
+        out.bool(self.interrupt_enable)?;
+        out.bool(self.clock)?;
+        out.u64(self.next_tick_ns)
+    }
+
+    fn load_state(&mut self, input: &mut SnapshotReader) -> io::Result<()> {
+        self.interrupt_enable = input.bool()?;
+        self.clock = input.bool()?;
+        self.next_tick_ns = input.u64()?;
+        Ok(())
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -158,6 +206,11 @@ impl FakeClock {
         self.interrupt_enable = (val & Clock::INT_ENB_MASK) != 0;
     }
 
+    fn lks_peek(&self) -> u8 {
+        ((self.interrupt_enable as u8) << Clock::INT_ENB_SHIFT)
+            | ((self.striker.read_clock() as u8) << Clock::CLOCK_SHIFT)
+    }
+
     fn lks_read(&mut self) -> u8 {
         ((self.interrupt_enable as u8) << Clock::INT_ENB_SHIFT)
             | ((self.striker.swap_clock(false) as u8) << Clock::CLOCK_SHIFT)
@@ -169,42 +222,68 @@ impl MMIOHandler for FakeClock {
         self.interrupt_enable = false;
     }
 
-    fn tick(&mut self, _emu: &mut EmulatorState) -> Option<Interrupt> {
+    // The striker is set from another thread, so it's looked at before every
+    // instruction.
+    fn next_wake(&self) -> Option<u64> {
+        Some(0)
+    }
+
+    fn interrupts(&self, requests: &mut Vec<Interrupt>) {
         if self.striker.read_clock() && self.interrupt_enable {
-            Some(Interrupt {
+            requests.push(Interrupt {
                 prio: Clock::PRIO,
                 vector: Clock::VECTOR,
-            })
-        } else {
-            None
+                edge: false,
+            });
         }
     }
 
-    fn read_byte(&mut self, _: &mut EmulatorState, addr: u16) -> u8 {
+    fn peek(&self, _: &EmulatorState, addr: u16) -> Option<u16> {
+        (addr == Self::LKS).then(|| self.lks_peek() as u16)
+    }
+
+    fn read_byte(&mut self, _: &mut EmulatorState, addr: u16) -> Option<u8> {
         match addr {
-            Self::LKS => self.lks_read(),
-            Self::LKS_UPPER => 0,
-            _ => panic!("Clock doesn't handle address {addr:o}"),
+            Self::LKS => Some(self.lks_read()),
+            Self::LKS_UPPER => Some(0),
+            _ => None,
         }
     }
 
-    fn read_word(&mut self, emu: &mut EmulatorState, addr: u16) -> u16 {
-        self.read_byte(emu, addr) as u16
+    fn read_word(&mut self, emu: &mut EmulatorState, addr: u16) -> Option<u16> {
+        if addr != Self::LKS {
+            return None;
+        }
+        self.read_byte(emu, addr).map(u16::from)
     }
 
-    fn write_byte(&mut self, _: &mut EmulatorState, addr: u16, val: u8) {
+    fn write_byte(&mut self, _: &mut EmulatorState, addr: u16, val: u8) -> Option<()> {
         match addr {
             Self::LKS => self.lks_write(val),
             Self::LKS_UPPER => (),
-            _ => panic!("Clock doesn't handle address {addr:o}"),
+            _ => return None,
         }
+        Some(())
     }
 
-    fn write_word(&mut self, emu: &mut EmulatorState, addr: u16, val: u16) {
-        self.write_byte(emu, addr, val as u8);
+    fn write_word(&mut self, emu: &mut EmulatorState, addr: u16, val: u16) -> Option<()> {
+        if addr != Self::LKS {
+            return None;
+        }
+        self.write_byte(emu, addr, val as u8)
     }
 
     fn default_addrs(&self) -> &[u16] {
         &[Self::LKS]
     }
+
+    // The striker belongs to the test driving the clock.
+    fn save_state(&self, out: &mut SnapshotWriter) -> io::Result<()> {
+        out.bool(self.interrupt_enable)
+    }
+
+    fn load_state(&mut self, input: &mut SnapshotReader) -> io::Result<()> {
+        self.interrupt_enable = input.bool()?;
+        Ok(())
+    }
 }
@@ -0,0 +1,49 @@
+use crate::EmulatorState;
+use crate::io::MMIOHandler;
+
+// Access for the CPU error register through MMIO. It records the cause of
+// bus error, stack limit and illegal HALT traps through vector 4; any write
+// clears it. It isn't cleared by RESET.
+#[derive(Default, Clone, Copy)]
+pub struct CpuErrorAccess();
+
+impl CpuErrorAccess {
+    pub const ADDR: u16 = 0o177766;
+    const ADDR_UPPER: u16 = Self::ADDR + 1;
+
+    pub const ILLEGAL_HALT: u16 = 0x1 << 7;
+    pub const ODD_ADDRESS: u16 = 0x1 << 6;
+    pub const UNIBUS_TIMEOUT: u16 = 0x1 << 4;
+    pub const YELLOW_ZONE: u16 = 0x1 << 3;
+    pub const RED_ZONE: u16 = 0x1 << 2;
+}
+
+impl MMIOHandler for CpuErrorAccess {
+    fn peek(&self, state: &EmulatorState, addr: u16) -> Option<u16> {
+        (addr == Self::ADDR).then(|| state.get_cpu_error())
+    }
+
+    fn read_word(&mut self, state: &mut EmulatorState, addr: u16) -> Option<u16> {
+        self.peek(state, addr)
+    }
+
+    fn read_byte(&mut self, state: &mut EmulatorState, addr: u16) -> Option<u8> {
+        match addr {
+            Self::ADDR => Some(state.get_cpu_error() as u8),
+            Self::ADDR_UPPER => Some((state.get_cpu_error() >> 8) as u8),
+            _ => None,
+        }
+    }
+
+    fn write_word(&mut self, state: &mut EmulatorState, addr: u16, _val: u16) -> Option<()> {
+        (addr == Self::ADDR).then(|| state.set_cpu_error(0))
+    }
+
+    fn write_byte(&mut self, state: &mut EmulatorState, addr: u16, _val: u8) -> Option<()> {
+        matches!(addr, Self::ADDR | Self::ADDR_UPPER).then(|| state.set_cpu_error(0))
+    }
+
+    fn default_addrs(&self) -> &[u16] {
+        &[Self::ADDR]
+    }
+}
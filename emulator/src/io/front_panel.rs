@@ -0,0 +1,116 @@
+use crate::EmulatorState;
+use crate::io::MMIOHandler;
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
+
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU16, Ordering};
+
+// The console's switches and lights, shared with whatever sets the one and
+// shows the other while the guest runs.
+#[derive(Default)]
+pub struct FrontPanelHandle {
+    switches: AtomicU16,
+    display: AtomicU16,
+}
+
+impl FrontPanelHandle {
+    pub fn set_switches(&self, val: u16) {
+        self.switches.store(val, Ordering::Release);
+    }
+
+    pub fn switches(&self) -> u16 {
+        self.switches.load(Ordering::Acquire)
+    }
+
+    // What the guest last wrote to the display register.
+    pub fn display(&self) -> u16 {
+        self.display.load(Ordering::Acquire)
+    }
+
+    fn set_display(&self, val: u16) {
+        self.display.store(val, Ordering::Release);
+    }
+}
+
+// The switch register, read at 177570, and the display register, written at
+// the same address.
+#[derive(Default)]
+pub struct FrontPanel {
+    handle: Arc<FrontPanelHandle>,
+}
+
+impl FrontPanel {
+    pub const ADDR: u16 = 0o177570;
+    pub const ADDR_UPPER: u16 = Self::ADDR + 1;
+
+    pub fn new(switches: u16) -> Self {
+        let panel = Self::default();
+        panel.handle.set_switches(switches);
+        panel
+    }
+
+    pub fn get_handle(&self) -> Arc<FrontPanelHandle> {
+        self.handle.clone()
+    }
+}
+
+impl MMIOHandler for FrontPanel {
+    fn read_byte(&mut self, _: &mut EmulatorState, addr: u16) -> Option<u8> {
+        let val = self.handle.switches();
+        match addr {
+            Self::ADDR => Some(val as u8),
+            Self::ADDR_UPPER => Some((val >> 8) as u8),
+            _ => None,
+        }
+    }
+
+    fn peek(&self, _: &EmulatorState, addr: u16) -> Option<u16> {
+        (addr == Self::ADDR).then(|| self.handle.switches())
+    }
+
+    fn read_word(&mut self, emu: &mut EmulatorState, addr: u16) -> Option<u16> {
+        self.peek(emu, addr)
+    }
+
+    fn write_byte(&mut self, emu: &mut EmulatorState, addr: u16, val: u8) -> Option<()> {
+        let old = self.handle.display();
+        let val = match addr {
+            Self::ADDR => (old & !0xff) | val as u16,
+            Self::ADDR_UPPER => (old & 0xff) | ((val as u16) << 8),
+            _ => return None,
+        };
+        self.write_word(emu, Self::ADDR, val)
+    }
+
+    fn write_word(&mut self, _: &mut EmulatorState, addr: u16, val: u16) -> Option<()> {
+        (addr == Self::ADDR).then(|| self.handle.set_display(val))
+    }
+
+    fn default_addrs(&self) -> &[u16] {
+        &[Self::ADDR]
+    }
+
+    // The switches are the operator's, not the machine's.
+    fn save_state(&self, out: &mut SnapshotWriter) -> io::Result<()> {
+        out.u16(self.handle.display())
+    }
+
+    fn load_state(&mut self, input: &mut SnapshotReader) -> io::Result<()> {
+        self.handle.set_display(input.u16()?);
+        Ok(())
+    }
+}
+
+// A value as a row of lights, grouped in octal digits from bit 15 down, as
+// on the panel.
+pub fn lights(val: u16) -> String {
+    let mut out = String::new();
+    for bit in (0..16).rev() {
+        out.push(if val & (1 << bit) != 0 { '●' } else { '○' });
+        if bit % 3 == 0 && bit != 0 {
+            out.push(' ');
+        }
+    }
+    out
+}
@@ -0,0 +1,93 @@
+use crate::io::Interrupt;
+
+// Pending interrupt requests, on the four bus request levels, BR4 to BR7.
+// Requests stay pending until the device withdraws them, except that an edge
+// triggered request is dropped once granted, until the device withdraws it
+// and makes it again. Among the requests on a level, the device nearest the
+// CPU, the one added first, wins, and a device with several vectors gets them
+// in the order it gives them.
+#[derive(Debug, Default)]
+pub struct InterruptController {
+    levels: [Vec<(usize, Interrupt)>; 4], // (device, request), by BR level.
+    pending: u8,                          // Bit per level with a request.
+    granted: Vec<(usize, u16)>,           // Edge requests, by device and vector.
+}
+
+impl InterruptController {
+    pub const MIN_BR: u8 = 4;
+    pub const MAX_BR: u8 = 7;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A priority outside BR4 to BR7 gets the nearest level.
+    fn level(prio: u8) -> usize {
+        (prio.clamp(Self::MIN_BR, Self::MAX_BR) - Self::MIN_BR) as usize
+    }
+
+    // Replaces all of a device's requests.
+    pub fn set_requests(&mut self, device: usize, requests: &[Interrupt]) {
+        for level in &mut self.levels {
+            level.retain(|&(dev, _)| dev != device);
+        }
+        self.granted
+            .retain(|&(dev, vector)| dev != device || requests.iter().any(|r| r.vector == vector));
+        for req in requests {
+            if self.granted.contains(&(device, req.vector)) {
+                continue;
+            }
+            let level = &mut self.levels[Self::level(req.prio)];
+            let pos = level.partition_point(|&(dev, _)| dev <= device);
+            level.insert(pos, (device, *req));
+        }
+        self.update_pending();
+    }
+
+    fn update_pending(&mut self) {
+        self.pending = 0;
+        for (i, level) in self.levels.iter().enumerate() {
+            if !level.is_empty() {
+                self.pending |= 1 << i;
+            }
+        }
+    }
+
+    // The request that would be granted with the CPU at a priority, and the
+    // device making it.
+    pub fn highest_above(&self, cpu_prio: u8) -> Option<(usize, Interrupt)> {
+        if self.pending == 0 {
+            return None;
+        }
+        let i = (u8::BITS - 1 - self.pending.leading_zeros()) as usize;
+        let prio = i as u8 + Self::MIN_BR;
+        if prio <= cpu_prio {
+            return None;
+        }
+        let (device, req) = self.levels[i][0];
+        Some((device, Interrupt { prio, ..req }))
+    }
+
+    // Drops an edge triggered request once the CPU has taken it.
+    pub fn grant(&mut self, device: usize, vector: u16) {
+        for level in &mut self.levels {
+            let Some(pos) = level
+                .iter()
+                .position(|&(dev, req)| dev == device && req.vector == vector && req.edge)
+            else {
+                continue;
+            };
+            level.remove(pos);
+            self.granted.push((device, vector));
+            self.update_pending();
+            return;
+        }
+    }
+
+    // The device's edge triggered requests that have been granted, but not
+    // yet withdrawn.
+    pub fn granted(&self, device: usize) -> Vec<u16> {
+        let granted = self.granted.iter().filter(|&&(dev, _)| dev == device);
+        granted.map(|&(_, vector)| vector).collect()
+    }
+}
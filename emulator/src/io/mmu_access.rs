@@ -0,0 +1,48 @@
+use crate::EmulatorState;
+use crate::io::MMIOHandler;
+use crate::mmu::Mmu;
+
+// Access for the memory management registers through MMIO
+#[derive(Default, Clone, Copy)]
+pub struct MmuAccess();
+
+impl MMIOHandler for MmuAccess {
+    fn reset(&mut self, state: &mut EmulatorState) {
+        state.get_mmu_mut().reset();
+    }
+
+    fn peek(&self, state: &EmulatorState, addr: u16) -> Option<u16> {
+        state.get_mmu().read_reg(addr)
+    }
+
+    fn read_word(&mut self, state: &mut EmulatorState, addr: u16) -> Option<u16> {
+        self.peek(state, addr)
+    }
+
+    fn read_byte(&mut self, state: &mut EmulatorState, addr: u16) -> Option<u8> {
+        let val = state.get_mmu().read_reg(addr)?;
+        if addr & 0x1 == 0 {
+            Some(val as u8)
+        } else {
+            Some((val >> u8::BITS) as u8)
+        }
+    }
+
+    fn write_word(&mut self, state: &mut EmulatorState, addr: u16, val: u16) -> Option<()> {
+        state.get_mmu_mut().write_reg(addr, val)
+    }
+
+    fn write_byte(&mut self, state: &mut EmulatorState, addr: u16, val: u8) -> Option<()> {
+        let old = state.get_mmu().read_reg(addr)?;
+        let new = if addr & 0x1 == 0 {
+            (old & !0xff) | (val as u16)
+        } else {
+            (old & 0xff) | ((val as u16) << u8::BITS)
+        };
+        state.get_mmu_mut().write_reg(addr, new)
+    }
+
+    fn default_addrs(&self) -> &[u16] {
+        &Mmu::ADDRS
+    }
+}
@@ -0,0 +1,47 @@
+use crate::EmulatorState;
+use crate::io::MMIOHandler;
+
+// Access for the stack limit register through MMIO. Only the upper byte is
+// implemented; the lower reads as zero.
+#[derive(Default, Clone, Copy)]
+pub struct StackLimitAccess();
+
+impl StackLimitAccess {
+    pub const ADDR: u16 = 0o177774;
+    const ADDR_UPPER: u16 = Self::ADDR + 1;
+}
+
+impl MMIOHandler for StackLimitAccess {
+    fn peek(&self, state: &EmulatorState, addr: u16) -> Option<u16> {
+        (addr == Self::ADDR).then(|| state.get_stack_limit())
+    }
+
+    fn read_word(&mut self, state: &mut EmulatorState, addr: u16) -> Option<u16> {
+        self.peek(state, addr)
+    }
+
+    fn read_byte(&mut self, state: &mut EmulatorState, addr: u16) -> Option<u8> {
+        match addr {
+            Self::ADDR => Some(0),
+            Self::ADDR_UPPER => Some((state.get_stack_limit() >> 8) as u8),
+            _ => None,
+        }
+    }
+
+    fn write_word(&mut self, state: &mut EmulatorState, addr: u16, val: u16) -> Option<()> {
+        (addr == Self::ADDR).then(|| state.set_stack_limit(val & !0xff))
+    }
+
+    fn write_byte(&mut self, state: &mut EmulatorState, addr: u16, val: u8) -> Option<()> {
+        match addr {
+            Self::ADDR => (),
+            Self::ADDR_UPPER => state.set_stack_limit((val as u16) << 8),
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn default_addrs(&self) -> &[u16] {
+        &[Self::ADDR]
+    }
+}
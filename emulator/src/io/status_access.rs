@@ -1,5 +1,5 @@
+use crate::EmulatorState;
 use crate::io::MMIOHandler;
-use crate::{EmulatorState, Status};
 
 // Accesss for processor status word through MMIO
 #[derive(Default, Clone, Copy)]
@@ -11,30 +11,39 @@ impl StatusAccess {
 }
 
 impl MMIOHandler for StatusAccess {
-    fn read_word(&mut self, state: &mut EmulatorState, addr: u16) -> u16 {
-        assert_eq!(addr, Self::ADDR);
-        state.get_status().to_raw()
+    fn peek(&self, state: &EmulatorState, addr: u16) -> Option<u16> {
+        (addr == Self::ADDR).then(|| state.get_status().to_raw())
     }
 
-    fn read_byte(&mut self, state: &mut EmulatorState, addr: u16) -> u8 {
+    fn read_word(&mut self, state: &mut EmulatorState, addr: u16) -> Option<u16> {
+        self.peek(state, addr)
+    }
+
+    fn read_byte(&mut self, state: &mut EmulatorState, addr: u16) -> Option<u8> {
         match addr {
-            Self::ADDR => state.get_status().to_raw() as u8,
-            Self::ADDR_UPPER => 0u8,
-            _ => panic!("PsAcesss doesn't handle address {addr:o}"),
+            Self::ADDR => Some(state.get_status().to_raw() as u8),
+            Self::ADDR_UPPER => Some((state.get_status().to_raw() >> 8) as u8),
+            _ => None,
         }
     }
 
-    fn write_word(&mut self, state: &mut EmulatorState, addr: u16, val: u16) {
-        assert_eq!(addr, Self::ADDR);
-        assert_eq!(val & !0xff, 0);
-        state.set_status(Status::from_raw(val));
+    fn write_word(&mut self, state: &mut EmulatorState, addr: u16, val: u16) -> Option<()> {
+        if addr != Self::ADDR {
+            return None;
+        }
+        let status = state.get_status().explicit_update(val);
+        state.set_status(status);
+        Some(())
     }
 
-    fn write_byte(&mut self, state: &mut EmulatorState, addr: u16, val: u8) {
+    fn write_byte(&mut self, state: &mut EmulatorState, addr: u16, val: u8) -> Option<()> {
+        let old = state.get_status().to_raw();
         match addr {
-            Self::ADDR => self.write_word(state, addr, val as u16),
-            Self::ADDR_UPPER => (),
-            _ => panic!("PsAcesss doesn't handle address {addr:o}"),
+            Self::ADDR => self.write_word(state, Self::ADDR, (old & !0xff) | val as u16),
+            Self::ADDR_UPPER => {
+                self.write_word(state, Self::ADDR, (old & 0xff) | ((val as u16) << 8))
+            }
+            _ => None,
         }
     }
 
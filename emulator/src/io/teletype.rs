@@ -1,11 +1,15 @@
 use std::ascii;
 use std::collections::VecDeque;
-use std::io::{Write, stdout};
+use std::fs::File;
+use std::io::{self, Write, stdout};
+use std::path::Path;
 use std::sync::{Arc, Mutex, atomic::AtomicU32, atomic::Ordering};
 use std::time::Duration;
 
 use crate::EmulatorState;
+use crate::control::Control;
 use crate::io::{Interrupt, MMIOHandler};
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
 
 use crossterm::cursor;
 use crossterm::event::{Event, KeyCode, KeyModifiers, poll, read};
@@ -18,25 +22,36 @@ pub trait Tty: Send + Sync {
 
     fn input_available(&self) -> bool;
     fn poll_input(&self) -> Option<u8>;
+    // The next character, if it's already arrived, without taking it.
+    fn peek_input(&self) -> Option<u8>;
+
+    // Whether input must be looked at even while the guest isn't, for keys
+    // meant for the emulator.
+    fn needs_polling(&self) -> bool {
+        false
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
-#[derive(Default)]
 struct StdIo {
     next: Mutex<Option<u8>>,
     count: AtomicU32,
+    control: Control,     // Stopped by Ctrl-C or Ctrl-D.
+    escape: Option<char>, // Ctrl and this interrupts it, for a monitor.
 }
 
 impl StdIo {
     const POLL_TIME_NS: u64 = 0;
     const POLL_PERIOD: u32 = 13;
 
-    fn new() -> StdIo {
+    fn new(control: Control, escape: Option<char>) -> StdIo {
         terminal::enable_raw_mode().unwrap();
         StdIo {
             next: Mutex::new(None),
             count: AtomicU32::new(0),
+            control,
+            escape,
         }
     }
 
@@ -62,11 +77,17 @@ impl StdIo {
             return None;
         };
 
-        if (event.code == KeyCode::Char('c') || event.code == KeyCode::Char('d'))
-            && (event.modifiers.contains(KeyModifiers::CONTROL))
+        if let KeyCode::Char(ch) = event.code
+            && event.modifiers.contains(KeyModifiers::CONTROL)
         {
-            crate::emulator::quit();
-            return None;
+            if Some(ch) == self.escape {
+                self.control.interrupt();
+                return None;
+            }
+            if ch == 'c' || ch == 'd' {
+                self.control.stop();
+                return None;
+            }
         }
 
         if event.code == KeyCode::Enter {
@@ -114,7 +135,9 @@ impl Tty for StdIo {
             )
             .unwrap();
         } else {
-            write!(stdout, "{}", ascii::Char::from_u8(val).unwrap()).unwrap();
+            // The parity bit, if the guest sets it, isn't printed.
+            let ch = ascii::Char::from_u8(val & 0o177).unwrap();
+            write!(stdout, "{ch}").unwrap();
             stdout.flush().unwrap();
         }
     }
@@ -128,6 +151,14 @@ impl Tty for StdIo {
         self.consume();
         val
     }
+
+    fn peek_input(&self) -> Option<u8> {
+        *self.next.lock().unwrap()
+    }
+
+    fn needs_polling(&self) -> bool {
+        self.escape.is_some()
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -174,6 +205,10 @@ impl Tty for PipeTty {
     fn poll_input(&self) -> Option<u8> {
         self.in_buf.lock().unwrap().pop_front()
     }
+
+    fn peek_input(&self) -> Option<u8> {
+        self.in_buf.lock().unwrap().front().copied()
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -181,21 +216,16 @@ impl Tty for PipeTty {
 pub struct Teletype {
     device: Arc<dyn Tty>,
 
-    tps_maintenance_control: bool, // Not used.
+    tps_maintenance_control: bool, // Reads back, but doesn't loop output back.
     tps_interrupt_enabled: bool,
-    printer_interrupted: bool,
-    printer_interrupt_accepted: bool,
     tps_ready: bool,
-    tps_ticks_until_ready: usize,
+    tps_ready_ns: u64, // When the character being printed is done.
+    print_delay_ns: u64,
 
     tks_interrupt_enabled: bool,
-    keyboard_interrupted: bool,
-}
+    keyboard_poll_ns: Option<u64>,
 
-impl Default for Teletype {
-    fn default() -> Self {
-        Teletype::new_to_stdout()
-    }
+    attached: Option<File>, // Printed instead of the device.
 }
 
 impl Teletype {
@@ -239,57 +269,75 @@ impl Teletype {
     #[allow(dead_code)]
     const TKS_DONE_MASK: u16 = 0x1 << Self::TKS_DONE_SHIFT;
 
-    #[allow(dead_code)]
     const KEY_PRIO: u8 = 0o4;
-    #[allow(dead_code)]
     const KEY_VECTOR: u16 = 0o60;
 
     #[allow(unused)]
     const TPS_READY_MASK: u8 = 0x1 << Self::TPS_READY_SHIFT;
 
-    // Takes 100 ms to type a character.
-    // I'm going to arbitrarily choose a fixed 5 us per instruction.
-    const PRINT_DELAY_TICKS: usize = 20_000;
+    // An ASR-33 takes 100 ms to type a character.
+    const PRINT_DELAY_NS: u64 = 100_000_000;
+
+    // Input arrives from outside the simulation, so while keyboard interrupts
+    // are enabled it's looked for this often.
+    const KEYBOARD_POLL_NS: u64 = 100_000;
+
+    // Ctrl-C or Ctrl-D on the terminal stops the emulator with the control.
+    pub fn new_to_stdout(control: Control) -> Self {
+        Self::new(Arc::new(StdIo::new(control, None)))
+    }
 
-    pub fn new_to_stdout() -> Self {
-        Self::new(Arc::new(StdIo::new()))
+    // Ctrl and the escape key also interrupts it, even while the guest isn't
+    // reading the keyboard.
+    pub fn new_to_stdout_with_escape(control: Control, escape: char) -> Self {
+        Self::new(Arc::new(StdIo::new(control, Some(escape))))
     }
 
     pub fn new(device: Arc<dyn Tty>) -> Self {
+        let keyboard_poll_ns = device.needs_polling().then_some(0);
         Teletype {
             device,
 
             tps_maintenance_control: false,
             tps_interrupt_enabled: false,
-            printer_interrupted: false,
-            printer_interrupt_accepted: false,
             tps_ready: true,
-            tps_ticks_until_ready: 0,
+            tps_ready_ns: 0,
+            print_delay_ns: Self::PRINT_DELAY_NS,
 
             tks_interrupt_enabled: false,
-            keyboard_interrupted: false,
+            keyboard_poll_ns,
+
+            attached: None,
         }
     }
 
+    // For a faster terminal on the line.
+    pub fn with_chars_per_sec(mut self, cps: u32) -> Self {
+        self.print_delay_ns = 1_000_000_000 / cps as u64;
+        self
+    }
+
     fn tps_write(&mut self, val: u8) {
         self.tps_maintenance_control = (val & Self::TPS_MAINT_MASK) != 0;
-        let were_enabled = self.tps_interrupt_enabled;
+        // Disabling printer interrupts withdraws the request, so reenabling
+        // them while ready interrupts again. (Not clear if this is the actual
+        // hardware behavior, but it seems reasonable enough).
         self.tps_interrupt_enabled = (val & Self::TPS_INT_ENB_MASK) != 0;
-        if were_enabled && !self.tps_interrupt_enabled {
-            // If printer interrupts are disabled while tps_ready, clear this flag
-            // so when printer interrupts are reenabled, a new interrupt is fired.
-            // (Not clear if this is the actual hardware behavior, but it seems
-            // reasonable enough).
-            self.printer_interrupt_accepted = false;
-        }
 
         // Ignore writes to ready
     }
 
-    fn tpb_write(&mut self, val: u8) {
+    fn tpb_write(&mut self, val: u8, now: u64) {
         if self.tps_ready {
-            self.device.handle_output(val);
-            self.tps_ticks_until_ready = Self::PRINT_DELAY_TICKS;
+            match &mut self.attached {
+                Some(file) => {
+                    if let Err(e) = file.write_all(&[val]) {
+                        error!("Teletype: can't write to attached file: {e}");
+                    }
+                }
+                None => self.device.handle_output(val),
+            }
+            self.tps_ready_ns = now + self.print_delay_ns;
             self.tps_ready = false;
         } else {
             error!("Teletype: write to TPB of {val} when not ready");
@@ -302,14 +350,25 @@ impl Teletype {
             | ((self.tps_ready as u8) << Self::TPS_READY_SHIFT)
     }
 
-    fn tks_write(&mut self, val: u16) {
+    fn tks_write(&mut self, val: u16, now: u64) {
         self.tks_interrupt_enabled = (val & Self::TKS_INT_ENB_MASK) != 0;
+        self.keyboard_poll_ns = self
+            .polls_keyboard()
+            .then_some(now + Self::KEYBOARD_POLL_NS);
+    }
+
+    fn polls_keyboard(&self) -> bool {
+        self.tks_interrupt_enabled || self.device.needs_polling()
     }
 
     fn tks_read(&mut self) -> u16 {
+        self.tks_val(self.device.input_available())
+    }
+
+    fn tks_val(&self, done: bool) -> u16 {
         // BUSY, RDR ENB not used yet, always 0.
         ((self.tps_interrupt_enabled as u16) << Self::TPS_INT_ENB_SHIFT)
-            | ((self.device.input_available() as u16) << Self::TKS_DONE_SHIFT)
+            | ((done as u16) << Self::TKS_DONE_SHIFT)
     }
 
     fn tkb_read(&mut self) -> u8 {
@@ -322,84 +381,127 @@ impl Teletype {
 }
 
 impl MMIOHandler for Teletype {
-    fn tick(&mut self, _: &mut EmulatorState) -> Option<Interrupt> {
-        if self.tps_maintenance_control {
-            todo!()
+    fn tick(&mut self, emu: &mut EmulatorState) {
+        let now = emu.get_time_ns();
+        if !self.tps_ready && now >= self.tps_ready_ns {
+            self.tps_ready = true;
         }
-
-        if self.tps_ticks_until_ready == 1 {
-            self.printer_interrupt_accepted = false;
+        if self.polls_keyboard() {
+            self.keyboard_poll_ns = Some(now + Self::KEYBOARD_POLL_NS);
         }
-        self.tps_ticks_until_ready = self.tps_ticks_until_ready.saturating_sub(1);
-        if self.tps_ticks_until_ready == 0 {
-            self.tps_ready = true;
+        if !self.tks_interrupt_enabled && self.device.needs_polling() {
+            self.device.input_available();
         }
+    }
 
-        // Keyboard gets priority.
-        if self.device.input_available() && self.tks_interrupt_enabled {
-            self.keyboard_interrupted = true;
-            return Some(Interrupt {
-                prio: Self::PRINT_PRIO,
+    fn next_wake(&self) -> Option<u64> {
+        let printer = (!self.tps_ready).then_some(self.tps_ready_ns);
+        match (printer, self.keyboard_poll_ns) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    // Keyboard gets priority. It keeps interrupting until the character is
+    // read, while the printer interrupts once each time it becomes ready.
+    fn interrupts(&self, requests: &mut Vec<Interrupt>) {
+        if self.tks_interrupt_enabled && self.device.input_available() {
+            requests.push(Interrupt {
+                prio: Self::KEY_PRIO,
                 vector: Self::KEY_VECTOR,
+                edge: false,
             });
         }
 
-        if self.tps_ready && self.tps_interrupt_enabled & !self.printer_interrupt_accepted {
-            self.printer_interrupted = true;
-            return Some(Interrupt {
+        if self.tps_ready && self.tps_interrupt_enabled {
+            requests.push(Interrupt {
                 prio: Self::PRINT_PRIO,
                 vector: Self::PRINT_VECTOR,
+                edge: true,
             });
         }
+    }
 
-        None
+    // Only a character that has already arrived is seen.
+    fn peek(&self, _: &EmulatorState, addr: u16) -> Option<u16> {
+        let input = self.device.peek_input();
+        let val = match addr {
+            Self::TPS => self.tps_read() as u16,
+            Self::TPB => 0,
+            Self::TKS => self.tks_val(input.is_some()),
+            Self::TKB => input.unwrap_or(0) as u16,
+            _ => return None,
+        };
+        Some(val)
     }
 
-    fn read_byte(&mut self, _: &mut EmulatorState, addr: u16) -> u8 {
-        match addr {
+    fn read_byte(&mut self, _: &mut EmulatorState, addr: u16) -> Option<u8> {
+        let val = match addr {
             Self::TPS => self.tps_read(),
             Self::TPS_UPPER | Self::TPB | Self::TPB_UPPER => 0,
             Self::TKS => self.tks_read() as u8,
             Self::TKS_UPPER => (self.tks_read() >> u8::BITS) as u8,
             Self::TKB => self.tkb_read(),
-            _ => panic!("Teletype doesn't handle address {addr:o}"),
-        }
+            _ => return None,
+        };
+        Some(val)
     }
 
-    fn read_word(&mut self, emu: &mut EmulatorState, addr: u16) -> u16 {
+    fn read_word(&mut self, emu: &mut EmulatorState, addr: u16) -> Option<u16> {
         if addr == Self::TKS {
-            self.tks_read()
+            Some(self.tks_read())
         } else {
-            self.read_byte(emu, addr) as u16
+            self.read_byte(emu, addr).map(u16::from)
         }
     }
 
-    fn write_byte(&mut self, _: &mut EmulatorState, addr: u16, val: u8) {
+    fn write_byte(&mut self, emu: &mut EmulatorState, addr: u16, val: u8) -> Option<()> {
         match addr {
             Self::TPS => self.tps_write(val),
-            Self::TPB => self.tpb_write(val),
-            Self::TKS => self.tks_write(val as u16),
+            Self::TPB => self.tpb_write(val, emu.get_time_ns()),
+            Self::TKS => self.tks_write(val as u16, emu.get_time_ns()),
             Self::TPS_UPPER | Self::TPB_UPPER | Self::TKB | Self::TKB_UPPER => (),
-            _ => panic!("Teletype doesn't handle address {addr:o}"),
+            _ => return None,
         }
+        Some(())
     }
 
-    fn write_word(&mut self, emu: &mut EmulatorState, addr: u16, val: u16) {
-        self.write_byte(emu, addr, val as u8);
-    }
-
-    fn interrupt_accepted(&mut self) {
-        if self.keyboard_interrupted {
-            self.keyboard_interrupted = false;
-        } else if self.printer_interrupted {
-            self.printer_interrupted = false;
-            self.printer_interrupt_accepted = true;
-        } else {
-            panic!("Teletype received interrupt_accepted() but didn't interrupt");
-        }
+    fn write_word(&mut self, emu: &mut EmulatorState, addr: u16, val: u16) -> Option<()> {
+        self.write_byte(emu, addr, val as u8)
     }
 
     fn default_addrs(&self) -> &[u16] {
         &[Self::TPS, Self::TPB, Self::TKS, Self::TKB]
     }
+
+    // Printer output goes to the file, rather than the terminal.
+    fn attach(&mut self, path: &Path) -> io::Result<()> {
+        self.attached = Some(File::create(path)?);
+        Ok(())
+    }
+
+    fn detach(&mut self) -> io::Result<()> {
+        self.attached = None;
+        Ok(())
+    }
+
+    // Characters buffered in the Tty, and the print delay, aren't saved.
+    fn save_state(&self, out: &mut SnapshotWriter) -> io::Result<()> {
+        out.bool(self.tps_maintenance_control)?;
+        out.bool(self.tps_interrupt_enabled)?;
+        out.bool(self.tps_ready)?;
+        out.u64(self.tps_ready_ns)?;
+        out.bool(self.tks_interrupt_enabled)
+    }
+
+    fn load_state(&mut self, input: &mut SnapshotReader) -> io::Result<()> {
+        self.tps_maintenance_control = input.bool()?;
+        self.tps_interrupt_enabled = input.bool()?;
+        self.tps_ready = input.bool()?;
+        self.tps_ready_ns = input.u64()?;
+        self.tks_interrupt_enabled = input.bool()?;
+        // Look for input straight away.
+        self.keyboard_poll_ns = self.polls_keyboard().then_some(0);
+        Ok(())
+    }
 }
@@ -1,9 +1,28 @@
 #![feature(ascii_char)]
 
+pub mod breakpoint;
+pub mod control;
 pub mod emulator;
 pub mod emulator_state;
+pub mod fpu;
+pub mod gdb;
+pub mod history;
 pub mod io;
+pub mod mmu;
+pub mod model;
+pub mod monitor;
+pub mod snapshot;
+pub mod stop;
+pub mod throttle;
+pub mod timing;
+pub mod trace;
 
-pub use emulator::{Emulator, ExecRet};
-pub use emulator_state::{EmulatorState, Status};
+pub use breakpoint::{Breakpoint, Condition, WatchAccess};
+pub use control::Control;
+pub use emulator::Emulator;
+pub use emulator_state::{EmulatorState, ProcessorMode, Status};
 pub use io::MMIOHandler;
+pub use model::CpuModel;
+pub use stop::{EmulatorError, StopReason};
+pub use throttle::Throttle;
+pub use trace::{TraceFormat, Tracer};
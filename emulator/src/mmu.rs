@@ -0,0 +1,301 @@
+use crate::ProcessorMode;
+use crate::snapshot::{SnapshotReader, SnapshotWriter};
+use common::asm::Reg;
+use common::constants::{MEM_END, MMIO_PHYS_START, MMIO_START};
+
+use num_traits::ToPrimitive;
+
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+// Reason(s) an access was aborted, in the same bits as SR0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MmuFault(pub u16);
+
+// KT11 memory management unit. Each of kernel and user mode have eight pages,
+// each with a Page Address Register (PAR) giving the base of the page in 64
+// byte blocks, and a Page Descriptor Register (PDR) giving its length and
+// access control. Addresses are 16 bit virtual and 18 bit physical.
+//
+// Only kernel and user register sets exist, as on the 11/40; supervisor (and
+// illegal) mode are mapped through the user set.
+#[derive(Debug, Default, Clone)]
+pub struct Mmu {
+    par: [[u16; Mmu::NUM_PAGES]; 2],
+    pdr: [[u16; Mmu::NUM_PAGES]; 2],
+    sr0: u16,
+    sr1: u16,
+    sr2: u16,
+    sr3: u16,
+
+    sr1_entries: u8, // Number of register changes recorded in SR1.
+}
+
+impl Mmu {
+    pub const KERNEL_PDR: u16 = 0o172300;
+    pub const KERNEL_PAR: u16 = 0o172340;
+    pub const USER_PDR: u16 = 0o177600;
+    pub const USER_PAR: u16 = 0o177640;
+    pub const SR0: u16 = 0o177572;
+    pub const SR1: u16 = 0o177574;
+    pub const SR2: u16 = 0o177576;
+    pub const SR3: u16 = 0o172516;
+
+    pub const ADDRS: [u16; 36] = Self::addrs();
+
+    pub const ABORT_VECTOR: u16 = 0o250;
+
+    pub const NUM_PAGES: usize = 8;
+    pub const PAGE_SIZE: u32 = 0o20000; // Largest page, in bytes.
+
+    // Virtual address fields.
+    const PAGE_SHIFT: u16 = 13;
+    const BLOCK_SHIFT: u16 = 6;
+    const BLOCK_MASK: u16 = 0o177;
+    const DISPLACEMENT_MASK: u16 = 0o17777; // Offset within the page.
+
+    const PAF_MASK: u16 = 0o7777; // Page address field, 18 bit.
+
+    // PDR fields.
+    const PLF_SHIFT: u16 = 8;
+    const PLF_MASK: u16 = 0o177; // Page length field, in blocks.
+    pub const PDR_W: u16 = 0o100; // Page has been written.
+    pub const PDR_ED: u16 = 0o10; // Expansion direction (down if set).
+    const ACF_MASK: u16 = 0o7;
+    const PDR_WRITABLE: u16 = (Self::PLF_MASK << Self::PLF_SHIFT) | Self::PDR_ED | Self::ACF_MASK;
+
+    // Access control field values. Those that also request memory management
+    // traps (1, 4 and 5 on the 11/45) are treated as their non-trapping
+    // equivalents.
+    pub const ACF_NON_RESIDENT: u16 = 0;
+    pub const ACF_READ_ONLY: u16 = 2;
+    pub const ACF_READ_WRITE: u16 = 6;
+
+    // SR0 fields.
+    pub const SR0_ABORT_NON_RESIDENT: u16 = 0x1 << 15;
+    pub const SR0_ABORT_PAGE_LENGTH: u16 = 0x1 << 14;
+    pub const SR0_ABORT_READ_ONLY: u16 = 0x1 << 13;
+    const SR0_ABORT_MASK: u16 =
+        Self::SR0_ABORT_NON_RESIDENT | Self::SR0_ABORT_PAGE_LENGTH | Self::SR0_ABORT_READ_ONLY;
+    pub const SR0_MAINT: u16 = 0x1 << 8;
+    const SR0_MODE_SHIFT: u16 = 5;
+    const SR0_PAGE_SHIFT: u16 = 1;
+    pub const SR0_ENABLE: u16 = 0x1;
+    const SR0_WRITABLE: u16 = Self::SR0_ABORT_MASK | Self::SR0_MAINT | Self::SR0_ENABLE;
+
+    const fn addrs() -> [u16; 36] {
+        let mut addrs = [0; 36];
+        let mut i = 0;
+        while i < Self::NUM_PAGES {
+            let off = (i as u16) * 2;
+            addrs[i] = Self::KERNEL_PDR + off;
+            addrs[i + 8] = Self::KERNEL_PAR + off;
+            addrs[i + 16] = Self::USER_PDR + off;
+            addrs[i + 24] = Self::USER_PAR + off;
+            i += 1;
+        }
+        addrs[32] = Self::SR0;
+        addrs[33] = Self::SR1;
+        addrs[34] = Self::SR2;
+        addrs[35] = Self::SR3;
+        addrs
+    }
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Only SR0 and SR3 are cleared by RESET; the page registers are kept.
+    pub fn reset(&mut self) {
+        self.sr0 = 0;
+        self.sr3 = 0;
+    }
+
+    pub fn save(&self, out: &mut SnapshotWriter) -> io::Result<()> {
+        for reg in self.par.iter().chain(&self.pdr).flatten() {
+            out.u16(*reg)?;
+        }
+        for reg in [self.sr0, self.sr1, self.sr2, self.sr3] {
+            out.u16(reg)?;
+        }
+        out.u8(self.sr1_entries)
+    }
+
+    pub fn load(&mut self, input: &mut SnapshotReader) -> io::Result<()> {
+        for reg in self.par.iter_mut().chain(&mut self.pdr).flatten() {
+            *reg = input.u16()?;
+        }
+        for reg in [&mut self.sr0, &mut self.sr1, &mut self.sr2, &mut self.sr3] {
+            *reg = input.u16()?;
+        }
+        self.sr1_entries = input.u8()?;
+        Ok(())
+    }
+
+    pub fn enabled(&self) -> bool {
+        (self.sr0 & Self::SR0_ENABLE) != 0
+    }
+
+    // SR0-SR2 stop tracking once an abort is recorded, until the abort bits
+    // are cleared, so the OS can inspect them.
+    fn frozen(&self) -> bool {
+        (self.sr0 & Self::SR0_ABORT_MASK) != 0
+    }
+
+    fn set_idx(mode: ProcessorMode) -> usize {
+        match mode {
+            ProcessorMode::Kernel => 0,
+            _ => 1,
+        }
+    }
+
+    // Where a virtual address goes with relocation disabled: the bottom 56 KiB
+    // are mapped directly and the top 8 KiB to the I/O page.
+    fn unmapped(addr: u16) -> u32 {
+        if addr >= MMIO_START {
+            MMIO_PHYS_START + (addr - MMIO_START) as u32
+        } else {
+            addr as u32
+        }
+    }
+
+    // Translate a virtual address to a physical one, without side effects.
+    pub fn map(&self, addr: u16, mode: ProcessorMode, access: Access) -> Result<u32, MmuFault> {
+        if !self.enabled() {
+            return Ok(Self::unmapped(addr));
+        }
+
+        let set = Self::set_idx(mode);
+        let page = (addr >> Self::PAGE_SHIFT) as usize;
+        let pdr = self.pdr[set][page];
+
+        let mut abort = 0;
+        match pdr & Self::ACF_MASK {
+            1 | 2 => {
+                if access == Access::Write {
+                    abort |= Self::SR0_ABORT_READ_ONLY;
+                }
+            }
+            4..=6 => (),
+            _ => abort |= Self::SR0_ABORT_NON_RESIDENT,
+        }
+
+        let block = (addr >> Self::BLOCK_SHIFT) & Self::BLOCK_MASK;
+        let plf = (pdr >> Self::PLF_SHIFT) & Self::PLF_MASK;
+        let expands_down = (pdr & Self::PDR_ED) != 0;
+        if (expands_down && block < plf) || (!expands_down && block > plf) {
+            abort |= Self::SR0_ABORT_PAGE_LENGTH;
+        }
+
+        if abort != 0 {
+            return Err(MmuFault(abort));
+        }
+
+        let base = ((self.par[set][page] & Self::PAF_MASK) as u32) << Self::BLOCK_SHIFT;
+        Ok((base + (addr & Self::DISPLACEMENT_MASK) as u32) & (MEM_END - 1))
+    }
+
+    // Called at the start of each instruction.
+    pub fn begin_ins(&mut self, pc: u16) {
+        if self.frozen() {
+            return;
+        }
+        self.sr1 = 0;
+        self.sr1_entries = 0;
+        self.sr2 = pc;
+    }
+
+    pub fn record_abort(&mut self, fault: MmuFault, mode: ProcessorMode, addr: u16) {
+        if self.frozen() {
+            return;
+        }
+        let page = addr >> Self::PAGE_SHIFT;
+        self.sr0 &= Self::SR0_WRITABLE & !Self::SR0_ABORT_MASK;
+        self.sr0 |= fault.0
+            | (mode.to_u16().unwrap() << Self::SR0_MODE_SHIFT)
+            | (page << Self::SR0_PAGE_SHIFT);
+    }
+
+    // Record an autoincrement/autodecrement in SR1, so the OS can back out of
+    // an aborted instruction.
+    pub fn record_reg_change(&mut self, reg: Reg, delta: i16) {
+        if self.frozen() || self.sr1_entries >= 2 {
+            return;
+        }
+        let entry = (((delta as u16) & 0o37) << 3) | reg.to_u16().unwrap();
+        self.sr1 |= entry << (8 * self.sr1_entries);
+        self.sr1_entries += 1;
+    }
+
+    pub fn mark_written(&mut self, addr: u16, mode: ProcessorMode) {
+        if !self.enabled() {
+            return;
+        }
+        let page = (addr >> Self::PAGE_SHIFT) as usize;
+        self.pdr[Self::set_idx(mode)][page] |= Self::PDR_W;
+    }
+
+    fn page_reg(addr: u16, base: u16) -> Option<usize> {
+        if (base..base + 2 * Self::NUM_PAGES as u16).contains(&addr) {
+            Some(((addr - base) / 2) as usize)
+        } else {
+            None
+        }
+    }
+
+    // None if there's no register at the address.
+    pub fn read_reg(&self, addr: u16) -> Option<u16> {
+        let addr = addr & !0x1;
+        if let Some(page) = Self::page_reg(addr, Self::KERNEL_PDR) {
+            return Some(self.pdr[0][page]);
+        }
+        if let Some(page) = Self::page_reg(addr, Self::KERNEL_PAR) {
+            return Some(self.par[0][page]);
+        }
+        if let Some(page) = Self::page_reg(addr, Self::USER_PDR) {
+            return Some(self.pdr[1][page]);
+        }
+        if let Some(page) = Self::page_reg(addr, Self::USER_PAR) {
+            return Some(self.par[1][page]);
+        }
+        match addr {
+            Self::SR0 => Some(self.sr0),
+            Self::SR1 => Some(self.sr1),
+            Self::SR2 => Some(self.sr2),
+            Self::SR3 => Some(self.sr3),
+            _ => None,
+        }
+    }
+
+    // Loading either a PAR or a PDR clears the PDR's W bit.
+    pub fn write_reg(&mut self, addr: u16, val: u16) -> Option<()> {
+        let addr = addr & !0x1;
+        if let Some(page) = Self::page_reg(addr, Self::KERNEL_PDR) {
+            self.pdr[0][page] = val & Self::PDR_WRITABLE;
+        } else if let Some(page) = Self::page_reg(addr, Self::KERNEL_PAR) {
+            self.par[0][page] = val & Self::PAF_MASK;
+            self.pdr[0][page] &= !Self::PDR_W;
+        } else if let Some(page) = Self::page_reg(addr, Self::USER_PDR) {
+            self.pdr[1][page] = val & Self::PDR_WRITABLE;
+        } else if let Some(page) = Self::page_reg(addr, Self::USER_PAR) {
+            self.par[1][page] = val & Self::PAF_MASK;
+            self.pdr[1][page] &= !Self::PDR_W;
+        } else {
+            match addr {
+                Self::SR0 => {
+                    self.sr0 = (self.sr0 & !Self::SR0_WRITABLE) | (val & Self::SR0_WRITABLE);
+                }
+                Self::SR1 | Self::SR2 => (), // Read only.
+                // Only stored; there's no separate I/D space or 22 bit mapping.
+                Self::SR3 => self.sr3 = val,
+                _ => return None,
+            }
+        }
+        Some(())
+    }
+}
@@ -0,0 +1,147 @@
+use crate::timing::{self, Timing};
+use common::asm::*;
+
+use std::fmt;
+use std::str::FromStr;
+
+// The processor being emulated. It decides which instructions exist, which CPU
+// registers are in the I/O page, and a few quirks where the models disagree.
+// Generic accepts everything the emulator implements.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CpuModel {
+    #[default]
+    Generic,
+    Pdp1120,
+    Pdp1140, // With the KE11-E EIS, KE11-F FIS and KT11-D MMU options.
+    Pdp1145, // With the FP11-B.
+    Pdp1170, // With the FP11-C.
+}
+
+impl CpuModel {
+    pub const ALL: [CpuModel; 5] = [
+        CpuModel::Generic,
+        CpuModel::Pdp1120,
+        CpuModel::Pdp1140,
+        CpuModel::Pdp1145,
+        CpuModel::Pdp1170,
+    ];
+
+    // MUL, DIV, ASH, ASHC and XOR.
+    pub fn has_eis(self) -> bool {
+        self != CpuModel::Pdp1120
+    }
+
+    pub fn has_fis(self) -> bool {
+        matches!(self, CpuModel::Generic | CpuModel::Pdp1140)
+    }
+
+    pub fn has_fp11(self) -> bool {
+        matches!(
+            self,
+            CpuModel::Generic | CpuModel::Pdp1145 | CpuModel::Pdp1170
+        )
+    }
+
+    // SOB, SXT, MARK and RTT.
+    pub fn has_extended_ins(self) -> bool {
+        self != CpuModel::Pdp1120
+    }
+
+    pub fn has_spl(self) -> bool {
+        self.has_fp11()
+    }
+
+    pub fn has_mmu(self) -> bool {
+        self != CpuModel::Pdp1120
+    }
+
+    // None of the real models here have MFPS and MTPS.
+    pub fn has_ps_ins(self) -> bool {
+        self == CpuModel::Generic
+    }
+
+    // The 11/20 and 11/40 have a fixed stack limit of 0o400.
+    pub fn has_stack_limit_reg(self) -> bool {
+        self.has_fp11()
+    }
+
+    pub fn has_cpu_error_reg(self) -> bool {
+        matches!(self, CpuModel::Generic | CpuModel::Pdp1170)
+    }
+
+    // OPR R,(R)+ and OPR R,-(R) use the updated R as the source, and
+    // JMP (R)+ and JSR reg,(R)+ jump to it.
+    pub fn uses_updated_reg(self) -> bool {
+        self == CpuModel::Pdp1120
+    }
+
+    // JMP and JSR with a register destination trap through 4 rather than 10.
+    pub fn reg_jmp_bus_error(self) -> bool {
+        self == CpuModel::Pdp1120
+    }
+
+    // The 11/20 is close enough to the 11/40, and the 11/45 to the 11/70.
+    pub fn timing(self) -> &'static Timing {
+        match self {
+            CpuModel::Generic | CpuModel::Pdp1120 | CpuModel::Pdp1140 => &timing::PDP1140,
+            CpuModel::Pdp1145 | CpuModel::Pdp1170 => &timing::PDP1170,
+        }
+    }
+
+    // Whether the model implements an instruction, rather than it being
+    // reserved.
+    pub fn has_ins(self, ins: &Ins) -> bool {
+        use SingleOperandOpcode::*;
+        match ins {
+            Ins::Eis(_) => self.has_eis(),
+            Ins::Fis(_) => self.has_fis(),
+            Ins::FpMisc(_) | Ins::FpSingleOperand(_) | Ins::Fp(_) => self.has_fp11(),
+            Ins::Sob(_) | Ins::Mark(_) => self.has_extended_ins(),
+            Ins::Misc(MiscIns {
+                op: MiscOpcode::Rtt,
+            }) => self.has_extended_ins(),
+            Ins::Spl(_) => self.has_spl(),
+            Ins::SingleOperand(ins) => match ins.op {
+                Sxt => self.has_extended_ins(),
+                Mfpi | Mtpi => self.has_mmu(),
+                // The 11/40's KT11-D has no separate D space.
+                Mfpd | Mtpd => self.has_mmu() && self != CpuModel::Pdp1140,
+                Mfps | Mtps => self.has_ps_ins(),
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for CpuModel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            CpuModel::Generic => "generic",
+            CpuModel::Pdp1120 => "11/20",
+            CpuModel::Pdp1140 => "11/40",
+            CpuModel::Pdp1145 => "11/45",
+            CpuModel::Pdp1170 => "11/70",
+        };
+        write!(f, "{name}")
+    }
+}
+
+// Accepts the names above, with or without the "11/".
+impl FromStr for CpuModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.strip_prefix("11/").unwrap_or(s);
+        CpuModel::ALL
+            .into_iter()
+            .find(|model| model.to_string().trim_start_matches("11/") == name)
+            .ok_or_else(|| {
+                let names: Vec<String> = CpuModel::ALL.iter().map(|m| m.to_string()).collect();
+                format!(
+                    "unknown CPU model {s:?}, expected one of {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
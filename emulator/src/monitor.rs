@@ -0,0 +1,347 @@
+use crate::breakpoint::{Breakpoint, Trigger};
+use crate::{Emulator, Status, StopReason};
+use aout::Aout;
+use common::asm::Reg;
+use common::constants::{MAX_INS_WORDS, MMIO_START, WORD_SIZE};
+use disassembler::{Disassembled, disassemble_at};
+
+use bytemuck::cast_slice;
+
+use std::fmt::Write;
+use std::fs::File;
+use std::path::Path;
+
+// What the monitor wants done after a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Output(String),
+    Quit,
+}
+
+// A location the monitor can examine or deposit to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Loc {
+    Mem(u16),
+    Reg(Reg),
+    Ps,
+}
+
+const HELP: &str = "\
+examine (e) loc[-loc]      show memory or a register, in octal
+deposit (d) loc val        set memory or a register
+step (s) [n]               run n instructions
+go (g) [addr]              run, from addr if given
+continue (c)               run from the pc
+break (b) [addr [cond]]    stop before the instruction at addr; list with none
+nobreak addr|all           remove breakpoints
+show devices|break         list devices or breakpoints
+attach dev file            connect a file to a device
+detach dev                 disconnect it
+load file                  load an a.out binary
+disassemble (dis) [a[-b]]  disassemble from a, or the pc
+quit (q)
+Registers are r0 to r5, sp, pc and ps. Numbers are octal.";
+
+// The number of instructions disassembled without an end address.
+const DIS_COUNT: usize = 8;
+
+// A console for a stopped emulator, in the style of SIMH's sim> prompt. This
+// runs a command line, and gives back what to print; running the guest
+// blocks until it stops again.
+pub fn exec(emu: &mut Emulator, line: &str) -> Result<Outcome, String> {
+    let mut words = line.split_whitespace();
+    let Some(cmd) = words.next() else {
+        return Ok(Outcome::Output(String::new()));
+    };
+    let args: Vec<&str> = words.collect();
+    let out = match cmd.to_lowercase().as_str() {
+        "e" | "ex" | "examine" => examine(emu, &args)?,
+        "d" | "dep" | "deposit" => deposit(emu, &args)?,
+        "s" | "step" => {
+            let n = match args.as_slice() {
+                [] => 1,
+                [n] => n.parse().map_err(|_| format!("bad count {n}"))?,
+                _ => return Err("usage: step [n]".into()),
+            };
+            run(emu, |emu| emu.run_for(n))
+        }
+        "g" | "go" => {
+            match args.as_slice() {
+                [] => (),
+                [addr] => emu.reg_write_word(Reg::PC, parse_num(addr)?),
+                _ => return Err("usage: go [addr]".into()),
+            }
+            run(emu, Emulator::run)
+        }
+        "c" | "cont" | "continue" => run(emu, Emulator::run),
+        "b" | "break" => set_break(emu, &args)?,
+        "nobreak" => remove_break(emu, &args)?,
+        "show" => match args.as_slice() {
+            ["dev" | "devices"] => show_devices(emu),
+            ["break"] => show_breaks(emu),
+            _ => return Err("usage: show devices|break".into()),
+        },
+        "attach" => {
+            let [dev, path] = args[..] else {
+                return Err("usage: attach dev file".into());
+            };
+            emu.attach(dev, Path::new(path))
+                .map_err(|e| e.to_string())?;
+            String::new()
+        }
+        "detach" => {
+            let [dev] = args[..] else {
+                return Err("usage: detach dev".into());
+            };
+            emu.detach(dev).map_err(|e| e.to_string())?;
+            String::new()
+        }
+        "load" => {
+            let [path] = args[..] else {
+                return Err("usage: load file".into());
+            };
+            let mut file = File::open(path).map_err(|e| format!("{path}: {e}"))?;
+            let aout = Aout::try_read_from(&mut file).map_err(|e| format!("{path}: {e}"))?;
+            if aout.text.len() > MMIO_START as usize {
+                return Err(format!("{path}: too big to load below the I/O page"));
+            }
+            emu.load_aout(&aout);
+            emu.reg_write_word(Reg::PC, aout.entry_point);
+            String::new()
+        }
+        "dis" | "disassemble" => disassemble(emu, &args)?,
+        "help" | "?" => HELP.into(),
+        "q" | "quit" | "exit" => return Ok(Outcome::Quit),
+        _ => return Err(format!("unknown command {cmd}")),
+    };
+    Ok(Outcome::Output(out))
+}
+
+fn parse_num(s: &str) -> Result<u16, String> {
+    u16::from_str_radix(s, 8).map_err(|_| format!("bad octal number {s}"))
+}
+
+fn parse_loc(s: &str) -> Result<Loc, String> {
+    let reg = match s.to_lowercase().as_str() {
+        "r0" => Reg::R0,
+        "r1" => Reg::R1,
+        "r2" => Reg::R2,
+        "r3" => Reg::R3,
+        "r4" => Reg::R4,
+        "r5" => Reg::R5,
+        "sp" | "r6" => Reg::SP,
+        "pc" | "r7" => Reg::PC,
+        "ps" | "psw" => return Ok(Loc::Ps),
+        _ => return parse_num(s).map(Loc::Mem),
+    };
+    Ok(Loc::Reg(reg))
+}
+
+fn read_loc(emu: &mut Emulator, loc: Loc) -> Result<u16, String> {
+    match loc {
+        Loc::Mem(addr) => {
+            if addr & 1 != 0 {
+                return Err(format!("odd address {addr:06o}"));
+            }
+            let lo = emu.mem_peek_byte(addr);
+            let hi = emu.mem_peek_byte(addr + 1);
+            match (lo, hi) {
+                (Some(lo), Some(hi)) => Ok(u16::from_le_bytes([lo, hi])),
+                _ => Err(unreadable(addr)),
+            }
+        }
+        Loc::Reg(reg) => Ok(emu.reg_read_word(reg)),
+        Loc::Ps => Ok(emu.get_state().get_status().to_raw()),
+    }
+}
+
+fn unreadable(addr: u16) -> String {
+    if addr >= MMIO_START {
+        format!("no register at {addr:06o} that can be read without side effects")
+    } else {
+        format!("nothing at {addr:06o}")
+    }
+}
+
+fn examine(emu: &mut Emulator, args: &[&str]) -> Result<String, String> {
+    let [arg] = args else {
+        return Err("usage: examine loc[-loc]".into());
+    };
+    let mut out = String::new();
+    match arg.split_once('-') {
+        Some((start, end)) => {
+            let start = parse_num(start)?;
+            let end = parse_num(end)?;
+            for addr in (start..=end).step_by(WORD_SIZE as usize) {
+                let val = read_loc(emu, Loc::Mem(addr))?;
+                writeln!(out, "{addr:06o}: {val:06o}").unwrap();
+            }
+        }
+        None => {
+            let loc = parse_loc(arg)?;
+            let val = read_loc(emu, loc)?;
+            let name = match loc {
+                Loc::Mem(addr) => format!("{addr:06o}"),
+                Loc::Reg(reg) => reg.to_string(),
+                Loc::Ps => "ps".into(),
+            };
+            writeln!(out, "{name}: {val:06o}").unwrap();
+        }
+    }
+    Ok(out.trim_end().into())
+}
+
+fn deposit(emu: &mut Emulator, args: &[&str]) -> Result<String, String> {
+    let [loc, val] = args else {
+        return Err("usage: deposit loc val".into());
+    };
+    let val = parse_num(val)?;
+    match parse_loc(loc)? {
+        Loc::Mem(addr) => {
+            if addr & 1 != 0 {
+                return Err(format!("odd address {addr:06o}"));
+            }
+            let [lo, hi] = val.to_le_bytes();
+            emu.mem_try_write_byte(addr, lo)
+                .and_then(|_| emu.mem_try_write_byte(addr + 1, hi))
+                .ok_or_else(|| format!("nothing at {addr:06o}"))?;
+        }
+        Loc::Reg(reg) => emu.reg_write_word(reg, val),
+        Loc::Ps => emu.get_state_mut().set_status(Status::from_raw(val)),
+    }
+    Ok(String::new())
+}
+
+// Runs the guest, then says why it stopped and where.
+fn run(
+    emu: &mut Emulator,
+    f: impl FnOnce(&mut Emulator) -> Result<StopReason, crate::EmulatorError>,
+) -> String {
+    let reason = match f(emu) {
+        Ok(StopReason::InsLimit) => "step expired".to_string(),
+        Ok(reason) => reason.to_string(),
+        Err(e) => return e.to_string(),
+    };
+    let pc = emu.reg_read_word(Reg::PC);
+    match ins_at(emu, pc) {
+        Some(ins) => format!("{reason}, pc {pc:06o} ({ins})"),
+        None => format!("{reason}, pc {pc:06o}"),
+    }
+}
+
+fn ins_at(emu: &mut Emulator, pc: u16) -> Option<String> {
+    let dis = disassemble_range(emu, pc, pc).into_iter().next()?;
+    let ins = dis.ins?.display_with_pc(pc).to_string();
+    Some(ins.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn set_break(emu: &mut Emulator, args: &[&str]) -> Result<String, String> {
+    let Some((addr, cond)) = args.split_first() else {
+        return Ok(show_breaks(emu));
+    };
+    let mut bp = Breakpoint::exec(parse_num(addr)?);
+    if !cond.is_empty() {
+        bp = bp.when(cond.join(" ").parse()?);
+    }
+    let id = emu.add_breakpoint(bp);
+    Ok(format!("breakpoint {id}"))
+}
+
+fn remove_break(emu: &mut Emulator, args: &[&str]) -> Result<String, String> {
+    let [arg] = args else {
+        return Err("usage: nobreak addr|all".into());
+    };
+    let addr = (*arg != "all").then(|| parse_num(arg)).transpose()?;
+    let ids: Vec<_> = emu
+        .breakpoints()
+        .iter()
+        .filter(|(_, bp)| addr.is_none_or(|addr| bp.trigger == Trigger::Exec(addr)))
+        .map(|(id, _)| id)
+        .collect();
+    if ids.is_empty() {
+        return Err(format!("no breakpoint at {arg}"));
+    }
+    for id in ids {
+        emu.remove_breakpoint(id);
+    }
+    Ok(String::new())
+}
+
+fn show_breaks(emu: &Emulator) -> String {
+    let mut out = String::new();
+    for (id, bp) in emu.breakpoints().iter() {
+        write!(out, "{id}: {}", bp.trigger).unwrap();
+        if let Some(cond) = &bp.cond {
+            write!(out, " when {cond}").unwrap();
+        }
+        out.push('\n');
+    }
+    out.trim_end().into()
+}
+
+fn show_devices(emu: &Emulator) -> String {
+    let mut out = String::new();
+    for (dev, addrs) in emu.devices() {
+        write!(out, "{}:", dev.name()).unwrap();
+        for addr in addrs {
+            write!(out, " {addr:06o}").unwrap();
+        }
+        out.push('\n');
+    }
+    out.trim_end().into()
+}
+
+// The instructions from start up to end, stopping early at anything that
+// can't be read. Device registers are only read if that has no side effects.
+fn disassemble_range(emu: &mut Emulator, start: u16, end: u16) -> Vec<Disassembled> {
+    let len = (end.saturating_sub(start) / WORD_SIZE + MAX_INS_WORDS) as usize;
+    let mut words = Vec::new();
+    for i in 0..len {
+        let Some(addr) = start.checked_add(i as u16 * WORD_SIZE) else {
+            break;
+        };
+        match (emu.mem_peek_byte(addr), emu.mem_peek_byte(addr + 1)) {
+            (Some(lo), Some(hi)) => words.push(u16::from_le_bytes([lo, hi])),
+            _ => break,
+        }
+    }
+    // An instruction running past what could be read is left out.
+    let readable = words.len();
+    words.resize(readable + MAX_INS_WORDS as usize, 0);
+    let readable_end = readable * WORD_SIZE as usize;
+    // Disassembled from 0 and moved, as the padding can run past the top of
+    // memory.
+    let mut out = disassemble_at(cast_slice(&words), 0);
+    out.retain(|dis| {
+        start as usize + dis.addr as usize <= end as usize
+            && dis.addr as usize + dis.repr.len() * WORD_SIZE as usize <= readable_end
+    });
+    for dis in &mut out {
+        dis.addr += start;
+    }
+    out
+}
+
+fn disassemble(emu: &mut Emulator, args: &[&str]) -> Result<String, String> {
+    let (start, end) = match args {
+        [] => (emu.reg_read_word(Reg::PC), None),
+        [range] => match range.split_once('-') {
+            Some((start, end)) => (parse_num(start)?, Some(parse_num(end)?)),
+            None => (parse_num(range)?, None),
+        },
+        _ => return Err("usage: disassemble [addr[-addr]]".into()),
+    };
+    if start & 1 != 0 {
+        return Err(format!("odd address {start:06o}"));
+    }
+    let limit = end.unwrap_or(start.saturating_add(DIS_COUNT as u16 * MAX_INS_WORDS * WORD_SIZE));
+    let mut lines = disassemble_range(emu, start, limit);
+    if end.is_none() {
+        lines.truncate(DIS_COUNT);
+    }
+    if lines.is_empty() {
+        return Err(unreadable(start));
+    }
+    let lines: Vec<_> = lines.iter().map(Disassembled::to_string).collect();
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,90 @@
+use std::io::{self, Read, Write};
+
+// Snapshots are a little-endian dump of the machine: a header, the CPU state
+// (memory, registers, PSW, MMU and FPU), then each device's state, in the
+// order the devices were added. Devices aren't named, so a snapshot can only
+// be loaded into an emulator set up the same way as the one that saved it.
+pub const MAGIC: [u8; 4] = *b"P11S";
+pub const VERSION: u16 = 3;
+
+pub fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+pub struct SnapshotWriter<'a> {
+    out: &'a mut dyn Write,
+}
+
+impl<'a> SnapshotWriter<'a> {
+    pub fn new(out: &'a mut dyn Write) -> Self {
+        SnapshotWriter { out }
+    }
+
+    pub fn bytes(&mut self, val: &[u8]) -> io::Result<()> {
+        self.out.write_all(val)
+    }
+
+    pub fn bool(&mut self, val: bool) -> io::Result<()> {
+        self.u8(val as u8)
+    }
+
+    pub fn u8(&mut self, val: u8) -> io::Result<()> {
+        self.bytes(&[val])
+    }
+
+    pub fn u16(&mut self, val: u16) -> io::Result<()> {
+        self.bytes(&val.to_le_bytes())
+    }
+
+    pub fn u32(&mut self, val: u32) -> io::Result<()> {
+        self.bytes(&val.to_le_bytes())
+    }
+
+    pub fn u64(&mut self, val: u64) -> io::Result<()> {
+        self.bytes(&val.to_le_bytes())
+    }
+}
+
+pub struct SnapshotReader<'a> {
+    input: &'a mut dyn Read,
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub fn new(input: &'a mut dyn Read) -> Self {
+        SnapshotReader { input }
+    }
+
+    pub fn bytes(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.input.read_exact(buf)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.bytes(&mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn bool(&mut self) -> io::Result<bool> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            val => Err(invalid(format!("invalid bool {val} in snapshot"))),
+        }
+    }
+
+    pub fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    pub fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+}
@@ -0,0 +1,64 @@
+use crate::breakpoint::WatchHit;
+use common::asm::Ins;
+
+use std::error::Error;
+use std::fmt;
+
+// Why running the emulator returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    // run_ins() ran an instruction, or waited for an interrupt.
+    Step,
+    // A HALT instruction.
+    Halted,
+    // A fault pushing onto the emergency stack, after a fault taking a trap
+    // or interrupt. The processor halts.
+    DoubleFault,
+    // Before the instruction at pc, by the breakpoint's ID.
+    Breakpoint { id: usize, pc: u16 },
+    // After the instruction that set off a watchpoint.
+    Watchpoint(WatchHit),
+    // run_for() ran all its instructions.
+    InsLimit,
+    // Control::stop().
+    HostStop,
+    // Control::interrupt().
+    HostInterrupt,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Step => write!(f, "step"),
+            StopReason::Halted => write!(f, "halted"),
+            StopReason::DoubleFault => write!(f, "double fault"),
+            StopReason::Breakpoint { id, pc } => write!(f, "breakpoint {id} at {pc:06o}"),
+            StopReason::Watchpoint(hit) => write!(f, "watchpoint {}: {hit}", hit.id),
+            StopReason::InsLimit => write!(f, "instruction limit"),
+            StopReason::HostStop => write!(f, "stopped"),
+            StopReason::HostInterrupt => write!(f, "interrupted"),
+        }
+    }
+}
+
+// A failure of the emulator itself, such as a feature it doesn't have, rather
+// than anything the guest can see. The machine may be left part way through
+// the instruction.
+#[derive(Debug, Clone)]
+pub struct EmulatorError {
+    pub pc: u16,
+    pub ins: Option<Box<Ins>>, // None if it couldn't be decoded.
+    pub message: String,
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "emulator error at pc {:06o}", self.pc)?;
+        if let Some(ins) = &self.ins {
+            write!(f, " ({})", ins.display_with_pc(self.pc))?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl Error for EmulatorError {}
@@ -0,0 +1,95 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// How fast the emulator runs, relative to the host's wall clock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Throttle {
+    // As fast as the host allows.
+    #[default]
+    Unlimited,
+    // Simulated time keeps pace with the wall clock, so the guest runs at the
+    // speed of the CPU model.
+    Model,
+    // A fixed number of instructions per second, up to MAX_RATE. Each
+    // instruction then takes the same simulated time, in place of the
+    // model's timing.
+    InsPerSec(u32),
+}
+
+impl Throttle {
+    // Simulated time is kept in whole ns.
+    pub const MAX_RATE: u32 = 1_000_000_000;
+
+    // The fixed instruction time, if any. Faster rates than MAX_RATE are
+    // taken as it.
+    pub fn ins_time_ns(self) -> Option<u32> {
+        match self {
+            Throttle::InsPerSec(rate) => Some((1_000_000_000 / rate).max(1)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Throttle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Throttle::Unlimited => write!(f, "unlimited"),
+            Throttle::Model => write!(f, "model"),
+            Throttle::InsPerSec(rate) => write!(f, "{rate}"),
+        }
+    }
+}
+
+// "unlimited", "model", or an instruction rate per second.
+impl FromStr for Throttle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unlimited" => Ok(Throttle::Unlimited),
+            "model" => Ok(Throttle::Model),
+            _ => match s.parse() {
+                Ok(rate @ 1..=Self::MAX_RATE) => Ok(Throttle::InsPerSec(rate)),
+                _ => Err(format!(
+                    "invalid speed {s:?}, expected unlimited, model, or up to {} instructions per second",
+                    Self::MAX_RATE
+                )),
+            },
+        }
+    }
+}
+
+// Keeps simulated time from running ahead of the wall clock, by sleeping. The
+// wall clock is only checked every CHECK_NS of simulated time. If the host
+// can't keep up, the guest just runs slow.
+#[derive(Debug)]
+pub struct Pacer {
+    start: Instant,
+    start_sim_ns: u64,
+    next_check_ns: u64,
+}
+
+impl Pacer {
+    const CHECK_NS: u64 = 1_000_000;
+
+    pub fn new(sim_ns: u64) -> Self {
+        Pacer {
+            start: Instant::now(),
+            start_sim_ns: sim_ns,
+            next_check_ns: sim_ns,
+        }
+    }
+
+    pub fn pace(&mut self, sim_ns: u64) {
+        if sim_ns < self.next_check_ns {
+            return;
+        }
+        self.next_check_ns = sim_ns + Self::CHECK_NS;
+        let target = Duration::from_nanos(sim_ns - self.start_sim_ns);
+        let wall = self.start.elapsed();
+        if target > wall {
+            std::thread::sleep(target - wall);
+        }
+    }
+}
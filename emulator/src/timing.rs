@@ -0,0 +1,195 @@
+use common::asm::*;
+
+// Instruction execution times, in ns, approximately as given in the processor
+// handbook tables. An instruction's time is its basic time, plus the time to
+// resolve its source and destination operands, which depends on their address
+// modes. Memory is assumed to be core on the 11/40, and to hit the cache on
+// the 11/70.
+#[derive(Debug)]
+pub struct Timing {
+    pub src: [u32; 8], // By address mode.
+    pub dst: [u32; 8], // Also used for single operand instructions.
+    pub jmp: [u32; 8], // Includes the basic time; mode 0 is illegal.
+
+    pub mov: u32,
+    pub double: u32,
+    pub single: u32,
+    pub branch: u32,
+    pub branch_not_taken: u32,
+    pub jsr: u32, // On top of jmp.
+    pub rts: u32,
+    pub rti: u32,
+    pub cc: u32,
+    pub halt: u32,
+    pub reset: u32,
+    pub trap: u32, // Any trap or interrupt, including EMT and TRAP.
+
+    pub sob: u32,
+    pub sob_not_taken: u32,
+    pub mark: u32,
+    pub spl: u32,
+    pub mul: u32,
+    pub div: u32,
+    pub ash: u32,
+    pub ashc: u32,
+
+    pub fis: [u32; 4], // FADD, FSUB, FMUL and FDIV.
+
+    pub fp_misc: u32,
+    pub fp_move: u32, // Loads, stores and conversions.
+    pub fp_add: u32,
+    pub fp_mul: u32,
+    pub fp_div: u32,
+    pub fp_double: u32, // Extra for double precision.
+}
+
+// While the processor waits for an interrupt, time passes in steps of this.
+pub const IDLE_NS: u32 = 1_000;
+
+pub const PDP1140: Timing = Timing {
+    src: [0, 780, 840, 1740, 840, 1740, 1460, 2360],
+    dst: [0, 900, 960, 1860, 960, 1860, 1580, 2480],
+    jmp: [0, 1760, 1760, 2340, 1760, 2340, 2200, 3100],
+
+    mov: 900,
+    double: 990,
+    single: 990,
+    branch: 880,
+    branch_not_taken: 760,
+    jsr: 1200,
+    rts: 2520,
+    rti: 2920,
+    cc: 1140,
+    halt: 1800,
+    reset: 80_000,
+    trap: 3820,
+
+    sob: 1560,
+    sob_not_taken: 1300,
+    mark: 2500,
+    spl: 1500,
+    mul: 8880,
+    div: 11_300,
+    ash: 3000,
+    ashc: 3500,
+
+    fis: [22_000, 22_500, 31_000, 45_000],
+
+    fp_misc: 1500,
+    fp_move: 3000,
+    fp_add: 6000,
+    fp_mul: 9000,
+    fp_div: 12_000,
+    fp_double: 3000,
+};
+
+pub const PDP1170: Timing = Timing {
+    src: [0, 300, 300, 600, 450, 750, 600, 900],
+    dst: [0, 450, 450, 750, 450, 750, 750, 1050],
+    jmp: [0, 600, 600, 900, 600, 900, 900, 1200],
+
+    mov: 300,
+    double: 300,
+    single: 300,
+    branch: 450,
+    branch_not_taken: 300,
+    jsr: 600,
+    rts: 900,
+    rti: 1350,
+    cc: 300,
+    halt: 1800,
+    reset: 10_000,
+    trap: 1800,
+
+    sob: 600,
+    sob_not_taken: 450,
+    mark: 1050,
+    spl: 450,
+    mul: 3300,
+    div: 7200,
+    ash: 1500,
+    ashc: 1800,
+
+    fis: [10_000, 10_000, 14_000, 20_000],
+
+    fp_misc: 600,
+    fp_move: 1000,
+    fp_add: 2700,
+    fp_mul: 3600,
+    fp_div: 4800,
+    fp_double: 2000,
+};
+
+impl Timing {
+    fn src_time(&self, operand: &Operand) -> u32 {
+        self.src[operand.mode as usize]
+    }
+
+    fn dst_time(&self, operand: &Operand) -> u32 {
+        self.dst[operand.mode as usize]
+    }
+
+    // The time taken by an instruction, other than any trap it causes, given
+    // whether it branched, and whether the FPU is in double precision mode.
+    pub fn ins_time(&self, ins: &Ins, branched: bool, double: bool) -> u32 {
+        let fp_double = if double { self.fp_double } else { 0 };
+        match ins {
+            Ins::DoubleOperand(ins) => {
+                let base = match ins.op {
+                    DoubleOperandOpcode::Mov | DoubleOperandOpcode::MovB => self.mov,
+                    _ => self.double,
+                };
+                base + self.src_time(&ins.src) + self.dst_time(&ins.dst)
+            }
+            Ins::Branch(_) if branched => self.branch,
+            Ins::Branch(_) => self.branch_not_taken,
+            Ins::Jmp(ins) => self.jmp[ins.dst.mode as usize],
+            Ins::Jsr(ins) => self.jmp[ins.dst.mode as usize] + self.jsr,
+            Ins::Rts(_) => self.rts,
+            Ins::SingleOperand(ins) => self.single + self.dst_time(&ins.dst),
+            Ins::Eis(ins) => {
+                let base = match ins.op {
+                    EisOpcode::Mul => self.mul,
+                    EisOpcode::Div => self.div,
+                    EisOpcode::Ash => self.ash,
+                    EisOpcode::Ashc => self.ashc,
+                    EisOpcode::Xor => return self.double + self.dst_time(&ins.operand),
+                };
+                base + self.src_time(&ins.operand)
+            }
+            Ins::CC(_) => self.cc,
+            Ins::Misc(ins) => match ins.op {
+                MiscOpcode::Halt | MiscOpcode::Wait => self.halt,
+                MiscOpcode::Rti | MiscOpcode::Rtt => self.rti,
+                MiscOpcode::Reset => self.reset,
+                // These, like EMT and TRAP, are all vectoring, which is
+                // counted as a trap.
+                MiscOpcode::Bpt | MiscOpcode::Iot => 0,
+            },
+            Ins::Trap(_) => 0,
+            Ins::Sob(_) if branched => self.sob,
+            Ins::Sob(_) => self.sob_not_taken,
+            Ins::Mark(_) => self.mark,
+            Ins::Spl(_) => self.spl,
+            Ins::FpMisc(_) => self.fp_misc,
+            Ins::FpSingleOperand(ins) => {
+                let base = if ins.is_float() {
+                    self.fp_move + fp_double
+                } else {
+                    self.fp_misc
+                };
+                base + self.src_time(&ins.operand)
+            }
+            Ins::Fp(ins) => {
+                let base = match ins.op {
+                    FpOpcode::Addf | FpOpcode::Subf | FpOpcode::Cmpf => self.fp_add,
+                    FpOpcode::Mulf | FpOpcode::Modf => self.fp_mul,
+                    FpOpcode::Divf => self.fp_div,
+                    _ => self.fp_move,
+                };
+                base + fp_double + self.src_time(&ins.operand)
+            }
+            Ins::Fis(ins) => self.fis[ins.op as usize - FisOpcode::Fadd as usize],
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use common::asm::{Ins, NUM_REGS};
+
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+// Layout of the instruction trace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    // The instruction with the registers and PSW after it, and the effective
+    // addresses of its operands.
+    #[default]
+    Native,
+    // As SIMH's "show history" prints "set cpu history" records, so traces can
+    // be diffed against SIMH's: the registers and PSW are from before the
+    // instruction, and SRC and DST are the operands' effective addresses.
+    Simh,
+}
+
+impl fmt::Display for TraceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceFormat::Native => write!(f, "native"),
+            TraceFormat::Simh => write!(f, "simh"),
+        }
+    }
+}
+
+impl FromStr for TraceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(TraceFormat::Native),
+            "simh" => Ok(TraceFormat::Simh),
+            _ => Err(format!(
+                "invalid trace format {s:?}, expected native or simh"
+            )),
+        }
+    }
+}
+
+// One executed instruction.
+#[derive(Debug, Clone)]
+pub struct TraceRecord {
+    pub pc: u16,
+    pub words: Vec<u16>,
+    pub ins: Ins,
+    pub regs_before: [u16; NUM_REGS],
+    pub ps_before: u16,
+    pub regs: [u16; NUM_REGS],
+    pub ps: u16,
+    // The effective address of each operand, in the order they were
+    // resolved, or None for a register.
+    pub eas: Vec<Option<u16>>,
+}
+
+impl TraceRecord {
+    // With one operand, it's the destination.
+    fn src_dst(&self) -> (Option<u16>, Option<u16>) {
+        match self.eas[..] {
+            [src, dst, ..] => (src, dst),
+            [dst] => (None, dst),
+            [] => (None, None),
+        }
+    }
+
+    fn disassembly(&self) -> String {
+        self.ins.display_with_pc(self.pc).to_string()
+    }
+
+    // SIMH's disassembly is upper case, with no space after commas, and
+    // numbers are octal without a prefix.
+    fn simh_disassembly(&self) -> String {
+        let dis = self.disassembly().replace("0o", "").to_uppercase();
+        let mut parts = dis.split_whitespace();
+        let op = parts.next().unwrap_or_default();
+        let operands: Vec<&str> = parts.collect();
+        if operands.is_empty() {
+            op.to_string()
+        } else {
+            format!("{op} {}", operands.join(""))
+        }
+    }
+}
+
+const REG_NAMES: [&str; NUM_REGS] = ["R0", "R1", "R2", "R3", "R4", "R5", "SP", "PC"];
+
+// Writes a record per instruction. Errors writing the trace are returned
+// from record(), but the emulator just logs them.
+pub struct Tracer {
+    out: Box<dyn Write + Send>,
+    format: TraceFormat,
+    header_written: bool,
+}
+
+impl Tracer {
+    pub fn new(out: impl Write + Send + 'static, format: TraceFormat) -> Self {
+        Tracer {
+            out: Box::new(out),
+            format,
+            header_written: false,
+        }
+    }
+
+    pub fn record(&mut self, rec: &TraceRecord) -> io::Result<()> {
+        match self.format {
+            TraceFormat::Native => self.write_native(rec),
+            TraceFormat::Simh => self.write_simh(rec),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    // 001010: 010021               mov r0, (r1)+            R0=000005 ... PS=000000 EA=002000
+    fn write_native(&mut self, rec: &TraceRecord) -> io::Result<()> {
+        let words: Vec<String> = rec.words.iter().map(|w| format!("{w:06o}")).collect();
+        let dis = rec
+            .disassembly()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(
+            self.out,
+            "{:06o}: {:<20} {dis:<24}",
+            rec.pc,
+            words.join(" ")
+        )?;
+        for (name, val) in REG_NAMES.iter().zip(rec.regs) {
+            write!(self.out, " {name}={val:06o}")?;
+        }
+        write!(self.out, " PS={:06o}", rec.ps)?;
+        for ea in rec.eas.iter().flatten() {
+            write!(self.out, " EA={ea:06o}")?;
+        }
+        writeln!(self.out)
+    }
+
+    fn write_simh(&mut self, rec: &TraceRecord) -> io::Result<()> {
+        if !self.header_written {
+            writeln!(
+                self.out,
+                "PC     PS     R0     R1     R2     R3     R4     R5     SP     SRC    DST    IR\n"
+            )?;
+            self.header_written = true;
+        }
+        write!(self.out, "{:06o} {:06o}", rec.pc, rec.ps_before)?;
+        for val in &rec.regs_before[..NUM_REGS - 1] {
+            write!(self.out, " {val:06o}")?;
+        }
+        let (src, dst) = rec.src_dst();
+        for ea in [src, dst] {
+            match ea {
+                Some(ea) => write!(self.out, " {ea:06o}")?,
+                None => write!(self.out, "       ")?,
+            }
+        }
+        writeln!(self.out, " {:06o} {}", rec.words[0], rec.simh_disassembly())
+    }
+}
@@ -1,5 +1,7 @@
 ; timer_ticks.s
-; Prints digits 0 - 9 (one every 2^8 ticks), followed by a newline, then halts.
+; Prints digits 1 - 9 (one every 10 clock ticks), followed by a newline, then
+; halts. With emu_cli --speed model, the clock ticks at 60 Hz, so that's one
+; every 1/6 s.
 
     STACK_TOP = 150000 
 
@@ -10,6 +12,8 @@
     TPB = TPS + 2
     TPS_READY_MASK = 177
 
+    TICKS = 10.             ; Clock ticks per digit.
+
     . = 100
     .word clock, 300
 
@@ -31,7 +35,7 @@ loop:
 
 ;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;
 ; fn clock()
-; Handles clock interrupt. Every 2^8 ticks, prints count and increments it.
+; Handles clock interrupt. Every TICKS ticks, increments count and prints it.
 ; After 9, prints \n and halts.
 ;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;;
 clock:
@@ -44,6 +48,11 @@ clock:
 
     mov LKS, r0 ; clear clock bit
 
+    ; Only count every TICKS ticks.
+    dec ticks
+    bne done
+    mov #TICKS, ticks
+
     ; Increment counter and print it.
     inc count
     mov count, r0
@@ -73,3 +82,7 @@ done:
 count:
     .word 0
 
+    ; Ticks left until the next count.
+ticks:
+    .word TICKS
+
@@ -1,7 +1,7 @@
 use as_lib::assemble;
-use emu_lib::Emulator;
 use emu_lib::io::clock::Clock;
 use emu_lib::io::teletype::Teletype;
+use emu_lib::{CpuModel, Emulator};
 
 use clap::Parser;
 
@@ -11,6 +11,10 @@ use clap::Parser;
 struct Args {
     /// Input assembly file
     input: String,
+
+    /// CPU model: generic, 11/20, 11/40, 11/45 or 11/70
+    #[arg(long, default_value_t)]
+    cpu: CpuModel,
 }
 
 fn main() {
@@ -20,8 +24,8 @@ fn main() {
     let input = std::fs::read_to_string(opt.input).unwrap();
     let aout = assemble(input.as_str());
 
-    let mut emu = Emulator::new();
-    emu.set_mmio_handler(Teletype::default());
+    let mut emu = Emulator::with_model(opt.cpu);
+    emu.set_mmio_handler(Teletype::new_to_stdout(emu.control()));
     emu.set_mmio_handler(Clock::default());
 
     emu.load_aout(&aout);
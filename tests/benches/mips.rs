@@ -0,0 +1,71 @@
+// Reports emulated instructions per second on compute heavy code, with the
+// decoded instruction cache off and on. Run with `cargo bench -p tests`.
+
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::{Emulator, StopReason};
+
+use std::time::Instant;
+
+// fib(20), recursively, as in examples/fib.s, a number of times.
+const PROG: &str = r#"
+    . = 1000
+_start:
+    mov     #150000, sp
+    mov     #10., r3
+1:
+    mov     #20., r0
+    jsr     pc, fib
+    dec     r3
+    bne     1b
+    halt
+
+fib:
+    cmp     #0, r0
+    beq     1f
+    cmp     #1, r0
+    beq     1f
+    mov     r1, -(sp)
+    mov     r2, -(sp)
+    dec     r0
+    mov     r0, r1
+    jsr     pc, fib
+    mov     r0, r2
+    mov     r1, r0
+    dec     r0
+    jsr     pc, fib
+    add     r2, r0
+    mov     (sp)+, r2
+    mov     (sp)+, r1
+1:
+    rts     pc
+"#;
+
+fn mips(decode_cache: bool) -> f64 {
+    let prog = assemble_raw(PROG);
+    let mut emu = Emulator::new();
+    emu.set_decode_cache(decode_cache);
+    emu.load_image(&prog.text, 0);
+    emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
+
+    let start = Instant::now();
+    let mut num_ins = 0u64;
+    while emu.run_ins().unwrap() != StopReason::Halted {
+        num_ins += 1;
+    }
+    let secs = start.elapsed().as_secs_f64();
+    assert_eq!(emu.reg_read_word(Reg::R0), 6765);
+    num_ins as f64 / secs / 1e6
+}
+
+fn main() {
+    // Once to warm up.
+    mips(true);
+    for decode_cache in [false, true] {
+        println!(
+            "decode cache {}: {:.1} MIPS",
+            if decode_cache { "on" } else { "off" },
+            mips(decode_cache)
+        );
+    }
+}
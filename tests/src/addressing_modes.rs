@@ -343,31 +343,30 @@ fn indirect_write() {
 }
 
 #[test]
-#[should_panic]
 fn unaligned() {
-    let prog = assemble_raw(
+    for asm in [
         r#"
         mov #101, r0
         mov @r0, r1
         halt
     "#,
-    );
-    let mut emu = Emulator::new();
-    emu.load_image(&prog.text, DATA_START);
-    emu.mem_write_word(0o100, 0o321);
-    emu.run_at(DATA_START);
-
-    let prog = assemble_raw(
         r#"
         mov #101, r0
         mov #20, @r0
         halt
     "#,
-    );
-    let mut emu = Emulator::new();
-    emu.load_image(&prog.text, DATA_START);
-    emu.mem_write_word(0o100, 0o321);
-    emu.run_at(DATA_START);
+    ] {
+        let prog = assemble_raw(asm);
+        let mut emu = Emulator::new();
+        emu.load_image(&prog.text, DATA_START);
+        emu.mem_write_word(0o100, 0o321);
+        emu.mem_write_word(0o4, 0o1000); // Bus error handler is a halt
+        emu.reg_write_word(Reg::SP, 0o150000);
+        emu.run_at(DATA_START);
+        assert_eq!(emu.reg_read_word(Reg::PC), 0o1002);
+        assert_eq!(emu.get_state().get_cpu_error(), 0o100); // Odd address
+        assert_eq!(emu.mem_read_word(0o100), 0o321);
+    }
 }
 
 #[test]
@@ -1220,7 +1219,7 @@ fn relative_tmp_label_read() {
     emu.load_image(&prog.text, 0);
     emu.run_at(2);
     assert_eq!(emu.reg_read_word(Reg::R0), 0o012);
-    assert_eq!(emu.reg_read_word(Reg::PC), 0 + prog.text.len().to_u16p());
+    assert_eq!(emu.reg_read_word(Reg::PC), prog.text.len().to_u16p());
     /*
     0: .word 012
     2: mov
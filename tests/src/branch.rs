@@ -200,5 +200,24 @@ fn far_br() {
             halt
     "#;
 
-    assemble_raw(&asm);
+    assemble_raw(asm);
+}
+
+#[test]
+fn sob() {
+    let prog = assemble_raw(
+        r#"
+        mov #5, r1
+        clr r0
+    loop:
+        add #2, r0
+        sob r1, loop
+        halt
+    "#,
+    );
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, DATA_START);
+    emu.run_at(DATA_START);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o12);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0);
 }
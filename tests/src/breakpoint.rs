@@ -0,0 +1,251 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::breakpoint::{WatchHit, WatchTarget};
+use emu_lib::io::teletype::{PipeTty, Teletype};
+use emu_lib::{Breakpoint, Condition, Emulator, StopReason, WatchAccess};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn load(asm: &str) -> (Emulator, HashMap<String, u16>) {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    let symbols: HashMap<_, _> = prog
+        .symbols
+        .iter()
+        .map(|(name, sym)| (name.clone(), sym.val))
+        .collect();
+    emu.reg_write_word(Reg::PC, symbols["_start"]);
+    (emu, symbols)
+}
+
+fn cond(s: &str) -> Condition {
+    s.parse().unwrap()
+}
+
+const COUNT: &str = r#"
+    . = 1000
+_start:
+    mov #5, r1
+loop:
+    inc r0
+    mov r0, val
+    sob r1, loop
+    halt
+val: .word 0
+"#;
+
+#[test]
+fn exec() {
+    let (mut emu, syms) = load(COUNT);
+    let id = emu.add_breakpoint(Breakpoint::exec(syms["loop"]));
+    for count in 0..5 {
+        let reason = emu.run().unwrap();
+        assert_eq!(
+            reason,
+            StopReason::Breakpoint {
+                id,
+                pc: syms["loop"]
+            }
+        );
+        assert_eq!(emu.reg_read_word(Reg::R0), count);
+    }
+
+    // Stepping from a breakpoint runs its instruction.
+    assert_eq!(emu.run_ins().unwrap(), StopReason::Step);
+    assert_eq!(emu.reg_read_word(Reg::R0), 5);
+
+    assert!(emu.remove_breakpoint(id));
+    assert!(!emu.remove_breakpoint(id));
+    assert_eq!(emu.run().unwrap(), StopReason::Halted);
+}
+
+#[test]
+fn exec_condition() {
+    let (mut emu, syms) = load(COUNT);
+    let id = emu.add_breakpoint(Breakpoint::exec(syms["loop"]).when(cond("r0 = 2")));
+    assert!(matches!(
+        emu.run().unwrap(),
+        StopReason::Breakpoint { id: 0, .. }
+    ));
+    assert_eq!(emu.reg_read_word(Reg::R0), 2);
+
+    // This holds whenever r0 isn't 3.
+    emu.remove_breakpoint(id);
+    let id = emu.add_breakpoint(Breakpoint::exec(syms["loop"]).when(cond("r0 - 3")));
+    assert_eq!(
+        emu.run().unwrap(),
+        StopReason::Breakpoint {
+            id,
+            pc: syms["loop"]
+        }
+    );
+    assert_eq!(emu.reg_read_word(Reg::R0), 4);
+}
+
+#[test]
+fn watch_mem() {
+    let asm = r#"
+        . = 1000
+    _start:
+        mov #1, val
+        mov #1, val
+        movb #2, val+1
+        tst val
+        halt
+    val: .word 0
+    "#;
+    let (mut emu, syms) = load(asm);
+    let val = syms["val"];
+    let writes = emu.add_breakpoint(Breakpoint::mem(val, WatchAccess::Write));
+    let changes = emu.add_breakpoint(Breakpoint::mem(val + 1, WatchAccess::Change));
+    let reads = emu.add_breakpoint(Breakpoint::mem(val, WatchAccess::Read));
+
+    let hit = |id, pc, addr, write, old, val| {
+        StopReason::Watchpoint(WatchHit {
+            id,
+            pc,
+            target: WatchTarget::Mem(addr),
+            write,
+            old,
+            val,
+        })
+    };
+    assert_eq!(
+        emu.run().unwrap(),
+        hit(writes, 0o1000, val, true, Some(0), 1)
+    );
+    emu.remove_breakpoint(writes);
+    // Writing the same value again isn't a change.
+    assert_eq!(
+        emu.run().unwrap(),
+        hit(changes, 0o1014, val + 1, true, Some(0), 2)
+    );
+    assert_eq!(
+        emu.run().unwrap(),
+        hit(reads, 0o1022, val, false, None, 0o1001)
+    );
+    assert_eq!(emu.run().unwrap(), StopReason::Halted);
+    assert_eq!(
+        hit(writes, 0o1000, val, true, Some(0), 1).to_string(),
+        "watchpoint 0: write of 001030 from 000000 to 000001 at pc 001000"
+    );
+}
+
+#[test]
+fn watch_io_page() {
+    // TPS becomes ready a while after each character, so the loop polls it.
+    let asm = r#"
+        TPS = 177564
+        TPB = TPS + 2
+        . = 1000
+    _start:
+        mov #3, r0
+    loop:
+        tstb @#TPS
+        bpl loop
+        movb #'a, @#TPB
+        sob r0, loop
+        halt
+    "#;
+    let (mut emu, syms) = load(asm);
+    let tty = Arc::new(PipeTty::default());
+    emu.set_mmio_handler(Teletype::new(tty.clone()));
+
+    // Device registers are peeked in conditions, so polling TPS here doesn't
+    // disturb the guest's. This holds once r0 is 2 and TPS is ready.
+    let id =
+        emu.add_breakpoint(Breakpoint::exec(syms["loop"]).when(cond("r0 - 2 ! @#177564 = 200")));
+    assert_eq!(
+        emu.run().unwrap(),
+        StopReason::Breakpoint {
+            id,
+            pc: syms["loop"]
+        }
+    );
+    assert_eq!(emu.reg_read_word(Reg::R0), 2);
+    assert_eq!(tty.take_output(), b"a".as_slice());
+
+    // And for the old value of a change.
+    emu.remove_breakpoint(id);
+    let id = emu.add_breakpoint(Breakpoint::mem(0o177566, WatchAccess::Change));
+    let StopReason::Watchpoint(hit) = emu.run().unwrap() else {
+        panic!("no watchpoint");
+    };
+    assert_eq!(hit.id, id);
+    assert_eq!(hit.old, Some(0));
+    assert_eq!(hit.val, b'a' as u16);
+    assert_eq!(emu.reg_read_word(Reg::R0), 2);
+    assert_eq!(tty.take_output(), b"a".as_slice());
+
+    // A read of an I/O register, with its side effects.
+    emu.remove_breakpoint(id);
+    let id = emu.add_breakpoint(Breakpoint::mem(0o177564, WatchAccess::Read).when(cond("r0 = 1")));
+    let StopReason::Watchpoint(hit) = emu.run().unwrap() else {
+        panic!("no watchpoint");
+    };
+    assert_eq!((hit.id, hit.pc), (id, syms["loop"]));
+    emu.remove_breakpoint(id);
+    assert_eq!(emu.run().unwrap(), StopReason::Halted);
+}
+
+#[test]
+fn watch_reg() {
+    let (mut emu, syms) = load(COUNT);
+    let id = emu.add_breakpoint(Breakpoint::reg(Reg::R1).when(cond("r1 = 3")));
+    let reason = emu.run().unwrap();
+    assert_eq!(
+        reason,
+        StopReason::Watchpoint(WatchHit {
+            id,
+            pc: syms["loop"] + 6,
+            target: WatchTarget::Reg(Reg::R1),
+            write: true,
+            old: Some(4),
+            val: 3,
+        })
+    );
+    assert_eq!(
+        reason.to_string(),
+        "watchpoint 0: write of r1 from 000004 to 000003 at pc 001012"
+    );
+}
+
+#[test]
+fn conditions() {
+    let regs = [0o12, 1, 2, 3, 4, 5, 0o1000, 0o2000];
+    let mem = |addr| (addr == 0o177564).then_some(0o200);
+    let holds = |s: &str| cond(s).eval(&regs, 0o340, mem);
+
+    // Left to right, so r0 is compared with 12 & @#177564 & 200, which is 0.
+    assert!(!holds("r0 = 12 & @#177564 & 200"));
+    assert!(cond("r0 = 12 & @#177564 & 200").eval(&[0; 8], 0, mem));
+    assert!(holds("r0 - 12 = 0"));
+    assert!(holds("@#177564 & 200 = 200"));
+    assert!(!holds("@#177564 & 100"));
+    assert!(holds("ps = 340"));
+    assert!(holds("r0 = 10."));
+    assert!(holds("r1 + r2 - 3 ! sp - 1000 = 0"));
+    assert!(holds("pc ! 1 = 2001"));
+    assert!(!holds("r0 = 11"));
+    // Words that can't be read make it false.
+    assert!(!holds("@#0 = 0"));
+    assert_eq!(cond(" r0 = 1 ").to_string(), "r0 = 1");
+
+    for bad in [
+        "",
+        "r0 =",
+        "r0 r1",
+        "@1000",
+        "r9",
+        "foo",
+        ".",
+        "200000",
+        "8",
+        "r0 = 1 = 2",
+        "r0 + ",
+    ] {
+        assert!(bad.parse::<Condition>().is_err(), "{bad:?}");
+    }
+}
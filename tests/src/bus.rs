@@ -0,0 +1,184 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::io::clock::Clock;
+use emu_lib::{Emulator, EmulatorState, MMIOHandler, StopReason};
+
+// Counts its ticks, which come every period if it has one, in two registers.
+// A byte write sets the low or high byte of the scratch register.
+#[derive(Default)]
+struct Counter {
+    ticks: u16,
+    scratch: u16,
+    period_ns: Option<u64>,
+    next_ns: u64,
+}
+
+impl Counter {
+    const TICKS: u16 = 0o170000;
+    const SCRATCH: u16 = 0o170002;
+
+    fn with_period(period_ns: u64) -> Self {
+        Counter {
+            period_ns: Some(period_ns),
+            next_ns: period_ns,
+            ..Default::default()
+        }
+    }
+}
+
+impl MMIOHandler for Counter {
+    fn tick(&mut self, _emu: &mut EmulatorState) {
+        self.ticks += 1;
+        self.next_ns += self.period_ns.unwrap();
+    }
+
+    fn next_wake(&self) -> Option<u64> {
+        self.period_ns.map(|_| self.next_ns)
+    }
+
+    fn read_byte(&mut self, emu: &mut EmulatorState, addr: u16) -> Option<u8> {
+        let val = self.read_word(emu, addr & !1)?;
+        if addr & 1 == 0 {
+            Some(val as u8)
+        } else {
+            Some((val >> 8) as u8)
+        }
+    }
+
+    fn read_word(&mut self, _emu: &mut EmulatorState, addr: u16) -> Option<u16> {
+        match addr {
+            Self::TICKS => Some(self.ticks),
+            Self::SCRATCH => Some(self.scratch),
+            _ => None,
+        }
+    }
+
+    // TICKS is read only.
+    fn write_byte(&mut self, _emu: &mut EmulatorState, addr: u16, val: u8) -> Option<()> {
+        if addr & !1 != Self::SCRATCH {
+            return None;
+        }
+        let shift = (addr & 1) * 8;
+        self.scratch = (self.scratch & !(0xff << shift)) | ((val as u16) << shift);
+        Some(())
+    }
+
+    fn write_word(&mut self, _emu: &mut EmulatorState, addr: u16, val: u16) -> Option<()> {
+        (addr == Self::SCRATCH).then(|| self.scratch = val)
+    }
+
+    fn default_addrs(&self) -> &[u16] {
+        &[Self::TICKS, Self::SCRATCH]
+    }
+}
+
+#[test]
+fn dispatch() {
+    let asm = r#"
+        TICKS = 170000
+        SCRATCH = 170002
+    _start:
+        nop
+        nop
+        mov @#TICKS, r0
+        movb #12, @#SCRATCH + 1
+        movb #34, @#SCRATCH
+        mov @#SCRATCH, r1
+        movb @#SCRATCH + 1, r2
+        halt
+    "#;
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(Counter::default());
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+
+    // It never asked to be ticked.
+    assert_eq!(emu.reg_read_word(Reg::R0), 0);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0o12 << 8 | 0o34);
+    assert_eq!(emu.reg_read_word(Reg::R2), 0o12);
+}
+
+#[test]
+fn host_access() {
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(Counter::default());
+    assert_eq!(emu.mem_try_write_word(Counter::SCRATCH, 0o1234), Some(()));
+    assert_eq!(emu.mem_try_read_word(Counter::SCRATCH), Some(0o1234));
+    assert_eq!(emu.mem_try_write_word(Counter::TICKS, 1), None);
+    assert_eq!(emu.mem_try_read_word(Counter::SCRATCH + 1), None);
+    assert_eq!(emu.mem_try_read_word(Counter::SCRATCH + 2), None);
+    // It can't be read without side effects, by default.
+    assert_eq!(emu.mem_peek_byte(Counter::SCRATCH), None);
+}
+
+#[test]
+#[should_panic(expected = "Invalid MMIO register 170004")]
+fn host_access_panics() {
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(Counter::default());
+    emu.mem_read_word(Counter::SCRATCH + 2);
+}
+
+#[test]
+fn scheduled() {
+    let asm = r#"
+        TICKS = 170000
+    _start:
+        mov #10000., r1
+    1:
+        sob r1, 1b
+        mov @#TICKS, r0
+        halt
+    "#;
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(Counter::with_period(100_000));
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+
+    // Once per period, give or take the halt.
+    let periods = emu.get_state().get_time_ns() / 100_000;
+    let ticks = emu.reg_read_word(Reg::R0) as u64;
+    assert!(ticks > 0 && periods - ticks <= 1, "{ticks} of {periods}");
+}
+
+#[test]
+fn wait_skips_ahead() {
+    let asm = r#"
+        LKS = 177546
+        INT_ENB = 100
+
+        . = 100
+        .word clock, 300
+
+        . = 1000
+    _start:
+        mov #1000, sp
+        mov #INT_ENB, @#LKS
+        wait
+    clock:
+        halt
+    "#;
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(Clock::with_hz(1));
+    emu.load_image(&prog.text, 0);
+    emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
+
+    // A second of waiting for the clock is a single step.
+    let mut steps = 0;
+    while emu.run_ins().unwrap() != StopReason::Halted {
+        steps += 1;
+    }
+    assert!(steps < 10, "{steps}");
+    assert!(emu.get_state().get_time_ns() >= 1_000_000_000);
+}
+
+#[test]
+#[should_panic(expected = "Duplicate MMIOHandler for 170002")]
+fn duplicate() {
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(Counter::default());
+    emu.set_mmio_handler_for(Counter::default(), [Counter::SCRATCH]);
+}
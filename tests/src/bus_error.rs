@@ -0,0 +1,172 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::Emulator;
+use emu_lib::io::teletype::{PipeTty, Teletype};
+
+use std::sync::Arc;
+
+fn run(asm: &str) -> Emulator {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    emu
+}
+
+const ODD_ADDRESS: u16 = 0o100;
+const UNIBUS_TIMEOUT: u16 = 0o20;
+
+#[test]
+fn odd_read() {
+    let asm = r#"
+        CPU_ERR = 177766
+
+        . = 4
+        .word bus_err, 340
+
+        . = 400
+    _start:
+        mov #150000, sp
+        mov #1001, r0
+    bad:
+        mov (r0), r1
+        mov #1, r5
+        halt
+
+    bus_err:
+        mov @#CPU_ERR, r2
+        mov (sp), r3            ; Saved PC
+        mov #bad, r4
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R2), ODD_ADDRESS);
+    assert_eq!(emu.reg_read_word(Reg::R3), emu.reg_read_word(Reg::R4) + 2);
+    assert_eq!(emu.reg_read_word(Reg::R5), 0);
+}
+
+#[test]
+fn odd_write() {
+    let asm = r#"
+        CPU_ERR = 177766
+
+        . = 4
+        .word bus_err, 340
+
+        . = 400
+    _start:
+        mov #150000, sp
+        mov #1001, r0
+        mov #1, (r0)
+        mov #1, r5
+        halt
+
+    bus_err:
+        mov @#CPU_ERR, r2
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R2), ODD_ADDRESS);
+    assert_eq!(emu.reg_read_word(Reg::R5), 0);
+}
+
+#[test]
+fn odd_pc() {
+    let asm = r#"
+        CPU_ERR = 177766
+
+        . = 4
+        .word bus_err, 340
+
+        . = 400
+    _start:
+        mov #150000, sp
+        mov #1001, r0
+        jmp (r0)
+
+    bus_err:
+        mov @#CPU_ERR, r2
+        mov (sp), r3            ; Saved PC
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R2), ODD_ADDRESS);
+    assert_eq!(emu.reg_read_word(Reg::R3), 0o1001);
+}
+
+#[test]
+fn probe_device() {
+    let asm = r#"
+        CPU_ERR = 177766
+        MISSING = 164000
+        TPS = 177564
+
+        . = 4
+        .word bus_err, 340
+
+        . = 400
+    _start:
+        mov #150000, sp
+        clr r0
+        tst @#MISSING
+        tst @#TPS
+        mov @#CPU_ERR, r2
+        clr @#CPU_ERR
+        mov @#CPU_ERR, r3
+        halt
+
+    bus_err:
+        inc r0
+        rti
+    "#;
+
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(Teletype::new(Arc::new(PipeTty::default())));
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    assert_eq!(emu.reg_read_word(Reg::R0), 1);
+    assert_eq!(emu.reg_read_word(Reg::R2), UNIBUS_TIMEOUT);
+    assert_eq!(emu.reg_read_word(Reg::R3), 0);
+}
+
+#[test]
+fn double_fault() {
+    let asm = r#"
+        CPU_ERR = 177766
+
+        . = 4
+        .word bus_err, 340
+
+        . = 34
+        .word trap_handler, 0
+
+        . = 400
+    _start:
+        mov #150001, sp         ; Bad stack
+        mov #7, r0
+    bad:
+        trap
+        halt
+
+    trap_handler:
+        clr r0
+        halt
+
+    bus_err:
+        mov @#CPU_ERR, r2
+        mov sp, r3
+        mov @#0, r4             ; Saved PC
+        mov #bad, r5
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o7);
+    assert_eq!(emu.reg_read_word(Reg::R2), ODD_ADDRESS);
+    assert_eq!(emu.reg_read_word(Reg::R3), 0);
+    assert_eq!(emu.reg_read_word(Reg::R4), emu.reg_read_word(Reg::R5) + 2);
+}
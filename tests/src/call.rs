@@ -5,7 +5,7 @@ use emu_lib::Emulator;
 
 // asm must set r0 to 1 if the jsr wasn't successful, 2 if it was
 fn run(asm: &str) {
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, DATA_START);
     emu.reg_write_word(Reg::SP, 0o150000);
@@ -139,3 +139,27 @@ fn call_reg() {
         jsr r1
     "#);
 }
+
+#[test]
+fn mark() {
+    run(r#"
+        mov r5, -(sp)
+        mov #1, -(sp)           ; Two parameters
+        mov #1, -(sp)
+        mov #6402, -(sp)        ; mark 2
+        mov sp, r5
+        jsr pc, subr
+        cmp r5, #7
+        bne bad
+        mov #2, r0
+        halt
+
+    subr:
+        mov #7, 6(r5)           ; Replace the saved r5
+        rts r5
+
+    bad:
+        mov #1, r0
+        halt
+    "#);
+}
@@ -85,3 +85,22 @@ fn sen() {
     run("sen", Z | C | V, N | V | Z | C);
     run("sen", N | V | Z | C, N | V | Z | C);
 }
+
+#[test]
+fn ccc() {
+    run("ccc", 0, 0);
+    run("ccc", N | V | Z | C, 0);
+}
+
+#[test]
+fn scc() {
+    run("scc", 0, N | V | Z | C);
+    run("scc", N | V | Z | C, N | V | Z | C);
+}
+
+#[test]
+fn combined() {
+    run("clc!clv", N | V | Z | C, N | Z);
+    run("sen!sez", C, N | Z | C);
+    run("sec!sev!sez", N, N | V | Z | C);
+}
@@ -0,0 +1,87 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::{Emulator, StopReason};
+
+use std::thread;
+use std::time::Duration;
+
+// Counts in r0 forever.
+const FOREVER: &str = r#"
+_start:
+    inc r0
+    br _start
+"#;
+
+fn start(asm: &str) -> Emulator {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
+    emu
+}
+
+fn stop_soon(emu: &Emulator) -> thread::JoinHandle<()> {
+    let control = emu.control();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        control.stop();
+    })
+}
+
+#[test]
+fn stop_and_restart() {
+    let mut emu = start(FOREVER);
+    let mut other = start(FOREVER);
+
+    let stopper = stop_soon(&emu);
+    assert_eq!(emu.run().unwrap(), StopReason::HostStop);
+    stopper.join().unwrap();
+    let count = emu.reg_read_word(Reg::R0);
+    assert_ne!(count, 0);
+
+    // Stopping one emulator leaves the other running, and the stopped one
+    // can run again.
+    let stopper = stop_soon(&other);
+    assert_eq!(other.run().unwrap(), StopReason::HostStop);
+    stopper.join().unwrap();
+    assert_ne!(other.reg_read_word(Reg::R0), 0);
+
+    let stopper = stop_soon(&emu);
+    assert_eq!(emu.run().unwrap(), StopReason::HostStop);
+    stopper.join().unwrap();
+    assert_ne!(emu.reg_read_word(Reg::R0), count);
+}
+
+#[test]
+fn interrupt() {
+    let mut emu = start(FOREVER);
+    emu.control().interrupt();
+    assert_eq!(emu.run().unwrap(), StopReason::HostInterrupt);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0);
+    emu.run_ins().unwrap();
+    assert_eq!(emu.reg_read_word(Reg::R0), 1);
+}
+
+#[test]
+fn pause_and_resume() {
+    let mut emu = start("_start: halt");
+    let control = emu.control();
+    control.pause();
+    assert!(control.is_paused());
+    let runner = thread::spawn(move || emu.run());
+
+    thread::sleep(Duration::from_millis(20));
+    assert!(!runner.is_finished());
+    control.resume();
+    assert_eq!(runner.join().unwrap().unwrap(), StopReason::Halted);
+    assert!(!control.is_paused());
+
+    // Stopping ends a pause.
+    let mut emu = start(FOREVER);
+    let control = emu.control();
+    control.pause();
+    let runner = thread::spawn(move || emu.run());
+    thread::sleep(Duration::from_millis(20));
+    control.stop();
+    assert_eq!(runner.join().unwrap().unwrap(), StopReason::HostStop);
+}
@@ -0,0 +1,84 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::{Emulator, StopReason};
+
+fn run(asm: &str, decode_cache: bool) -> Emulator {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.set_decode_cache(decode_cache);
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    emu
+}
+
+#[test]
+fn self_modifying() {
+    // The second time round, the inc is a dec and the immediate is 7.
+    let asm = r#"
+    _start:
+        clr r0
+        clr r2
+    loop:
+        inc r0
+    imm:
+        mov #1, r1
+        add r1, r2
+        mov #5300, loop     ; dec r0
+        mov #7, imm + 2
+        cmp #10, r2
+        bhi loop
+        halt
+    "#;
+    for decode_cache in [false, true] {
+        let emu = run(asm, decode_cache);
+        assert_eq!(emu.reg_read_word(Reg::R0), 0);
+        assert_eq!(emu.reg_read_word(Reg::R1), 7);
+        assert_eq!(emu.reg_read_word(Reg::R2), 0o10);
+    }
+}
+
+#[test]
+fn byte_write() {
+    // Changes the register of the mov, with a byte write.
+    let asm = r#"
+    _start:
+        clr r0
+        clr r1
+        mov #2, r3
+    loop:
+        inc r0
+        movb #5201, loop    ; inc r1
+        sob r3, loop
+        halt
+    "#;
+    let emu = run(asm, true);
+    assert_eq!(emu.reg_read_word(Reg::R0), 1);
+    assert_eq!(emu.reg_read_word(Reg::R1), 1);
+}
+
+#[test]
+fn step_back() {
+    // Stepping back puts the original instruction back.
+    let asm = r#"
+    _start:
+        clr r0
+    target:
+        inc r0
+        mov #5300, target   ; dec r0
+        br target
+    "#;
+    let prog = assemble_raw(asm);
+    let target = prog.symbols.get("target").unwrap().val;
+    let mut emu = Emulator::new();
+    emu.set_history_limit(10);
+    emu.load_image(&prog.text, 0);
+    emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
+    for _ in 0..5 {
+        assert_eq!(emu.run_ins().unwrap(), StopReason::Step);
+    }
+    assert_eq!(emu.reg_read_word(Reg::R0), 0);
+    assert!(emu.run_back_until(target));
+    assert!(emu.run_back_until(target));
+    assert_eq!(emu.run_ins().unwrap(), StopReason::Step);
+    assert_eq!(emu.reg_read_word(Reg::R0), 1);
+}
@@ -8,12 +8,10 @@ use emu_lib::Emulator;
 #[test]
 fn mul_full() {
     fn run(r0_init: u16, r2_init: u16, result_exp: u32, flags_exp: u16) {
-        let asm = format!(
-            r#"
+        let asm = r#"
             mul r2, r0
             halt
-        "#
-        );
+        "#.to_string();
         let prog = assemble_raw(&asm);
         let mut emu = Emulator::new();
         emu.load_image(&prog.text, DATA_START);
@@ -62,7 +60,7 @@ fn mul_lower() {
             mul r4, r3
             halt
         "#;
-        let prog = assemble_raw(&asm);
+        let prog = assemble_raw(asm);
         let mut emu = Emulator::new();
         emu.load_image(&prog.text, DATA_START);
         emu.reg_write_word(Reg::R3, r3_init);
@@ -103,12 +101,10 @@ fn div_odd() {
 #[test]
 fn div() {
     fn run(dividend: u32, divisor: u16, quot_exp: u16, rem_exp: u16, flags_exp: u16) {
-        let asm = format!(
-            r#"
+        let asm = r#"
             div r2, r0
             halt
-        "#
-        );
+        "#.to_string();
         let prog = assemble_raw(&asm);
         let mut emu = Emulator::new();
         emu.load_image(&prog.text, DATA_START);
@@ -146,12 +142,10 @@ fn div() {
 #[test]
 fn ash() {
     fn run(val: i16, shift: i16, val_exp: i16, flags_exp: u16) {
-        let asm = format!(
-            r#"
+        let asm = r#"
             ash r0, r1
             halt
-        "#
-        );
+        "#.to_string();
         let prog = assemble_raw(&asm);
         let mut emu = Emulator::new();
         emu.load_image(&prog.text, DATA_START);
@@ -187,12 +181,10 @@ fn ash() {
 #[test]
 fn ashc() {
     fn run(val: i32, shift: i16, val_exp: i32, flags_exp: u16) {
-        let asm = format!(
-            r#"
+        let asm = r#"
             ashc r0, r2
             halt
-        "#
-        );
+        "#.to_string();
         let prog = assemble_raw(&asm);
         let mut emu = Emulator::new();
         emu.load_image(&prog.text, DATA_START);
@@ -237,6 +229,31 @@ fn ashc() {
     run(i32::MIN, -0o1, i32::MIN >> 1, N);
 }
 
+#[test]
+fn odd_register() {
+    // ASHC on an odd register shifts it as both halves, so shifting right
+    // rotates it.
+    let prog = assemble_raw("ashc r0, r1\nhalt");
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, DATA_START);
+    emu.reg_write_word(Reg::R0, -3i16 as u16);
+    emu.reg_write_word(Reg::R1, 0o1234);
+    emu.run_at(DATA_START);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0o100123);
+    check_flags(&emu, C);
+
+    // DIV on an odd register divides it as both halves, and keeps the
+    // remainder. The assembler won't take it, so it's div r2, r1 by hand.
+    let prog = assemble_raw(".word 071102\nhalt");
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, DATA_START);
+    emu.reg_write_word(Reg::R1, 0o1);
+    emu.reg_write_word(Reg::R2, 0o40000);
+    emu.run_at(DATA_START);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0o1);
+    check_flags(&emu, 0);
+}
+
 #[test]
 fn xor() {
     fn run(r0_init: u16, r1_init: u16, r1_exp: u16, flags_exp: u16) {
@@ -244,7 +261,7 @@ fn xor() {
             xor r1, r0
             halt
         "#;
-        let prog = assemble_raw(&asm);
+        let prog = assemble_raw(asm);
         let mut emu = Emulator::new();
         emu.load_image(&prog.text, DATA_START);
         emu.reg_write_word(Reg::R0, r0_init);
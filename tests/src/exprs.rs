@@ -67,8 +67,7 @@ fn literal() {
     eval_byte(r"177", 0o177);
     eval_byte(r"377", 0o377);
 
-    let asm = format!(
-        r#"
+    let asm = r#"
         SYM = -1
         ; Go through memory to avoid sign-extension for movb to reg
         movb    #SYM, data
@@ -77,8 +76,7 @@ fn literal() {
         halt
     data:
         .word 0
-    "#
-    );
+    "#.to_string();
     let prog = assemble_raw(&asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, DATA_START);
@@ -163,7 +161,7 @@ fn compound() {
 fn malformed() {
     use std::panic::catch_unwind;
     fn fail(asm: &str) {
-        if let Ok(_) = catch_unwind(|| assemble_raw(asm)) {
+        if catch_unwind(|| assemble_raw(asm)).is_ok() {
             panic!("Failure: was supposed to panic");
         }
     }
@@ -192,7 +190,7 @@ fn array_len() {
         mov #len, r0
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, 0);
     emu.run_at(prog.symbols.get("_start").unwrap().val);
@@ -211,7 +209,7 @@ fn array_len() {
         mov len, r0
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, DATA_START);
     emu.run_at(DATA_START);
@@ -234,7 +232,7 @@ fn relocation() {
         mov #66, val 
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, DATA_START);
     emu.run_at(DATA_START);
@@ -254,10 +252,10 @@ fn relocation() {
         mov #66, val 
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, 0);
-    emu.run();
+    emu.run().unwrap();
     assert_eq!(emu.mem_read_word(0o2), 0o66);
     assert_eq!(emu.reg_read_word(Reg::PC), prog.text.len().to_u16p());
 
@@ -273,7 +271,7 @@ fn relocation() {
         mov #66, val 
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, 0);
     emu.run_at(DATA_START);
@@ -297,7 +295,7 @@ fn period_unchanged() {
         mov #66, val 
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, 0);
     emu.run_at(DATA_START);
@@ -314,7 +312,7 @@ fn decreasing() {
         . = 1
         . = 0
     "#;
-    assemble_raw(&asm);
+    assemble_raw(asm);
 }
 
 #[test]
@@ -326,7 +324,7 @@ fn reloc_label_reads() {
         mov #loc, r1
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, 0);
     emu.mem_write_word(0o100, 0o123);
@@ -342,7 +340,7 @@ fn reloc_label_reads() {
         mov #loc, r1
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, DATA_START);
     emu.mem_write_word(0o100, 0o123);
@@ -361,7 +359,7 @@ fn reloc_label_reads() {
         mov #loc, r1
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, DATA_START);
     emu.mem_write_word(0o100, 0o123);
@@ -384,7 +382,7 @@ fn update_symbol() {
         mov #sym, r1
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, DATA_START);
     emu.run_at(DATA_START);
@@ -409,7 +407,7 @@ fn forward() {
         mov elem, r0
         halt
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, DATA_START);
     emu.run_at(DATA_START);
@@ -432,7 +430,7 @@ fn double_forward() {
             .word 0, 0, 27, 0
 
     "#;
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
     let mut emu = Emulator::new();
     emu.load_image(&prog.text, DATA_START);
     emu.run_at(DATA_START);
@@ -0,0 +1,146 @@
+use as_lib::{Program, assemble_raw};
+use common::asm::Reg;
+use common::float::Float;
+use emu_lib::Emulator;
+
+fn run(asm: &str) -> (Emulator, Program) {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    (emu, prog)
+}
+
+fn read_float(emu: &Emulator, addr: u16) -> f64 {
+    let hi = emu.get_state().mem_read_word(addr as u32) as u64;
+    let lo = emu.get_state().mem_read_word(addr as u32 + 2) as u64;
+    Float::unpack((hi << 48) | (lo << 32)).to_f64()
+}
+
+const PRELUDE: &str = r#"
+    . = 244
+    .word fis_err, 340
+
+    . = 1000
+"#;
+
+#[test]
+fn arithmetic() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        mov #1000, sp
+        mov #add_b, r0
+        fadd r0
+        mov r0, r1
+        mov #sub_b, r0
+        fsub r0
+        mov r0, r2
+        mov #mul_b, r0
+        fmul r0
+        mov #div_b, r0
+        fdiv r0
+        mfps r5
+        halt
+
+    fis_err:
+        halt
+
+    add_b: .flt2 2.25
+    add_a: .flt2 1.5
+    sub_b: .flt2 2.25
+    sub_a: .flt2 1.5
+    mul_b: .flt2 -2.
+    mul_a: .flt2 1.5
+    div_b: .flt2 4.
+    div_a: .flt2 3.
+    "#
+    );
+
+    let (emu, prog) = run(&asm);
+    let addr = |label| prog.symbols.get(label).unwrap().val;
+    // The operand pointer is popped past B, to the result.
+    assert_eq!(emu.reg_read_word(Reg::R1), addr("add_a"));
+    assert_eq!(emu.reg_read_word(Reg::R2), addr("sub_a"));
+    assert_eq!(emu.reg_read_word(Reg::R0), addr("div_a"));
+    assert_eq!(read_float(&emu, addr("add_a")), 3.75);
+    assert_eq!(read_float(&emu, addr("sub_a")), -0.75);
+    assert_eq!(read_float(&emu, addr("mul_a")), -3.0);
+    assert_eq!(read_float(&emu, addr("div_a")), 0.75);
+    assert_eq!(emu.reg_read_word(Reg::R5) & 0o17, 0);
+}
+
+#[test]
+fn flags() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        mov #1000, sp
+        mov #b, r0
+        fsub r0
+        mfps r1
+        mov #neg_b, r0
+        fmul r0
+        mfps r2
+        halt
+
+    fis_err:
+        halt
+
+    b: .flt2 1.5
+    a: .flt2 1.5
+    neg_b: .flt2 -1.
+    neg_a: .flt2 2.
+    "#
+    );
+
+    let (emu, _) = run(&asm);
+    assert_eq!(emu.reg_read_word(Reg::R1) & 0o17, 0o4); // Z
+    assert_eq!(emu.reg_read_word(Reg::R2) & 0o17, 0o10); // N
+}
+
+#[test]
+fn errors() {
+    // Each error traps with the operands left alone, and the condition codes
+    // give the cause.
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        mov #1000, sp
+        mov #zero, r0
+        fdiv r0
+        mov r1, r2
+        mov #big, r0
+        fmul r0
+        mov r1, r3
+        mov #small, r0
+        fdiv r0
+        mov r1, r4
+        halt
+
+    fis_err:
+        mov 2(sp), r1
+        bic #177760, r1
+        rti
+
+    zero: .flt2 0.
+    one: .flt2 1.
+    big: .flt2 1.0e30
+    big2: .flt2 1.0e30
+    small: .flt2 1.0e30
+    small2: .flt2 1.0e-30
+    "#
+    );
+
+    let (emu, prog) = run(&asm);
+    let addr = |label| prog.symbols.get(label).unwrap().val;
+    assert_eq!(emu.reg_read_word(Reg::R2), 0o3); // V and C
+    assert_eq!(emu.reg_read_word(Reg::R3), 0o2); // V
+    assert_eq!(emu.reg_read_word(Reg::R4), 0o12); // N and V
+    assert_eq!(emu.reg_read_word(Reg::R0), addr("small"));
+    assert_eq!(read_float(&emu, addr("one")), 1.0);
+    assert_eq!(read_float(&emu, addr("big2")), 1.0e30f32 as f64);
+}
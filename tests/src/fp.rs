@@ -0,0 +1,364 @@
+use as_lib::{Program, assemble_raw};
+use common::asm::Reg;
+use common::float::{Float, Precision};
+use emu_lib::Emulator;
+use emu_lib::fpu::Fpu;
+
+fn run(asm: &str) -> (Emulator, Program) {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    (emu, prog)
+}
+
+fn read_float(emu: &Emulator, prog: &Program, label: &str, prec: Precision) -> f64 {
+    let addr = prog.symbols.get(label).unwrap().val as u32;
+    let mut raw = 0u64;
+    for i in 0..prec.words() {
+        let word = emu.get_state().mem_read_word(addr + 2 * i as u32);
+        raw |= (word as u64) << (48 - 16 * i);
+    }
+    Float::unpack(raw).to_f64()
+}
+
+const PRELUDE: &str = r#"
+    . = 244
+    .word fp_err, 340
+
+    . = 1000
+"#;
+
+#[test]
+fn single_arithmetic() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        setf
+        ldf a, ac0
+        addf b, ac0         ; 3.75
+        stf ac0, sum
+        ldf a, ac1
+        subf b, ac1         ; -0.75
+        stf ac1, diff
+        ldf a, ac2
+        mulf b, ac2         ; 3.375
+        stf ac2, prod
+        ldf a, ac3
+        divf b, ac3         ; 0.666...
+        stf ac3, quot
+        halt
+
+    fp_err:
+        halt
+
+    a: .flt2 1.5
+    b: .flt2 2.25
+    sum: .flt2 0.
+    diff: .flt2 0.
+    prod: .flt2 0.
+    quot: .flt2 0.
+    "#
+    );
+
+    let (emu, prog) = run(&asm);
+    let single = |label| read_float(&emu, &prog, label, Precision::Single);
+    assert_eq!(single("sum"), 3.75);
+    assert_eq!(single("diff"), -0.75);
+    assert_eq!(single("prod"), 3.375);
+    assert_eq!(single("quot"), (2.0f32 / 3.0) as f64);
+    // The last result was positive and non-zero.
+    assert_eq!(emu.get_state().get_fpu().get_flags(), 0);
+}
+
+#[test]
+fn double_arithmetic() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        setd
+        mov #a, r0
+        ldd (r0)+, ac0
+        divd (r0)+, ac0
+        mov #res, r1
+        std ac0, (r1)+
+        mov r0, r2
+        mov r1, r3
+        halt
+
+    fp_err:
+        halt
+
+    a: .flt4 1.
+    b: .flt4 3.
+    res: .flt4 0.
+    "#
+    );
+
+    let (emu, prog) = run(&asm);
+    let res = prog.symbols.get("res").unwrap().val;
+    // Autoincrement steps over whole doubles.
+    assert_eq!(emu.reg_read_word(Reg::R2), res);
+    assert_eq!(emu.reg_read_word(Reg::R3), res + 8);
+    // 56 bits of 1/3: 0.0101... rounded up in the last place.
+    let words: Vec<u16> = (0..4)
+        .map(|i| emu.get_state().mem_read_word(res as u32 + 2 * i))
+        .collect();
+    assert_eq!(words, [0o37652, 0o125252, 0o125252, 0o125253]);
+    assert_eq!(read_float(&emu, &prog, "res", Precision::Double), 1.0 / 3.0);
+}
+
+#[test]
+fn accumulators() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        ldf #40300, ac0     ; 1.5, as an immediate high word
+        stf ac0, ac5
+        clrf ac0
+        ldf ac5, ac1
+        negf ac1
+        absf ac5
+        stf ac1, minus
+        ldf ac5, ac2
+        stf ac2, plus
+        tstf ac0
+        stfps r0
+        halt
+
+    fp_err:
+        halt
+
+    minus: .flt2 0.
+    plus: .flt2 0.
+    "#
+    );
+
+    let (emu, prog) = run(&asm);
+    assert_eq!(read_float(&emu, &prog, "minus", Precision::Single), -1.5);
+    assert_eq!(read_float(&emu, &prog, "plus", Precision::Single), 1.5);
+    assert_eq!(emu.reg_read_word(Reg::R0), Fpu::FZ);
+}
+
+#[test]
+fn compare() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        clr r0
+        ldf a, ac0
+        cmpf b, ac0         ; b < a
+        cfcc
+        bge bad
+        cmpf a, ac0
+        cfcc
+        bne bad
+        inc r0
+    bad:
+        halt
+
+    fp_err:
+        halt
+
+    a: .flt2 2.
+    b: .flt2 -3.5
+    "#
+    );
+
+    let (emu, _) = run(&asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 1);
+}
+
+#[test]
+fn int_conversions() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        mov #-7, r0
+        ldcif r0, ac0
+        mulf half, ac0      ; -3.5
+        stcfi ac0, r1       ; Truncates toward zero
+        mfps r5
+
+        setl
+        ldcif big, ac1
+        setd
+        ldcdf half, ac2     ; Single to double
+        addd ac2, ac1
+        stcdl ac1, long
+        stcdf ac1, fhalf    ; Double to single
+        halt
+
+    fp_err:
+        halt
+
+    half: .flt2 0.5
+    big: .word 1, 2         ; 65538
+    long: .word 0, 0
+    fhalf: .flt2 0.
+    "#
+    );
+
+    let (emu, prog) = run(&asm);
+    assert_eq!(emu.reg_read_word(Reg::R1), (-3i16) as u16);
+    assert_eq!(emu.reg_read_word(Reg::R5) & 0o17, 0o10); // CPU N
+    let long = prog.symbols.get("long").unwrap().val as u32;
+    assert_eq!(emu.get_state().mem_read_word(long), 1);
+    assert_eq!(emu.get_state().mem_read_word(long + 2), 2);
+    assert_eq!(read_float(&emu, &prog, "fhalf", Precision::Single), 65538.5);
+}
+
+#[test]
+fn modf() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        ldf a, ac0
+        modf b, ac0         ; 7.5
+        stf ac0, frac
+        stf ac1, int
+        halt
+
+    fp_err:
+        halt
+
+    a: .flt2 2.5
+    b: .flt2 3.
+    frac: .flt2 0.
+    int: .flt2 0.
+    "#
+    );
+
+    let (emu, prog) = run(&asm);
+    assert_eq!(read_float(&emu, &prog, "frac", Precision::Single), 0.5);
+    assert_eq!(read_float(&emu, &prog, "int", Precision::Single), 7.0);
+}
+
+#[test]
+fn exponent() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        ldf a, ac0          ; 0.75 * 2^4
+        stexp ac0, r0
+        ldexp #-1, ac0      ; 0.75 * 2^-1
+        stf ac0, res
+        halt
+
+    fp_err:
+        halt
+
+    a: .flt2 12.
+    res: .flt2 0.
+    "#
+    );
+
+    let (emu, prog) = run(&asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 4);
+    assert_eq!(read_float(&emu, &prog, "res", Precision::Single), 0.375);
+}
+
+#[test]
+fn divide_by_zero() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        mov #1000, sp
+        ldf a, ac0
+    bad:
+        divf zero, ac0
+        halt
+
+    fp_err:
+        stst r1
+        stst errs
+        stfps r3
+        mov (sp), r4
+        halt
+
+    a: .flt2 1.
+    zero: .flt2 0.
+    errs: .word 0, 0
+    "#
+    );
+
+    let (emu, prog) = run(&asm);
+    let bad = prog.symbols.get("bad").unwrap().val;
+    let errs = prog.symbols.get("errs").unwrap().val as u32;
+    assert_eq!(emu.reg_read_word(Reg::R1), 4);
+    assert_eq!(emu.get_state().mem_read_word(errs), 4);
+    assert_eq!(emu.get_state().mem_read_word(errs + 2), bad);
+    assert_ne!(emu.reg_read_word(Reg::R3) & Fpu::FER, 0);
+    assert_eq!(emu.reg_read_word(Reg::R4), bad + 4);
+}
+
+#[test]
+fn overflow() {
+    // Without FIV, the result is zero.
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        mov #1000, sp
+        clr r0
+        ldf big, ac0
+        mulf big, ac0
+        stfps r1
+        stf ac0, res
+        ldfps #1000         ; FIV
+        ldf big, ac0
+        mulf big, ac0
+        halt
+
+    fp_err:
+        stst r2
+        inc r0
+        halt
+
+    big: .flt2 1.0e30
+    res: .flt2 1.
+    "#
+    );
+
+    let (emu, prog) = run(&asm);
+    assert_eq!(emu.reg_read_word(Reg::R1), Fpu::FV | Fpu::FZ);
+    assert_eq!(read_float(&emu, &prog, "res", Precision::Single), 0.0);
+    assert_eq!(emu.reg_read_word(Reg::R0), 1);
+    assert_eq!(emu.reg_read_word(Reg::R2), 8);
+}
+
+#[test]
+fn interrupts_disabled() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+    _start:
+        mov #1000, sp
+        clr r0
+        ldfps #40000        ; FID
+        ldf a, ac0
+        divf zero, ac0
+        stst r1
+        halt
+
+    fp_err:
+        inc r0
+        halt
+
+    a: .flt2 1.
+    zero: .flt2 0.
+    "#
+    );
+
+    let (emu, _) = run(&asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0);
+    assert_eq!(emu.reg_read_word(Reg::R1), 4);
+}
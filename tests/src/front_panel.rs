@@ -0,0 +1,70 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::io::front_panel::{FrontPanel, lights};
+use emu_lib::{Emulator, StopReason};
+
+// Decides what to show from switch 0, as bootstraps choose a device.
+const PROG: &str = r#"
+    SR = 177570
+    DR = SR
+
+    . = 1000
+_start:
+    mov @#SR, r0
+    bit #1, r0
+    beq even
+    mov #1234, @#DR
+    halt
+even:
+    com r0
+    mov r0, @#DR
+    movb #77, @#DR+1
+    movb @#SR+1, r1
+    halt
+"#;
+
+#[test]
+fn switches_and_display() {
+    let prog = assemble_raw(PROG);
+    let panel = FrontPanel::new(0o1);
+    let handle = panel.get_handle();
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(panel);
+    emu.load_image(&prog.text, 0);
+
+    assert_eq!(emu.run_at(0o1000), StopReason::Halted);
+    assert_eq!(handle.display(), 0o1234);
+
+    // The switches can be changed between runs.
+    handle.set_switches(0o100200);
+    assert_eq!(emu.run_at(0o1000), StopReason::Halted);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o077577);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0o177600);
+    assert_eq!(handle.display(), 0o037577);
+    assert_eq!(handle.switches(), 0o100200);
+}
+
+#[test]
+fn display_in_snapshot() {
+    let mut emu = Emulator::new();
+    let panel = FrontPanel::new(0o777);
+    emu.set_mmio_handler(panel);
+    emu.mem_write_word(FrontPanel::ADDR, 0o52525);
+    let mut snapshot = Vec::new();
+    emu.save_snapshot(&mut snapshot).unwrap();
+
+    let mut resumed = Emulator::new();
+    let panel = FrontPanel::new(0o123);
+    let handle = panel.get_handle();
+    resumed.set_mmio_handler(panel);
+    resumed.load_snapshot(&mut snapshot.as_slice()).unwrap();
+    assert_eq!(handle.display(), 0o52525);
+    assert_eq!(handle.switches(), 0o123);
+}
+
+#[test]
+fn lights_in_octal_groups() {
+    assert_eq!(lights(0), "○ ○○○ ○○○ ○○○ ○○○ ○○○");
+    assert_eq!(lights(0o100007), "● ○○○ ○○○ ○○○ ○○○ ●●●");
+    assert_eq!(lights(0o52525), "○ ●○● ○●○ ●○● ○●○ ●○●");
+}
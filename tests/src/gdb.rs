@@ -0,0 +1,165 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::{Emulator, ProcessorMode, gdb};
+
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+struct Client {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Client {
+    fn byte(&mut self) -> u8 {
+        let mut buf = [0];
+        self.reader.read_exact(&mut buf).unwrap();
+        buf[0]
+    }
+
+    fn write(&mut self, packet: &str) {
+        let sum = packet.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(self.stream, "${packet}#{sum:02x}").unwrap();
+        assert_eq!(self.byte(), b'+');
+    }
+
+    fn reply(&mut self) -> String {
+        assert_eq!(self.byte(), b'$');
+        let mut reply = String::new();
+        loop {
+            match self.byte() {
+                b'#' => break,
+                b => reply.push(b as char),
+            }
+        }
+        self.byte();
+        self.byte();
+        self.stream.write_all(b"+").unwrap();
+        reply
+    }
+
+    fn cmd(&mut self, packet: &str) -> String {
+        self.write(packet);
+        self.reply()
+    }
+}
+
+// Serves the program in another thread, which gives the emulator back when
+// GDB is done with it.
+fn connect(asm: &str) -> (Client, JoinHandle<Emulator>, u16) {
+    let prog = assemble_raw(asm);
+    let start = prog.symbols.get("_start").unwrap().val;
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    emu.reg_write_word(Reg::PC, start);
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        gdb::serve(&mut emu, stream).unwrap();
+        emu
+    });
+    let stream = TcpStream::connect(addr).unwrap();
+    let reader = BufReader::new(stream.try_clone().unwrap());
+    (Client { stream, reader }, server, start)
+}
+
+const PROG: &str = r#"
+    . = 1000
+_start:
+    mov #3, r1
+loop:
+    inc r0
+    mov r0, val
+    sob r1, loop
+    halt
+val: .word 0
+"#;
+
+#[test]
+fn registers_and_memory() {
+    let (mut gdb, server, _) = connect(PROG);
+    assert!(gdb.cmd("qSupported:swbreak+").contains("swbreak+"));
+    assert_eq!(gdb.cmd("?"), "S05");
+    // r0 to r5, sp, pc and ps, little endian.
+    assert_eq!(gdb.cmd("g"), format!("{}0002{}", "0000".repeat(7), "0000"));
+    assert_eq!(gdb.cmd("P0=3412"), "OK");
+    assert_eq!(gdb.cmd("p0"), "3412");
+    assert_eq!(gdb.cmd("P8=e000"), "OK");
+    assert_eq!(gdb.cmd("p8"), "e000");
+    assert_eq!(gdb.cmd("p9"), "E01");
+
+    // mov #3, r1 is 012701 000003.
+    assert_eq!(gdb.cmd("m200,4"), "c1150300");
+    assert_eq!(gdb.cmd("M100,3:010203"), "OK");
+    assert_eq!(gdb.cmd("m100,3"), "010203");
+    // Nothing's at 177400.
+    assert_eq!(gdb.cmd("mff00,2"), "E01");
+    // Device registers are peeked, such as the PSW.
+    assert_eq!(gdb.cmd("mfffe,2"), "e000");
+    assert_eq!(gdb.cmd("D"), "OK");
+
+    let emu = server.join().unwrap();
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o11064);
+    assert_eq!(emu.get_state().get_status().to_raw(), 0o340);
+}
+
+#[test]
+fn write_regs_changing_mode() {
+    let (mut gdb, server, _) = connect(PROG);
+    assert_eq!(gdb.cmd("P6=0010"), "OK");
+    // Into user mode, with its own SP of 1234.
+    let regs = format!("{}9c020002{}", "0000".repeat(6), "00c0");
+    assert_eq!(gdb.cmd(&format!("G{regs}")), "OK");
+    assert_eq!(gdb.cmd("g"), regs);
+    assert_eq!(gdb.cmd("D"), "OK");
+
+    let emu = server.join().unwrap();
+    let state = emu.get_state();
+    assert_eq!(state.get_status().get_curr_mode(), ProcessorMode::User);
+    assert_eq!(state.sp_for(ProcessorMode::User), 0o1234);
+    assert_eq!(state.sp_for(ProcessorMode::Kernel), 0o10000);
+}
+
+#[test]
+fn breakpoints_and_watchpoints() {
+    let (mut gdb, server, start) = connect(PROG);
+    let lp = start + 4;
+    let val = lp + 10;
+    assert_eq!(gdb.cmd(&format!("Z0,{lp:x},2")), "OK");
+    for count in 0..2 {
+        assert_eq!(gdb.cmd("c"), "T05swbreak:;");
+        assert_eq!(gdb.cmd("p7"), format!("{:02x}{:02x}", lp & 0xff, lp >> 8));
+        assert_eq!(gdb.cmd("p0"), format!("{count:02x}00"));
+    }
+    assert_eq!(gdb.cmd("s"), "S05");
+    assert_eq!(gdb.cmd("p0"), "0200");
+    assert_eq!(gdb.cmd(&format!("z0,{lp:x},2")), "OK");
+
+    assert_eq!(gdb.cmd(&format!("Z2,{val:x},2")), "OK");
+    assert_eq!(gdb.cmd("c"), format!("T05watch:{val:x};"));
+    assert_eq!(gdb.cmd(&format!("z2,{val:x},2")), "OK");
+    assert_eq!(gdb.cmd("c"), "S05");
+    gdb.write("k");
+
+    let emu = server.join().unwrap();
+    assert_eq!(emu.reg_read_word(Reg::R0), 3);
+    assert_eq!(emu.breakpoints().iter().count(), 0);
+}
+
+#[test]
+fn interrupt() {
+    let (mut gdb, server, _) = connect("_start: br _start");
+    gdb.write("c");
+    thread::sleep(Duration::from_millis(20));
+    gdb.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(gdb.reply(), "S02");
+    // A Ctrl-C once stopped doesn't stop the next step.
+    gdb.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(gdb.cmd("s"), "S05");
+    assert_eq!(gdb.cmd("D"), "OK");
+    server.join().unwrap();
+}
@@ -0,0 +1,133 @@
+use as_lib::{Program, assemble_raw};
+use common::asm::Reg;
+use emu_lib::{Emulator, StopReason};
+
+fn start(asm: &str, limit: usize) -> (Emulator, Program) {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.set_history_limit(limit);
+    emu.load_image(&prog.text, 0);
+    emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
+    (emu, prog)
+}
+
+fn regs(emu: &Emulator) -> Vec<u16> {
+    use Reg::*;
+    let mut regs: Vec<u16> = [R0, R1, R2, R3, R4, R5, SP, PC]
+        .into_iter()
+        .map(|r| emu.reg_read_word(r))
+        .collect();
+    regs.push(emu.get_state().get_status().to_raw());
+    regs
+}
+
+#[test]
+fn step_back() {
+    // Goes through a trap, so the PSW and stack change too.
+    let asm = r#"
+        . = 30
+        .word emt_handler, 340
+
+        . = 1000
+    _start:
+        mov #1000, sp
+        mov #5, r0
+    loop:
+        movb r0, buf(r0)
+        sob r0, loop
+        emt 0
+        mov #-1, buf
+        halt
+
+    emt_handler:
+        mov #123, r1
+        rti
+
+    buf: .word 0, 0, 0, 0
+    "#;
+    let (mut emu, prog) = start(asm, 100);
+    let buf = prog.symbols.get("buf").unwrap().val;
+    let mem =
+        |emu: &mut Emulator| -> Vec<u8> { (0..8).map(|i| emu.mem_read_byte(buf + i)).collect() };
+
+    let mut states = vec![(regs(&emu), mem(&mut emu))];
+    while emu.run_ins().unwrap() != StopReason::Halted {
+        states.push((regs(&emu), mem(&mut emu)));
+    }
+    // The halt is a step too, taking it back to the last state.
+    assert_eq!(emu.history_len(), states.len());
+
+    while let Some(state) = states.pop() {
+        assert!(emu.step_back());
+        assert_eq!((regs(&emu), mem(&mut emu)), state);
+    }
+    assert!(!emu.step_back());
+    assert_eq!(mem(&mut emu), [0; 8]);
+}
+
+#[test]
+fn limit() {
+    let asm = r#"
+    _start:
+        mov #1, r0
+        mov #2, r0
+        mov #3, r0
+        mov #4, r0
+        halt
+    "#;
+    let (mut emu, _) = start(asm, 2);
+    while emu.run_ins().unwrap() != StopReason::Halted {}
+    assert_eq!(emu.history_len(), 2);
+    assert!(emu.step_back());
+    assert!(emu.step_back());
+    assert!(!emu.step_back());
+    assert_eq!(emu.reg_read_word(Reg::R0), 3);
+
+    // Off by default.
+    let (mut emu, _) = start(asm, 0);
+    while emu.run_ins().unwrap() != StopReason::Halted {}
+    assert!(!emu.step_back());
+}
+
+#[test]
+fn find_corruption() {
+    // One of the pushes onto a queue overwrites its length.
+    let asm = r#"
+    _start:
+        mov #1000, sp
+        clr r0
+    loop:
+        mov #queue, r1
+        add r0, r1
+    bad:
+        movb r0, (r1)
+        inc r0
+        cmp #12, r0
+        bne loop
+        halt
+
+    queue: .byte 0, 0, 0, 0, 0, 0, 0, 0
+    len: .word 0
+    "#;
+    let (mut emu, prog) = start(asm, 1000);
+    let sym = |name: &str| prog.symbols.get(name).unwrap().val;
+    emu.run().unwrap();
+    assert_eq!(emu.mem_read_word(sym("len")), 0o11 << 8 | 0o10);
+
+    // Go back to the last write that changed len.
+    while emu.mem_read_word(sym("len")) == 0o11 << 8 | 0o10 {
+        assert!(emu.step_back());
+    }
+    assert_eq!(emu.reg_read_word(Reg::PC), sym("bad"));
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o11);
+
+    // And back to the previous iteration.
+    assert!(emu.run_back_until(sym("bad")));
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o10);
+    assert!(!emu.run_back_until(sym("len")));
+    assert_eq!(emu.reg_read_word(Reg::PC), sym("_start"));
+
+    // Running forward again redoes the same.
+    emu.run().unwrap();
+    assert_eq!(emu.mem_read_word(sym("len")), 0o11 << 8 | 0o10);
+}
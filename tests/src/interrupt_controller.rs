@@ -0,0 +1,121 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::Emulator;
+use emu_lib::io::Interrupt;
+use emu_lib::io::interrupt_controller::InterruptController;
+use emu_lib::io::teletype::{PipeTty, Teletype};
+
+use std::sync::Arc;
+
+fn req(prio: u8, vector: u16) -> Interrupt {
+    Interrupt {
+        prio,
+        vector,
+        edge: false,
+    }
+}
+
+#[test]
+fn arbitration() {
+    let mut ctl = InterruptController::new();
+    ctl.set_requests(2, &[req(5, 0o300)]);
+    ctl.set_requests(1, &[req(5, 0o310), req(6, 0o320)]);
+    ctl.set_requests(0, &[req(4, 0o330)]);
+
+    // Highest level first, then the device nearest the CPU.
+    assert_eq!(ctl.highest_above(0), Some((1, req(6, 0o320))));
+    assert_eq!(ctl.highest_above(6), None);
+    ctl.set_requests(1, &[req(5, 0o310)]);
+    assert_eq!(ctl.highest_above(0), Some((1, req(5, 0o310))));
+    ctl.set_requests(1, &[]);
+    assert_eq!(ctl.highest_above(4), Some((2, req(5, 0o300))));
+    assert_eq!(ctl.highest_above(5), None);
+    ctl.set_requests(2, &[]);
+    assert_eq!(ctl.highest_above(3), Some((0, req(4, 0o330))));
+    assert_eq!(ctl.highest_above(4), None);
+}
+
+#[test]
+fn out_of_range_level() {
+    let mut ctl = InterruptController::new();
+    ctl.set_requests(0, &[req(3, 0o300)]);
+    assert_eq!(ctl.highest_above(3), Some((0, req(4, 0o300))));
+    ctl.set_requests(0, &[req(0o10, 0o300)]);
+    assert_eq!(ctl.highest_above(6), Some((0, req(7, 0o300))));
+    assert_eq!(ctl.highest_above(7), None);
+}
+
+#[test]
+fn edge() {
+    let edge = Interrupt {
+        edge: true,
+        ..req(4, 0o64)
+    };
+    let mut ctl = InterruptController::new();
+    ctl.set_requests(0, &[req(4, 0o60), edge]);
+
+    // Granting a level request leaves it pending; an edge one is dropped
+    // until it's withdrawn and made again.
+    ctl.grant(0, 0o60);
+    assert_eq!(ctl.highest_above(0), Some((0, req(4, 0o60))));
+    ctl.set_requests(0, &[edge]);
+    ctl.grant(0, 0o64);
+    assert_eq!(ctl.highest_above(0), None);
+    assert_eq!(ctl.granted(0), [0o64]);
+    ctl.set_requests(0, &[edge]);
+    assert_eq!(ctl.highest_above(0), None);
+    ctl.set_requests(0, &[]);
+    ctl.set_requests(0, &[edge]);
+    assert_eq!(ctl.highest_above(0), Some((0, edge)));
+    assert!(ctl.granted(0).is_empty());
+}
+
+#[test]
+fn keyboard_and_printer() {
+    // Both become pending while the CPU is at priority 7, and are both taken,
+    // keyboard first, once SPL lowers it.
+    let asm = r#"
+        TKS = 177560
+        TKB = TKS + 2
+        TPS = 177564
+        INT_ENB = 100
+
+        . = 60
+        .word keyboard, 340
+        .word printer, 340
+
+        . = 1000
+    _start:
+        mov #1000, sp
+        mov #log, r1
+        spl 7
+        mov #INT_ENB, @#TKS
+        mov #INT_ENB, @#TPS
+        spl 0
+        nop
+        halt
+
+    keyboard:
+        movb @#TKB, (r1)+
+        rti
+
+    printer:
+        clr @#TPS
+        movb #'p, (r1)+
+        rti
+
+    log: .word 0, 0
+    "#;
+    let prog = assemble_raw(asm);
+    let tty = Arc::new(PipeTty::default());
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(Teletype::new(tty.clone()));
+    tty.push_input(b'k');
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+
+    let log = prog.symbols.get("log").unwrap().val;
+    assert_eq!(emu.reg_read_word(Reg::R1), log + 2);
+    assert_eq!(emu.mem_read_byte(log), b'k');
+    assert_eq!(emu.mem_read_byte(log + 1), b'p');
+}
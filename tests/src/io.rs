@@ -3,7 +3,7 @@ use common::asm::Reg;
 use common::misc::ToU16P;
 use emu_lib::io::clock::{Clock, FakeClock};
 use emu_lib::io::teletype::*;
-use emu_lib::{Emulator, ExecRet};
+use emu_lib::{Emulator, StopReason};
 
 use std::io::BufRead;
 use std::sync::Arc;
@@ -223,9 +223,9 @@ fn threads() {
     emu.load_image(&prog.text, 0);
     emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
 
-    for _ in 0..250_000 {
-        let ret = emu.run_ins();
-        if ret == ExecRet::Halt {
+    for _ in 0..1_000_000 {
+        let ret = emu.run_ins().unwrap();
+        if ret == StopReason::Halted {
             break;
         }
     }
@@ -233,7 +233,9 @@ fn threads() {
     let mut buf = tty.take_output();
     buf.make_contiguous();
     let out = String::from_utf8_lossy(buf.as_slices().0);
-    assert_eq!(out, "00000000000111111111110000000000111111111110000000");
+    // Each thread runs for 20 ticks, a third of a second, which is enough to
+    // print about three characters at 10 a second.
+    assert_eq!(out, "0001110011100011000111001110");
 }
 
 #[test]
@@ -265,7 +267,7 @@ fn prio() {
         halt
     "#;
 
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
 
     let clock = FakeClock::default();
     let striker = clock.get_striker();
@@ -277,7 +279,7 @@ fn prio() {
     emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
 
     striker.strike();
-    emu.run();
+    emu.run().unwrap();
 
     assert_eq!(emu.reg_read_word(Reg::R0), 3);
 
@@ -311,7 +313,7 @@ fn prio() {
         halt
     "#;
 
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
 
     let clock = FakeClock::default();
     let striker = clock.get_striker();
@@ -323,7 +325,7 @@ fn prio() {
     emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
 
     striker.strike();
-    emu.run();
+    emu.run().unwrap();
 
     assert_eq!(emu.reg_read_word(Reg::R0), 0);
 
@@ -363,7 +365,7 @@ fn prio() {
         halt
     "#;
 
-    let prog = assemble_raw(&asm);
+    let prog = assemble_raw(asm);
 
     let clock = FakeClock::default();
     let striker = clock.get_striker();
@@ -375,7 +377,7 @@ fn prio() {
     emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
 
     striker.strike();
-    emu.run();
+    emu.run().unwrap();
 
     assert_eq!(emu.reg_read_word(Reg::R0), 3);
     assert_eq!(emu.reg_read_word(Reg::R1), 10);
@@ -411,6 +413,33 @@ fn pipe_keyboard_spin() {
     assert_eq!(emu.reg_read_byte(Reg::R0), val);
 }
 
+// Loopback isn't emulated, but setting the maintenance bit is harmless.
+#[test]
+fn tps_maintenance() {
+    let asm = r#"
+        TPS = 177564
+        TPB = TPS + 2
+        MAINT = 4
+
+        . = 400
+    _start:
+        mov #MAINT, @#TPS
+        mov @#TPS, r0
+        movb #'a, @#TPB
+        halt
+    "#;
+
+    let prog = assemble_raw(asm);
+
+    let tty = Arc::new(PipeTty::default());
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(Teletype::new(tty.clone()));
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o204);
+    assert_eq!(tty.take_output(), [b'a']);
+}
+
 #[test]
 fn pipe_echo_spin() {
     let asm = r#"
@@ -476,7 +505,7 @@ fn pipe_echo_spin_line() {
     let prog = assemble_raw(&asm);
 
     let tty = Arc::new(PipeTty::default());
-    let teletype = Teletype::new(tty.clone());
+    let teletype = Teletype::new(tty.clone()).with_chars_per_sec(960);
     let mut emu = Emulator::new();
     emu.set_mmio_handler(teletype);
 
@@ -496,8 +525,8 @@ fn pipe_echo_spin_line() {
 
     emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
     for _ in 0..1_000_000 {
-        let ret = emu.run_ins();
-        if ret == ExecRet::Halt {
+        let ret = emu.run_ins().unwrap();
+        if ret == StopReason::Halted {
             break;
         }
     }
@@ -4,8 +4,24 @@ use common::constants::DATA_START;
 use common::misc::ToU16P;
 use emu_lib::Emulator;
 
+const BUS_ERR_HANDLER: u16 = 0o1000;
+
+// Bus errors trap to a halt at BUS_ERR_HANDLER.
+fn bus_error_emu() -> Emulator {
+    let mut emu = Emulator::new();
+    emu.mem_write_word(0o4, BUS_ERR_HANDLER);
+    emu.mem_write_word(BUS_ERR_HANDLER, 0);
+    emu.reg_write_word(Reg::SP, 0o150000);
+    emu
+}
+
+fn assert_bus_error(emu: &Emulator) {
+    assert_eq!(emu.reg_read_word(Reg::PC), BUS_ERR_HANDLER + 2);
+    assert_eq!(emu.get_state().get_cpu_error(), 0o100); // Odd address
+    assert_eq!(emu.reg_read_word(Reg::R0), 0);
+}
+
 #[test]
-#[should_panic]
 fn unaligned_a() {
     let prog = assemble_raw(
         r#"
@@ -18,13 +34,13 @@ fn unaligned_a() {
         halt
     "#,
     );
-    let mut emu = Emulator::new();
+    let mut emu = bus_error_emu();
     emu.load_image(&prog.text, DATA_START);
     emu.run_at(DATA_START);
+    assert_bus_error(&emu);
 }
 
 #[test]
-#[should_panic]
 fn unaligned_b() {
     let prog = assemble_raw(
         r#"
@@ -37,9 +53,10 @@ fn unaligned_b() {
         halt
     "#,
     );
-    let mut emu = Emulator::new();
+    let mut emu = bus_error_emu();
     emu.load_image(&prog.text, DATA_START);
     emu.run_at(DATA_START);
+    assert_bus_error(&emu);
 }
 
 #[test]
@@ -145,9 +162,9 @@ fn cont() {
     emu.load_image(&prog.text, 0);
     emu.run_at(prog.symbols.get("_start").unwrap().val);
     assert_eq!(emu.reg_read_word(Reg::R0), 0);
-    emu.cont();
+    emu.cont().unwrap();
     assert_eq!(emu.reg_read_word(Reg::R0), 1);
-    emu.cont();
+    emu.cont().unwrap();
     assert_eq!(emu.reg_read_word(Reg::R0), 2);
     assert_eq!(emu.reg_read_word(Reg::PC), prog.text.len().to_u16p());
 }
@@ -0,0 +1,259 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::Emulator;
+use emu_lib::mmu::Mmu;
+
+fn run(asm: &str) -> Emulator {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    emu
+}
+
+// Identity maps kernel pages 0-6, maps page 7 to the I/O page, and enables
+// relocation. Expects the caller to define the abort handler.
+const PRELUDE: &str = r#"
+    STACK_TOP = 150000
+
+    KPDR0 = 172300
+    KPAR0 = 172340
+    UPDR0 = 177600
+    UPAR0 = 177640
+    SR0 = 177572
+    SR2 = 177576
+
+    FULL_RW = 77406
+    FULL_RO = 77402
+
+    . = 250
+    .word abort, 340
+
+    . = 400
+
+_start:
+    mov #STACK_TOP, sp
+    mov #KPAR0, r0
+    mov #KPDR0, r1
+    clr r2
+    mov #7, r3
+map_loop:
+    mov r2, (r0)+
+    mov #FULL_RW, (r1)+
+    add #200, r2
+    dec r3
+    bne map_loop
+    mov #7600, (r0)
+    mov #FULL_RW, (r1)
+"#;
+
+#[test]
+fn relocation() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+        mov #400, @#KPAR0+2     ; Virtual 20000 -> physical 40000
+        mov #1, @#SR0
+        mov #1234, @#20000
+        clr @#SR0
+        mov @#40000, r0
+        halt
+
+    abort:
+        clr r0
+        halt
+    "#
+    );
+
+    let emu = run(&asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o1234);
+    assert_eq!(emu.get_state().mem_read_word(0o40000), 0o1234);
+}
+
+#[test]
+fn relocation_18_bit() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+        mov #6000, @#KPAR0+2     ; Virtual 20000 -> physical 600000
+        mov #1, @#SR0
+        mov #4321, @#20002
+        mov @#20002, r0
+        halt
+
+    abort:
+        clr r0
+        halt
+    "#
+    );
+
+    let emu = run(&asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o4321);
+    assert_eq!(emu.get_state().mem_read_word(0o600002), 0o4321);
+    assert_eq!(emu.get_state().mem_read_word(0o20002), 0);
+}
+
+#[test]
+fn page_length_abort() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+        mov #6, @#KPDR0+2       ; Page 1 only one block long
+        mov #1, @#SR0
+        mov #1, @#20076         ; Ok
+    bad:
+        mov #1, @#20100         ; Past the end
+        clr r5
+        halt
+
+    abort:
+        mov @#SR0, r0
+        mov @#SR2, r1
+        mov #bad, r2
+        mov #7, r5
+        halt
+    "#
+    );
+
+    let emu = run(&asm);
+    assert_eq!(
+        emu.reg_read_word(Reg::R0),
+        Mmu::SR0_ABORT_PAGE_LENGTH | (1 << 1) | Mmu::SR0_ENABLE
+    );
+    assert_eq!(emu.reg_read_word(Reg::R1), emu.reg_read_word(Reg::R2));
+    assert_eq!(emu.reg_read_word(Reg::R5), 0o7);
+}
+
+#[test]
+fn read_only_abort() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+        mov #FULL_RO, @#KPDR0+2
+        mov #1, @#SR0
+        mov @#20000, r3         ; Reads are fine
+        mov #1, @#20000
+        halt
+
+    abort:
+        mov @#SR0, r0
+        bic #160000, @#SR0      ; Clear abort bits to unfreeze
+        mov @#SR0, r1
+        halt
+    "#
+    );
+
+    let emu = run(&asm);
+    assert_eq!(
+        emu.reg_read_word(Reg::R0),
+        Mmu::SR0_ABORT_READ_ONLY | (1 << 1) | Mmu::SR0_ENABLE
+    );
+    // The page number is read only, and kept.
+    assert_eq!(emu.reg_read_word(Reg::R1), (1 << 1) | Mmu::SR0_ENABLE);
+}
+
+#[test]
+fn user_mode() {
+    let asm = format!(
+        r#"
+        . = 34
+        .word trap_handler, 340
+        {PRELUDE}
+        mov #400, @#UPAR0       ; User virtual 0 -> physical 40000
+        mov #FULL_RW, @#UPDR0
+        mov #1, @#SR0
+
+        mov #140000, -(sp)      ; User mode
+        clr -(sp)
+        rti
+
+    abort:
+        halt
+
+    trap_handler:
+        mov @#40100, r4
+        mov @#SR0, r3
+        halt
+
+        . = 40000
+        mov #5, @#100
+        mov #1, r2
+        trap
+        halt
+    "#
+    );
+
+    let emu = run(&asm);
+    assert_eq!(emu.reg_read_word(Reg::R4), 0o5);
+    assert_eq!(emu.reg_read_word(Reg::R2), 0o1);
+    assert_eq!(emu.reg_read_word(Reg::R3), Mmu::SR0_ENABLE);
+}
+
+#[test]
+fn user_non_resident() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+        mov #400, @#UPAR0       ; User virtual 0 -> physical 40000
+        mov #FULL_RW, @#UPDR0
+        mov #1, @#SR0
+
+        mov #140000, -(sp)      ; User mode
+        clr -(sp)
+        rti
+
+    abort:
+        mov @#SR0, r0
+        mov @#SR2, r1
+        halt
+
+        . = 40000
+        mov @#20000, r2         ; User page 1 not resident
+        halt
+    "#
+    );
+
+    let emu = run(&asm);
+    assert_eq!(
+        emu.reg_read_word(Reg::R0),
+        Mmu::SR0_ABORT_NON_RESIDENT | (0o3 << 5) | (1 << 1) | Mmu::SR0_ENABLE
+    );
+    assert_eq!(emu.reg_read_word(Reg::R1), 0);
+}
+
+#[test]
+fn move_to_from_prev_space() {
+    let asm = format!(
+        r#"
+        {PRELUDE}
+        PSW = 177776
+
+        mov #400, @#UPAR0       ; User virtual 0 -> physical 40000
+        mov #FULL_RW, @#UPDR0
+        mov #1, @#SR0
+        mov #30000, @#PSW       ; Previous mode user
+
+        mov #1234, -(sp)
+        mtpi @#100
+        mfpi @#100
+        mov (sp)+, r0
+        mov @#100, r1
+        mov #4321, -(sp)
+        mtpd @#102
+        mfpd @#102
+        mov (sp)+, r2
+        halt
+
+    abort:
+        clr r0
+        halt
+    "#
+    );
+
+    let emu = run(&asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o1234);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0);
+    assert_eq!(emu.reg_read_word(Reg::R2), 0o4321);
+    assert_eq!(emu.get_state().mem_read_word(0o40100), 0o1234);
+    assert_eq!(emu.get_state().mem_read_word(0o40102), 0o4321);
+}
@@ -0,0 +1,163 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::{CpuModel, Emulator};
+
+fn run(asm: &str, model: CpuModel) -> Emulator {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::with_model(model);
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    emu
+}
+
+// Runs a single instruction, with R0 and R1 pointing at scratch memory and R2
+// set to 1, and returns the vector it trapped through, if any.
+fn trap_vector(ins: &str, model: CpuModel) -> Option<u16> {
+    let asm = format!(
+        r#"
+        . = 4
+        .word bus_err, 340
+        .word reserved, 340
+        . = 244
+        .word fp_err, 340
+
+        . = 1000
+    _start:
+        mov #1000, sp
+        mov #scratch, r0
+        mov #scratch, r1
+        mov #1, r2
+        clr r5
+        {ins}
+        halt
+
+    bus_err:
+        mov #4, r5
+        halt
+    reserved:
+        mov #10, r5
+        halt
+    fp_err:
+        mov #244, r5
+        halt
+
+    scratch: .word 0, 0, 0, 0, 0, 0, 0, 0
+    "#
+    );
+    let emu = run(&asm, model);
+    match emu.reg_read_word(Reg::R5) {
+        0 => None,
+        vector => Some(vector),
+    }
+}
+
+#[test]
+fn parse() {
+    assert_eq!("11/20".parse(), Ok(CpuModel::Pdp1120));
+    assert_eq!("70".parse(), Ok(CpuModel::Pdp1170));
+    assert_eq!("generic".parse(), Ok(CpuModel::Generic));
+    assert!("11/44".parse::<CpuModel>().is_err());
+    for model in CpuModel::ALL {
+        assert_eq!(model.to_string().parse(), Ok(model));
+    }
+}
+
+#[test]
+fn instruction_groups() {
+    use CpuModel::*;
+    let cases = [
+        ("mul r0, r2", [None, Some(0o10), None, None, None]),
+        ("xor r0, r2", [None, Some(0o10), None, None, None]),
+        ("sob r2, _start", [None, Some(0o10), None, None, None]),
+        ("sxt r2", [None, Some(0o10), None, None, None]),
+        ("fadd r0", [None, Some(0o10), None, Some(0o10), Some(0o10)]),
+        ("setd", [None, Some(0o10), Some(0o10), None, None]),
+        ("spl 0", [None, Some(0o10), Some(0o10), None, None]),
+        ("mfpi r2", [None, Some(0o10), None, None, None]),
+        ("mfpd r2", [None, Some(0o10), Some(0o10), None, None]),
+        (
+            "mfps r2",
+            [None, Some(0o10), Some(0o10), Some(0o10), Some(0o10)],
+        ),
+    ];
+    for (ins, expected) in cases {
+        for (model, vector) in [Generic, Pdp1120, Pdp1140, Pdp1145, Pdp1170]
+            .into_iter()
+            .zip(expected)
+        {
+            assert_eq!(trap_vector(ins, model), vector, "{ins} on {model}");
+        }
+    }
+}
+
+#[test]
+fn io_page() {
+    use CpuModel::*;
+    const CPU_ERR: &str = "tst @#177766";
+    const STACK_LIMIT: &str = "tst @#177774";
+    const MMR0: &str = "tst @#177572";
+    let cases = [
+        (CPU_ERR, [None, Some(4), Some(4), Some(4), None]),
+        (STACK_LIMIT, [None, Some(4), Some(4), None, None]),
+        (MMR0, [None, Some(4), None, None, None]),
+        ("tst @#177776", [None, None, None, None, None]),
+    ];
+    for (ins, expected) in cases {
+        for (model, vector) in [Generic, Pdp1120, Pdp1140, Pdp1145, Pdp1170]
+            .into_iter()
+            .zip(expected)
+        {
+            assert_eq!(trap_vector(ins, model), vector, "{ins} on {model}");
+        }
+    }
+}
+
+#[test]
+fn jmp_reg() {
+    assert_eq!(trap_vector("jmp r2", CpuModel::Pdp1120), Some(4));
+    assert_eq!(trap_vector("jsr pc, r2", CpuModel::Pdp1120), Some(4));
+    assert_eq!(trap_vector("jmp r2", CpuModel::Pdp1140), Some(0o10));
+    assert_eq!(trap_vector("jsr pc, r2", CpuModel::Generic), Some(0o10));
+}
+
+#[test]
+fn push_sp() {
+    // The 11/20 pushes the decremented SP, the others the original.
+    let asm = r#"
+    _start:
+        mov #1000, sp
+        mov sp, -(sp)
+        mov (sp), r0
+        mov #2000, r1
+        mov r1, (r1)+
+        mov @#2000, r2
+        halt
+    "#;
+    let emu = run(asm, CpuModel::Pdp1120);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o776);
+    assert_eq!(emu.reg_read_word(Reg::R2), 0o2002);
+    let emu = run(asm, CpuModel::Pdp1170);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o1000);
+    assert_eq!(emu.reg_read_word(Reg::R2), 0o2000);
+}
+
+#[test]
+fn jmp_autoinc() {
+    // The 11/20 jumps to the incremented register.
+    let asm = r#"
+    _start:
+        clr r1
+        mov #target, r0
+        jmp (r0)+
+    target:
+        br one
+        mov #2, r1
+        halt
+    one:
+        mov #1, r1
+        halt
+    "#;
+    assert_eq!(run(asm, CpuModel::Pdp1140).reg_read_word(Reg::R1), 1);
+    let emu = run(asm, CpuModel::Pdp1120);
+    assert_eq!(emu.reg_read_word(Reg::R1), 2);
+}
@@ -0,0 +1,194 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::Emulator;
+
+fn run(asm: &str) -> Emulator {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    emu
+}
+
+#[test]
+fn trap_switches_stacks() {
+    let asm = r#"
+        PSW = 177776
+
+        . = 34
+        .word handler, 0
+
+        . = 400
+    _start:
+        mov #150000, sp
+        mov #30000, @#PSW       ; Previous mode user
+        mov #120000, -(sp)
+        mtpi sp                 ; User stack pointer
+
+        mov #170000, -(sp)      ; User mode, previous user
+        mov #user, -(sp)
+        rti
+
+    user:
+        mov sp, r0
+        trap
+
+    handler:
+        mov @#PSW, r2
+        mov sp, r1
+        mfpi sp
+        mov (sp)+, r3
+        mov 2(sp), r4           ; Saved PS
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o120000);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0o147774);
+    assert_eq!(emu.reg_read_word(Reg::R2), 0o030000);
+    assert_eq!(emu.reg_read_word(Reg::R3), 0o120000);
+    assert_eq!(emu.reg_read_word(Reg::R4) & !0o17, 0o170000);
+}
+
+#[test]
+fn user_rti_cant_raise_privilege() {
+    let asm = r#"
+        PSW = 177776
+
+        . = 400
+    _start:
+        mov #150000, sp
+        mov #30000, @#PSW
+        mov #120000, -(sp)
+        mtpi sp
+
+        mov #170340, -(sp)      ; User mode, priority 7
+        mov #user, -(sp)
+        rti
+
+    user:
+        clr -(sp)               ; Kernel mode, priority 0
+        mov #next, -(sp)
+        rti
+    next:
+        mov @#PSW, r0
+        mov sp, r1
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o170340);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0o120000);
+}
+
+#[test]
+fn user_psw_write() {
+    let asm = r#"
+        PSW = 177776
+
+        . = 400
+    _start:
+        mov #150000, sp
+        mov #30000, @#PSW
+        mov #120000, -(sp)
+        mtpi sp
+
+        mov #170000, -(sp)
+        mov #user, -(sp)
+        rti
+
+    user:
+        clr @#PSW               ; Try to switch to kernel mode
+        mov @#PSW, r0
+        mov sp, r1
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0) & 0o170000, 0o170000);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0o120000);
+}
+
+#[test]
+fn kernel_psw_write() {
+    let asm = r#"
+        PSW = 177776
+
+        . = 400
+    _start:
+        mov #150000, sp
+        mov #30340, @#PSW
+        mov @#PSW, r0
+        movb #1, @#PSW+1
+        mov @#PSW, r1
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o030340);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0o000740);
+}
+
+#[test]
+fn spl_mfps_mtps() {
+    let asm = r#"
+        . = 400
+    _start:
+        mov #150000, sp
+        spl 5
+        ccc
+        mfps r0
+        mtps #351               ; Priority 7, N and C
+        mfps r1
+        mfps @#1000
+
+        mov #140000, -(sp)      ; User mode
+        mov #user, -(sp)
+        rti
+
+    user:
+        spl 3                   ; No effect outside kernel mode
+        mtps #100               ; Neither is the priority
+        mfps r2
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o177640);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0o177751); // Sign extended
+    assert_eq!(emu.get_state().mem_read_byte(0o1000), 0o351);
+    assert_eq!(emu.reg_read_word(Reg::R2), 0);
+}
+
+#[test]
+fn user_halt_traps() {
+    let asm = r#"
+        CPUERR = 177766
+
+        . = 4
+        .word handler, 0
+
+        . = 400
+    _start:
+        mov #150000, sp
+        mov #140000, -(sp)      ; User mode
+        mov #user, -(sp)
+        rti
+
+    user:
+        halt
+        mov #1, r1              ; Not reached
+
+    handler:
+        mov @#CPUERR, r0
+        mov (sp), r2            ; Saved PC
+        mov 2(sp), r3           ; Saved PS
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o200);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0);
+    assert_eq!(emu.reg_read_word(Reg::R2), 0o420);
+    assert_eq!(emu.reg_read_word(Reg::R3) & !0o17, 0o140000);
+}
@@ -0,0 +1,211 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::Emulator;
+use emu_lib::io::front_panel::FrontPanel;
+use emu_lib::io::teletype::{PipeTty, Teletype};
+use emu_lib::monitor::{self, Outcome};
+
+use std::fs;
+use std::sync::Arc;
+
+const PROG: &str = r#"
+    TPS = 177564
+    TPB = TPS + 2
+    . = 1000
+_start:
+    mov #3, r1
+loop:
+    inc r0
+    sob r1, loop
+    movb #'a, @#TPB
+    halt
+"#;
+
+const KPDR0: u16 = 0o172300;
+const KPAR0: u16 = 0o172340;
+const SR0: u16 = 0o177572;
+
+fn setup() -> Emulator {
+    let prog = assemble_raw(PROG);
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(Teletype::new(Arc::new(PipeTty::default())));
+    emu.load_image(&prog.text, 0);
+    emu
+}
+
+fn exec(emu: &mut Emulator, line: &str) -> String {
+    match monitor::exec(emu, line) {
+        Ok(Outcome::Output(out)) => out,
+        res => panic!("{line}: {res:?}"),
+    }
+}
+
+#[test]
+fn examine_and_deposit() {
+    let mut emu = setup();
+    assert_eq!(exec(&mut emu, "e 1000"), "001000: 012701");
+    assert_eq!(exec(&mut emu, "d 2000 123"), "");
+    assert_eq!(
+        exec(&mut emu, "examine 2000-2002"),
+        "002000: 000123\n002002: 000000"
+    );
+    assert_eq!(exec(&mut emu, "d r3 777"), "");
+    assert_eq!(exec(&mut emu, "e R3"), "r3: 000777");
+    assert_eq!(exec(&mut emu, "dep ps 340"), "");
+    assert_eq!(exec(&mut emu, "e ps"), "ps: 000340");
+
+    assert_eq!(
+        monitor::exec(&mut emu, "e 1001"),
+        Err("odd address 001001".into())
+    );
+    assert_eq!(
+        monitor::exec(&mut emu, "e 177400"),
+        Err("no register at 177400 that can be read without side effects".into())
+    );
+    assert_eq!(
+        monitor::exec(&mut emu, "d 1000 9"),
+        Err("bad octal number 9".into())
+    );
+    assert!(monitor::exec(&mut emu, "frob").is_err());
+    assert_eq!(monitor::exec(&mut emu, "quit"), Ok(Outcome::Quit));
+}
+
+#[test]
+fn run_and_break() {
+    let mut emu = setup();
+    assert_eq!(exec(&mut emu, "break 1004 r0 = 2"), "breakpoint 0");
+    assert_eq!(exec(&mut emu, "show break"), "0: exec 001004 when r0 = 2");
+    assert_eq!(
+        exec(&mut emu, "go 1000"),
+        "breakpoint 0 at 001004, pc 001004 (inc r0)"
+    );
+    assert_eq!(exec(&mut emu, "e r0"), "r0: 000002");
+    assert_eq!(
+        exec(&mut emu, "step 2"),
+        "step expired, pc 001010 (movb #0o141, @#0o177566)"
+    );
+    assert_eq!(exec(&mut emu, "nobreak all"), "");
+    assert_eq!(exec(&mut emu, "c"), "halted, pc 001020 (halt)");
+    assert_eq!(exec(&mut emu, "e r0"), "r0: 000003");
+}
+
+#[test]
+fn devices() {
+    let mut emu = setup();
+    let devices = exec(&mut emu, "show devices");
+    assert!(
+        devices.contains("Teletype: 177560 177562 177564 177566"),
+        "{devices}"
+    );
+
+    let path = std::env::temp_dir().join(format!("monitor-attach-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    assert_eq!(exec(&mut emu, &format!("attach teletype {path}")), "");
+    exec(&mut emu, "go 1000");
+    assert_eq!(exec(&mut emu, "detach teletype"), "");
+    assert_eq!(fs::read(path).unwrap(), b"a");
+    fs::remove_file(path).unwrap();
+
+    let err = monitor::exec(&mut emu, "attach StatusAccess x").unwrap_err();
+    assert_eq!(err, "StatusAccess can't be attached");
+    assert_eq!(
+        monitor::exec(&mut emu, "detach frob").unwrap_err(),
+        "no device frob"
+    );
+}
+
+#[test]
+fn disassemble() {
+    let mut emu = setup();
+    let dis = exec(&mut emu, "dis 1000-1006");
+    let lines: Vec<_> = dis.lines().collect();
+    assert_eq!(lines.len(), 3, "{dis}");
+    assert!(lines[0].contains("0o012701 0o000003"), "{dis}");
+    assert!(lines[0].ends_with("#0o3, r1"), "{dis}");
+    assert!(lines[2].contains("sob"), "{dis}");
+    assert!(lines[2].ends_with("r1, 0o1004"), "{dis}");
+
+    // From the pc, up to what can be read.
+    exec(&mut emu, "d pc 1010");
+    let dis = exec(&mut emu, "dis");
+    assert_eq!(dis.lines().count(), 8);
+    assert!(dis.lines().next().unwrap().starts_with("0o001010"), "{dis}");
+
+    assert_eq!(
+        monitor::exec(&mut emu, "dis 177400"),
+        Err("no register at 177400 that can be read without side effects".into())
+    );
+    // With kernel page 7 mapped onto the bottom of memory, the last word can
+    // be read. The padding after it mustn't run past the end.
+    for page in 0..8 {
+        let base = if page == 7 { 0 } else { page * 0o200 };
+        emu.mem_write_word(KPAR0 + page * 2, base);
+        emu.mem_write_word(KPDR0 + page * 2, 0o77406);
+    }
+    emu.mem_write_word(SR0, 1);
+    assert_eq!(exec(&mut emu, "d 177776 5"), "");
+    let dis = exec(&mut emu, "dis 177776");
+    assert_eq!(dis.lines().count(), 1, "{dis}");
+    assert!(dis.ends_with("reset"), "{dis}");
+}
+
+#[test]
+fn examine_io_page() {
+    let asm = r#"
+        TKS = 177560
+        TKB = 177562
+        . = 1000
+    _start:
+        tstb @#TKS
+        bpl _start
+        movb @#TKB, r0
+        halt
+    "#;
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    let tty = Arc::new(PipeTty::default());
+    emu.set_mmio_handler(Teletype::new(tty.clone()));
+    emu.load_image(&prog.text, 0);
+    tty.push_input(b'x');
+
+    // Registers are peeked, so examining TKB doesn't take the character.
+    assert_eq!(
+        exec(&mut emu, "e 177560-177562"),
+        "177560: 000200\n177562: 000170"
+    );
+    assert_eq!(exec(&mut emu, "e 177562"), "177562: 000170");
+    assert_eq!(exec(&mut emu, "e 177564"), "177564: 000200");
+    emu.set_mmio_handler(FrontPanel::new(0o1234));
+    assert_eq!(exec(&mut emu, "e 177570"), "177570: 001234");
+    emu.run_at(0o1000);
+    assert_eq!(emu.reg_read_word(Reg::R0), b'x' as u16);
+}
+
+#[test]
+fn load() {
+    let mut emu = setup();
+    let path = std::env::temp_dir().join(format!("monitor-load-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    let mut aout = Vec::new();
+    as_lib::assemble(PROG).write_to(&mut aout);
+    fs::write(path, &aout).unwrap();
+    assert_eq!(exec(&mut emu, &format!("load {path}")), "");
+    assert_eq!(exec(&mut emu, "e pc"), "pc: 001000");
+
+    // Bad files are reported, not loaded.
+    fs::write(path, b"not an a.out").unwrap();
+    assert_eq!(
+        monitor::exec(&mut emu, &format!("load {path}")),
+        Err(format!("{path}: bad magic number 067556"))
+    );
+    aout[2] = 0; // Too little text for the entry point.
+    fs::write(path, &aout).unwrap();
+    assert_eq!(
+        monitor::exec(&mut emu, &format!("load {path}")),
+        Err(format!("{path}: bad entry point 001000"))
+    );
+    fs::write(path, &aout[..4]).unwrap();
+    assert!(monitor::exec(&mut emu, &format!("load {path}")).is_err());
+    fs::remove_file(path).unwrap();
+}
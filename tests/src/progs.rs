@@ -5,7 +5,7 @@ use common::misc::ToU16P;
 use emu_lib::Emulator;
 use emu_lib::io::teletype::*;
 
-use std::assert_matches::assert_matches;
+use std::assert_matches;
 use std::sync::Arc;
 
 #[test]
@@ -142,7 +142,7 @@ fn unsigned_mul() {
         mov (sp)+, r2
         rts pc
     "#;
-    let aout = assemble(&asm);
+    let aout = assemble(asm);
     let mut emu = Emulator::new();
     emu.load_aout(&aout);
 
@@ -155,9 +155,9 @@ fn unsigned_mul() {
         let lower = emu.reg_read_word(Reg::R0);
         let upper = emu.reg_read_word(Reg::R1);
 
-        let gold = lhs.widening_mul(rhs);
-        assert_eq!(lower, gold.0);
-        assert_eq!(upper, gold.1);
+        let gold = (lhs as u32) * (rhs as u32);
+        assert_eq!(lower, gold as u16);
+        assert_eq!(upper, (gold >> u16::BITS) as u16);
     };
 
     run(0, 0);
@@ -322,3 +322,21 @@ fn rolb() {
     run_mp("rolb", 0xaaff, 0xaafe, 0, N | C);
     run_mp("rolb", 0xaaff, 0xaaff, C, C | N);
 }
+
+#[test]
+fn sxt() {
+    run("sxt", 0o1234, 0, Z);
+
+    let prog = assemble_raw(
+        r#"
+        sen
+        sxt r0
+        halt
+    "#,
+    );
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, DATA_START);
+    emu.run_at(DATA_START);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o177777);
+    check_flags(&emu, N);
+}
@@ -0,0 +1,124 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::io::clock::Clock;
+use emu_lib::io::teletype::{PipeTty, Teletype};
+use emu_lib::{CpuModel, Emulator};
+
+use std::io::ErrorKind;
+use std::sync::Arc;
+
+// Prints a message a character per teleprinter interrupt, while counting
+// clock ticks, then halts with the count in R0.
+const PROG: &str = r#"
+    TPS = 177564
+    TPB = TPS + 2
+    LKS = 177546
+    INT_ENB = 100
+
+    . = 64
+    .word tp_ready, 200
+    . = 100
+    .word clock, 300
+
+    . = 1000
+_start:
+    mov #1000, sp
+    mov #msg, r1
+    clr r0
+    mov #INT_ENB, @#LKS
+    mov #INT_ENB, @#TPS
+loop:
+    wait
+    br loop
+
+tp_ready:
+    movb (r1)+, r2
+    beq done
+    movb r2, @#TPB
+    rti
+done:
+    halt
+
+clock:
+    tst @#LKS
+    inc r0
+    rti
+
+msg: .asciz "hello, world!"
+"#;
+
+fn machine(model: CpuModel) -> (Emulator, Arc<PipeTty>) {
+    let tty = Arc::new(PipeTty::default());
+    let mut emu = Emulator::with_model(model);
+    emu.set_mmio_handler(Teletype::new(tty.clone()).with_chars_per_sec(1000));
+    emu.set_mmio_handler(Clock::with_hz(1000));
+    (emu, tty)
+}
+
+fn output(tty: &PipeTty) -> String {
+    String::from_utf8(tty.take_output().into()).unwrap()
+}
+
+fn snapshot_at(time_ns: u64) -> (Emulator, Arc<PipeTty>, Vec<u8>) {
+    let prog = assemble_raw(PROG);
+    let (mut emu, tty) = machine(CpuModel::Pdp1140);
+    emu.load_image(&prog.text, 0);
+    emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
+    while emu.get_state().get_time_ns() < time_ns {
+        emu.run_ins().unwrap();
+    }
+    let mut snapshot = Vec::new();
+    emu.save_snapshot(&mut snapshot).unwrap();
+    (emu, tty, snapshot)
+}
+
+#[test]
+fn resume() {
+    let (mut emu, tty, snapshot) = snapshot_at(6_000_000);
+    // Part way through the message.
+    let before = output(&tty);
+    assert!(!before.is_empty() && before.len() < 13, "{before:?}");
+    emu.run().unwrap();
+
+    let (mut resumed, resumed_tty) = machine(CpuModel::Pdp1140);
+    resumed.load_snapshot(&mut snapshot.as_slice()).unwrap();
+    resumed.run().unwrap();
+
+    let after = output(&tty);
+    assert_eq!(before + &after, "hello, world!");
+    assert_eq!(output(&resumed_tty), after);
+    for reg in [Reg::R0, Reg::R1, Reg::R2, Reg::SP, Reg::PC] {
+        assert_eq!(
+            resumed.reg_read_word(reg),
+            emu.reg_read_word(reg),
+            "{reg:?}"
+        );
+    }
+    assert_eq!(
+        resumed.get_state().get_time_ns(),
+        emu.get_state().get_time_ns()
+    );
+    // Ticks kept coming through the whole run.
+    assert!(emu.reg_read_word(Reg::R0) > 10);
+}
+
+#[test]
+fn mismatch() {
+    let (_, _, snapshot) = snapshot_at(100_000);
+
+    let (mut other, _) = machine(CpuModel::Pdp1170);
+    let err = other.load_snapshot(&mut snapshot.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let mut other = Emulator::with_model(CpuModel::Pdp1140);
+    let err = other.load_snapshot(&mut snapshot.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let (mut other, _) = machine(CpuModel::Pdp1140);
+    let err = other.load_snapshot(&mut &b"hello"[..]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    let err = other
+        .load_snapshot(&mut &snapshot[..snapshot.len() - 1])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,135 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::Emulator;
+
+fn run(asm: &str) -> Emulator {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    emu
+}
+
+const YELLOW_ZONE: u16 = 0o10;
+const RED_ZONE: u16 = 0o4;
+
+#[test]
+fn yellow_zone() {
+    let asm = r#"
+        CPU_ERR = 177766
+
+        . = 4
+        .word bus_err, 340
+
+        . = 1000
+    _start:
+        mov #400, sp
+        clr r0
+        mov #123, -(sp)         ; Completes, then traps
+        inc r0
+        halt
+
+    bus_err:
+        mov @#CPU_ERR, r2
+        mov sp, r3
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0);
+    assert_eq!(emu.reg_read_word(Reg::R2), YELLOW_ZONE);
+    assert_eq!(emu.reg_read_word(Reg::R3), 0o372);
+    assert_eq!(emu.get_state().mem_read_word(0o376), 0o123);
+}
+
+#[test]
+fn red_zone() {
+    let asm = r#"
+        CPU_ERR = 177766
+
+        . = 4
+        .word bus_err, 340
+
+        . = 1000
+    _start:
+        mov #340, sp
+    bad:
+        mov #123, -(sp)         ; Aborted
+        halt
+
+    bus_err:
+        mov @#CPU_ERR, r2
+        mov sp, r3
+        mov @#0, r4             ; Saved PC
+        mov #bad, r5
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R2), RED_ZONE);
+    assert_eq!(emu.reg_read_word(Reg::R3), 0);
+    assert_eq!(emu.reg_read_word(Reg::R4), emu.reg_read_word(Reg::R5) + 4);
+    assert_eq!(emu.get_state().mem_read_word(0o336), 0);
+}
+
+#[test]
+fn limit_register() {
+    let asm = r#"
+        CPU_ERR = 177766
+        STACK_LIMIT = 177774
+
+        . = 4
+        .word bus_err, 340
+
+        . = 1000
+    _start:
+        mov #1234, @#STACK_LIMIT
+        mov @#STACK_LIMIT, r0
+        mov #1402, sp
+        clr -(sp)               ; Fine
+        clr r1
+        clr -(sp)               ; Yellow
+        inc r1
+        halt
+
+    bus_err:
+        mov @#CPU_ERR, r2
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o1000);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0);
+    assert_eq!(emu.reg_read_word(Reg::R2), YELLOW_ZONE);
+}
+
+#[test]
+fn user_mode_unchecked() {
+    let asm = r#"
+        . = 4
+        .word bus_err, 340
+        . = 20
+        .word done, 340
+
+        . = 1000
+    _start:
+        mov #150000, sp
+        mov #170000, -(sp)      ; User mode
+        mov #user, -(sp)
+        rti
+
+    user:
+        mov #100, sp
+        mov #7, -(sp)
+        mov (sp)+, r0
+        iot                     ; HALT would trap in user mode
+
+    bus_err:
+        clr r0
+    done:
+        halt
+    "#;
+
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o7);
+}
@@ -0,0 +1,114 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::emulator_state::Status;
+use emu_lib::io::Interrupt;
+use emu_lib::{CpuModel, Emulator, EmulatorError, EmulatorState, MMIOHandler, StopReason};
+
+fn run(asm: &str) -> (Emulator, Result<StopReason, EmulatorError>) {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::with_model(CpuModel::Pdp1140);
+    emu.load_image(&prog.text, 0);
+    emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
+    let res = emu.run();
+    (emu, res)
+}
+
+#[test]
+fn halted() {
+    let (emu, res) = run("_start: halt");
+    assert_eq!(res.unwrap(), StopReason::Halted);
+    assert_eq!(emu.reg_read_word(Reg::PC), 2);
+}
+
+#[test]
+fn ins_limit() {
+    let asm = r#"
+    _start:
+        inc r0
+        br _start
+    "#;
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
+    assert_eq!(emu.run_for(100).unwrap(), StopReason::InsLimit);
+    assert_eq!(emu.reg_read_word(Reg::R0), 50);
+    assert_eq!(emu.run_for(1).unwrap(), StopReason::InsLimit);
+    assert_eq!(emu.reg_read_word(Reg::R0), 51);
+
+    // A halt comes first.
+    let mut emu = Emulator::new();
+    emu.load_image(&[0, 0], 0);
+    assert_eq!(emu.run_for(100).unwrap(), StopReason::Halted);
+}
+
+#[test]
+fn double_fault() {
+    // With the MMU on and nothing mapped, neither the next instruction, nor
+    // the MMU trap vector, nor the emergency stack can be reached.
+    let asm = r#"
+        SR0 = 177572
+    _start:
+        mov #1, @#SR0
+        halt
+    "#;
+    let (_, res) = run(asm);
+    assert_eq!(res.unwrap(), StopReason::DoubleFault);
+}
+
+// Requests an interrupt through a vector that can't be one.
+struct BadVector;
+
+impl MMIOHandler for BadVector {
+    fn interrupts(&self, requests: &mut Vec<Interrupt>) {
+        requests.push(Interrupt {
+            prio: 4,
+            vector: 0o1002,
+            edge: false,
+        });
+    }
+
+    fn read_byte(&mut self, _emu: &mut EmulatorState, _addr: u16) -> Option<u8> {
+        None
+    }
+
+    fn read_word(&mut self, _emu: &mut EmulatorState, _addr: u16) -> Option<u16> {
+        None
+    }
+
+    fn write_byte(&mut self, _emu: &mut EmulatorState, _addr: u16, _val: u8) -> Option<()> {
+        None
+    }
+
+    fn write_word(&mut self, _emu: &mut EmulatorState, _addr: u16, _val: u16) -> Option<()> {
+        None
+    }
+}
+
+#[test]
+fn emulator_error() {
+    let asm = r#"
+        PSW = 177776
+        . = 1000
+    _start:
+        mov #1, r1
+        clr @#PSW
+        inc r1
+        halt
+    "#;
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.load_image(&prog.text, 0);
+    emu.set_mmio_handler(BadVector);
+    emu.get_state_mut().set_status(Status::from_raw(0o340));
+    emu.reg_write_word(Reg::PC, 0o1000);
+    let err = emu.run().unwrap_err();
+    assert_eq!(err.pc, 0o1010);
+    assert!(err.ins.is_some());
+    assert!(err.message.contains("vector 1002"), "{}", err.message);
+    assert!(
+        err.to_string()
+            .starts_with("emulator error at pc 001010 (inc")
+    );
+    assert_eq!(emu.reg_read_word(Reg::R1), 1);
+}
@@ -1,20 +1,39 @@
 #![cfg(test)]
-#![feature(bigint_helper_methods)]
-#![feature(assert_matches)]
 
 mod flags;
 
 mod addressing_modes;
 mod branch;
+mod breakpoint;
+mod bus;
+mod bus_error;
 mod call;
 mod condition_code;
+mod control;
+mod decode_cache;
 mod double_operand;
 mod eis;
 mod exprs;
+mod fis;
+mod fp;
+mod front_panel;
+mod gdb;
+mod history;
+mod interrupt_controller;
 mod io;
 mod jmp;
 mod misc;
 mod mixed_addressing;
+mod mmu;
+mod models;
+mod modes;
+mod monitor;
 mod progs;
 mod single_operand;
+mod snapshot;
+mod stack_limit;
+mod stop;
+mod throttle;
+mod timing;
+mod trace;
 mod trap;
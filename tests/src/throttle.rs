@@ -0,0 +1,140 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::breakpoint::WatchHit;
+use emu_lib::io::clock::Clock;
+use emu_lib::io::teletype::{PipeTty, Teletype};
+use emu_lib::{Breakpoint, CpuModel, Emulator, StopReason, Throttle, WatchAccess};
+
+use std::sync::Arc;
+
+use std::time::{Duration, Instant};
+
+#[test]
+fn parse() {
+    assert_eq!("unlimited".parse(), Ok(Throttle::Unlimited));
+    assert_eq!("model".parse(), Ok(Throttle::Model));
+    assert_eq!("300000".parse(), Ok(Throttle::InsPerSec(300_000)));
+    assert_eq!("1000000000".parse(), Ok(Throttle::InsPerSec(1_000_000_000)));
+    assert!("0".parse::<Throttle>().is_err());
+    assert!("1000000001".parse::<Throttle>().is_err());
+    assert!("fast".parse::<Throttle>().is_err());
+}
+
+#[test]
+fn ins_time() {
+    assert_eq!(Throttle::InsPerSec(100_000).ins_time_ns(), Some(10_000));
+    assert_eq!(
+        Throttle::InsPerSec(Throttle::MAX_RATE).ins_time_ns(),
+        Some(1)
+    );
+    // Instructions always take some time.
+    assert_eq!(Throttle::InsPerSec(u32::MAX).ins_time_ns(), Some(1));
+    assert_eq!(Throttle::Model.ins_time_ns(), None);
+}
+
+#[test]
+fn model_speed() {
+    // Three ticks of the 60 Hz clock take 50 ms, on the wall clock too.
+    let asm = r#"
+        LKS = 177546
+        LKS_INT_ENB = 100
+
+        . = 100
+        .word clock, 300
+
+        . = 1000
+    _start:
+        mov #1000, sp
+        clr r0
+        mov #LKS_INT_ENB, @#LKS
+    loop:
+        cmp #3, r0
+        bne loop
+        halt
+
+    clock:
+        tst @#LKS
+        inc r0
+        rti
+    "#;
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::with_model(CpuModel::Pdp1140);
+    emu.set_throttle(Throttle::Model);
+    emu.set_mmio_handler(Clock::default());
+    emu.load_image(&prog.text, 0);
+
+    let start = Instant::now();
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    let wall = start.elapsed();
+    let sim = Duration::from_nanos(emu.get_state().get_time_ns());
+    assert!(sim >= Duration::from_millis(50), "{sim:?}");
+    // Pacing is only checked every ms.
+    assert!(wall + Duration::from_millis(1) >= sim, "{wall:?} < {sim:?}");
+}
+
+#[test]
+fn ins_per_sec() {
+    // 10k instructions at 100k a second.
+    let asm = r#"
+    _start:
+        mov #4999., r0
+    loop:
+        sob r0, loop
+        halt
+    "#;
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.set_throttle(Throttle::InsPerSec(100_000));
+    emu.load_image(&prog.text, 0);
+
+    let start = Instant::now();
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    let wall = start.elapsed();
+    assert_eq!(emu.reg_read_word(Reg::R0), 0);
+    // Each instruction takes 10 us.
+    assert_eq!(emu.get_state().get_time_ns(), 5001 * 10_000);
+    assert!(wall >= Duration::from_millis(49), "{wall:?}");
+}
+
+#[test]
+fn timer_ticks() {
+    // A digit every 10 ticks of the 60 Hz clock, on the wall clock too.
+    let mut asm = include_str!("../../examples/timer_ticks.s").to_string();
+    asm += include_str!("../../examples/teletype_spin.s");
+    let prog = assemble_raw(&asm);
+    let mut emu = Emulator::new();
+    emu.set_throttle(Throttle::Model);
+    let tty = Arc::new(PipeTty::default());
+    emu.set_mmio_handler(Teletype::new(tty.clone()));
+    emu.set_mmio_handler(Clock::default());
+    emu.load_image(&prog.text, 0);
+    emu.reg_write_word(Reg::PC, prog.symbols.get("_start").unwrap().val);
+    emu.add_breakpoint(Breakpoint::mem(0o177566, WatchAccess::Write));
+
+    let start = Instant::now();
+    let mut times = Vec::new();
+    while let StopReason::Watchpoint(WatchHit { val, .. }) = emu.run().unwrap() {
+        if val != b'\n' as u16 {
+            let sim = Duration::from_nanos(emu.get_state().get_time_ns());
+            times.push((sim, start.elapsed()));
+        }
+    }
+    assert_eq!(times.len(), 9);
+
+    let interval = Duration::from_nanos(10 * 1_000_000_000 / 60);
+    for pair in times.windows(2) {
+        let [(sim0, _), (sim1, wall1)] = pair else {
+            unreachable!()
+        };
+        let sim = *sim1 - *sim0;
+        assert!(
+            sim.abs_diff(interval) < Duration::from_micros(100),
+            "{sim:?}"
+        );
+        // Pacing is only checked every ms.
+        assert!(
+            *wall1 + Duration::from_millis(1) >= *sim1,
+            "{wall1:?} < {sim1:?}"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use as_lib::assemble_raw;
+use common::asm::Reg;
+use emu_lib::io::clock::Clock;
+use emu_lib::{CpuModel, Emulator};
+
+fn run(asm: &str, model: CpuModel) -> Emulator {
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::with_model(model);
+    emu.set_mmio_handler(Clock::default());
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    emu
+}
+
+#[test]
+fn address_modes() {
+    let asm = r#"
+        . = 1000
+    _start:
+        mov #data, r0           ; Immediate source
+        mov r0, r1
+        add (r0)+, r1
+        cmp r1, r1              ; Z, so the branch isn't taken
+        bne _start
+        halt
+
+    data: .word 1
+    "#;
+    let emu = run(asm, CpuModel::Pdp1140);
+    assert_eq!(
+        emu.get_state().get_time_ns(),
+        (900 + 840) + 900 + (990 + 840) + 990 + 760 + 1800
+    );
+    let emu = run(asm, CpuModel::Pdp1170);
+    assert_eq!(
+        emu.get_state().get_time_ns(),
+        (300 + 300) + 300 + (300 + 300) + 300 + 300 + 1800
+    );
+}
+
+#[test]
+fn traps() {
+    // Each trap takes the trap time, on top of the instructions.
+    let asm = r#"
+        . = 34
+        .word handler, 0
+
+        . = 1000
+    _start:
+        mov #1000, sp
+        trap 0
+        halt
+
+    handler:
+        rti
+    "#;
+    let emu = run(asm, CpuModel::Pdp1170);
+    assert_eq!(
+        emu.get_state().get_time_ns(),
+        (300 + 300) + 1800 + 1350 + 1800
+    );
+}
+
+#[test]
+fn clock() {
+    // The clock ticks at 60 Hz of simulated time, however many instructions
+    // that takes.
+    let asm = r#"
+        LKS = 177546
+        LKS_INT_ENB = 100
+
+        . = 100
+        .word clock, 300
+
+        . = 1000
+    _start:
+        mov #1000, sp
+        clr r0
+        clr r1
+        mov #LKS_INT_ENB, @#LKS
+    loop:
+        inc r1
+        cmp #3, r0
+        bne loop
+        halt
+
+    clock:
+        tst @#LKS               ; Clear the clock bit
+        inc r0
+        rti
+    "#;
+    for model in [CpuModel::Pdp1140, CpuModel::Pdp1170] {
+        let emu = run(asm, model);
+        let time = emu.get_state().get_time_ns();
+        assert!(
+            (50_000_000..50_050_000).contains(&time),
+            "{time} on {model}"
+        );
+        // Three times as fast, so about three times as many iterations.
+        let iterations = emu.reg_read_word(Reg::R1) as u64;
+        let loop_ns = match model {
+            CpuModel::Pdp1140 => 990 + 990 + 840 + 880,
+            _ => 300 + 300 + 300 + 450,
+        };
+        assert!(
+            iterations.abs_diff(50_000_000 / loop_ns) < 20,
+            "{iterations} on {model}"
+        );
+    }
+}
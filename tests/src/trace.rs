@@ -0,0 +1,95 @@
+use as_lib::assemble_raw;
+use emu_lib::{Emulator, TraceFormat, Tracer};
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn trace(asm: &str, format: TraceFormat) -> Vec<String> {
+    let prog = assemble_raw(asm);
+    let buf = SharedBuf::default();
+    let mut emu = Emulator::new();
+    emu.set_tracer(Tracer::new(buf.clone(), format));
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    emu.clear_tracer();
+    let out = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+    out.lines().map(String::from).collect()
+}
+
+const PROG: &str = r#"
+    . = 1000
+_start:
+    mov #2000, r1
+    mov #5, r0
+    mov r0, (r1)+
+    add @#2000, 2(r1)
+    halt
+"#;
+
+#[test]
+fn native() {
+    let lines = trace(PROG, TraceFormat::Native);
+    assert_eq!(
+        lines,
+        [
+            "001000: 012701 002000        mov #0o2000, r1          R0=000000 R1=002000 R2=000000 R3=000000 R4=000000 R5=000000 SP=000000 PC=001004 PS=000000 EA=001002",
+            "001004: 012700 000005        mov #0o5, r0             R0=000005 R1=002000 R2=000000 R3=000000 R4=000000 R5=000000 SP=000000 PC=001010 PS=000000 EA=001006",
+            "001010: 010021               mov r0, (r1)+            R0=000005 R1=002002 R2=000000 R3=000000 R4=000000 R5=000000 SP=000000 PC=001012 PS=000000 EA=002000",
+            "001012: 063761 002000 000002 add @#0o2000, 0o2(r1)    R0=000005 R1=002002 R2=000000 R3=000000 R4=000000 R5=000000 SP=000000 PC=001020 PS=000000 EA=002000 EA=002004",
+            "001020: 000000               halt                     R0=000005 R1=002002 R2=000000 R3=000000 R4=000000 R5=000000 SP=000000 PC=001022 PS=000000",
+        ]
+    );
+}
+
+#[test]
+fn simh() {
+    // Registers are from before each instruction.
+    let lines = trace(PROG, TraceFormat::Simh);
+    assert_eq!(
+        lines,
+        [
+            "PC     PS     R0     R1     R2     R3     R4     R5     SP     SRC    DST    IR",
+            "",
+            "001000 000000 000000 000000 000000 000000 000000 000000 000000 001002        012701 MOV #2000,R1",
+            "001004 000000 000000 002000 000000 000000 000000 000000 000000 001006        012700 MOV #5,R0",
+            "001010 000000 000005 002000 000000 000000 000000 000000 000000        002000 010021 MOV R0,(R1)+",
+            "001012 000000 000005 002002 000000 000000 000000 000000 000000 002000 002004 063761 ADD @#2000,2(R1)",
+            "001020 000000 000005 002002 000000 000000 000000 000000 000000               000000 HALT",
+        ]
+    );
+}
+
+#[test]
+fn trap() {
+    // The record of an instruction that traps is before the trap is taken.
+    let asm = r#"
+        . = 4
+        .word bus_err, 340
+
+        . = 1000
+    _start:
+        mov #1000, sp
+        tst @#1
+    bus_err:
+        halt
+    "#;
+    let lines = trace(asm, TraceFormat::Native);
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("001004: 005737 000001        tst @#0o1"));
+    assert!(lines[1].contains("SP=001000 PC=001010 PS=000000 EA=000001"));
+    assert!(lines[2].starts_with("001010: 000000"));
+    assert!(lines[2].contains("SP=000774 PC=001012 PS=000340"));
+}
@@ -2,6 +2,7 @@ use as_lib::assemble_raw;
 use common::asm::Reg;
 use common::misc::ToU16P;
 use emu_lib::Emulator;
+use emu_lib::io::clock::FakeClock;
 
 // Assumes "proper" halt is last ins in binary
 fn run(asm: &str) -> Emulator {
@@ -236,3 +237,172 @@ fn trap_rti() {
     assert_eq!(emu.reg_read_word(Reg::R0), 0o1);
     assert_eq!(emu.reg_read_word(Reg::R1), 0o4);
 }
+
+#[test]
+fn reserved_instruction() {
+    let asm = r#"
+        STACK_TOP = 150000
+
+        . = 10
+        .word handler, 0
+
+        . = 400
+
+    _start:
+        mov #STACK_TOP, sp
+        clr r0
+    bad:
+        .word 77                ; Reserved
+        br done
+
+    handler:
+        mov (sp), r1            ; Saved PC
+        mov #bad, r2
+        inc r0
+        rti
+
+    done:
+        halt
+    "#;
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o1);
+    assert_eq!(emu.reg_read_word(Reg::R1), emu.reg_read_word(Reg::R2) + 2);
+}
+
+#[test]
+fn iot() {
+    let asm = r#"
+        STACK_TOP = 150000
+
+        . = 20
+        .word handler, 0
+
+        . = 400
+
+    _start:
+        mov #STACK_TOP, sp
+        clr r0
+        iot
+        br done
+
+    handler:
+        mov #3, r0
+        rti
+
+    done:
+        halt
+    "#;
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o3);
+}
+
+#[test]
+fn reset() {
+    let asm = r#"
+        STACK_TOP = 150000
+        LKS = 177546
+        SR0 = 177572
+        KPAR0 = 172340
+
+        . = 400
+
+    _start:
+        mov #STACK_TOP, sp
+        mov #100, @#LKS         ; Enable clock interrupts
+        mov #1234, @#KPAR0
+        mov #400, @#SR0         ; Maintenance mode, relocation not enabled
+        reset
+        mov @#LKS, r0
+        mov @#SR0, r1
+        mov @#KPAR0, r2
+        halt
+    "#;
+
+    let prog = assemble_raw(asm);
+    let mut emu = Emulator::new();
+    emu.set_mmio_handler(FakeClock::default());
+    emu.load_image(&prog.text, 0);
+    emu.run_at(prog.symbols.get("_start").unwrap().val);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0);
+    assert_eq!(emu.reg_read_word(Reg::R2), 0o1234);
+}
+
+#[test]
+fn trace() {
+    let asm = r#"
+        STACK_TOP = 150000
+
+        . = 14
+        .word handler, 0
+
+        . = 400
+
+    _start:
+        mov #STACK_TOP, sp
+        clr r0
+        mov #20, -(sp)          ; T bit
+        mov #traced, -(sp)
+        rti
+
+    handler:
+        inc r0
+        rtt
+
+    traced:
+        mov #1, r1
+        mov #2, r2
+        halt
+    "#;
+    let emu = run(asm);
+    // Once after the RTI, and once after each mov.
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o3);
+    assert_eq!(emu.reg_read_word(Reg::R1), 0o1);
+    assert_eq!(emu.reg_read_word(Reg::R2), 0o2);
+    assert!(emu.get_state().get_status().get_t());
+}
+
+#[test]
+fn bpt() {
+    let asm = r#"
+        STACK_TOP = 150000
+
+        . = 14
+        .word handler, 0
+
+        . = 400
+
+    _start:
+        mov #STACK_TOP, sp
+        clr r0
+        bpt
+        br done
+
+    handler:
+        mov #5, r0
+        rti
+
+    done:
+        halt
+    "#;
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0), 0o5);
+}
+
+#[test]
+fn psw_write_t() {
+    let asm = r#"
+        STACK_TOP = 150000
+        PSW = 177776
+
+        . = 400
+
+    _start:
+        mov #STACK_TOP, sp
+        mov #20, @#PSW
+        mov @#PSW, r0
+        halt
+    "#;
+    let emu = run(asm);
+    assert_eq!(emu.reg_read_word(Reg::R0) & 0o20, 0);
+}